@@ -10,7 +10,8 @@ use bitcoin::consensus::encode::serialize as btc_ser;
 use bitcoin::hashes::hex::{FromHex, ToHex};
 use bitcoin::hashes::Hash;
 use bitcoin::secp256k1::{self, ecdsa::Signature, Message, Secp256k1};
-use bitcoin::util::sighash::SighashCache;
+use bitcoin::util::schnorr::{SchnorrSig, TapTweak};
+use bitcoin::util::sighash::{Prevouts, SighashCache};
 use bitcoin::{PackedLockTime, PublicKey, Sequence};
 use elements::confidential;
 use elements::confidential::{Asset, Value};
@@ -130,6 +131,8 @@ impl BETransaction {
                     !(i.previous_output.vout == u32::max_value()
                         && i.previous_output.txid == elements::Txid::all_zeros())
                 })
+                // pegins spend mainchain outputs, which are not elements transactions
+                .filter(|i| !i.is_pegin)
                 .map(|i| i.previous_output.txid.into())
                 .collect(),
         }
@@ -325,6 +328,85 @@ impl BETransaction {
         Ok(())
     }
 
+    /// Add an output that is not blinded to `address`, confidential or not, elements only
+    pub fn add_explicit_output(
+        &mut self,
+        address: &str,
+        value: u64,
+        asset: elements::issuance::AssetId,
+        id: NetworkId,
+    ) -> Result<(), Error> {
+        match (self, id) {
+            (BETransaction::Elements(tx), NetworkId::Elements(net)) => {
+                let address = elements::Address::parse_with_params(address, net.address_params())
+                    .map_err(|_| Error::InvalidAddress)?;
+                tx.output.push(elements::TxOut {
+                    asset: confidential::Asset::Explicit(asset),
+                    value: confidential::Value::Explicit(value),
+                    script_pubkey: address.script_pubkey(),
+                    ..Default::default()
+                });
+                Ok(())
+            }
+            _ => Err(Error::Generic("explicit outputs are supported only in elements".into())),
+        }
+    }
+
+    /// Add an unspendable output destroying `value` of `asset`, elements only
+    pub fn add_burn_output(
+        &mut self,
+        value: u64,
+        asset: elements::issuance::AssetId,
+    ) -> Result<(), Error> {
+        match self {
+            BETransaction::Elements(tx) => {
+                let script_pubkey = elements::script::Builder::new()
+                    .push_opcode(elements::opcodes::all::OP_RETURN)
+                    .into_script();
+                tx.output.push(elements::TxOut {
+                    asset: confidential::Asset::Explicit(asset),
+                    value: confidential::Value::Explicit(value),
+                    script_pubkey,
+                    ..Default::default()
+                });
+                Ok(())
+            }
+            BETransaction::Bitcoin(_) => {
+                Err(Error::Generic("burn outputs are supported only in elements".into()))
+            }
+        }
+    }
+
+    /// Add an output sending `value` of `asset` to `script_pubkey` on the mainchain identified by
+    /// `genesis_hash`, elements only
+    pub fn add_pegout_output(
+        &mut self,
+        value: u64,
+        asset: elements::issuance::AssetId,
+        genesis_hash: bitcoin::BlockHash,
+        script_pubkey: &bitcoin::Script,
+    ) -> Result<(), Error> {
+        match self {
+            BETransaction::Elements(tx) => {
+                let script_pubkey = elements::script::Builder::new()
+                    .push_opcode(elements::opcodes::all::OP_RETURN)
+                    .push_slice(&genesis_hash[..])
+                    .push_slice(script_pubkey.as_bytes())
+                    .into_script();
+                tx.output.push(elements::TxOut {
+                    asset: confidential::Asset::Explicit(asset),
+                    value: confidential::Value::Explicit(value),
+                    script_pubkey,
+                    ..Default::default()
+                });
+                Ok(())
+            }
+            BETransaction::Bitcoin(_) => {
+                Err(Error::Generic("pegout outputs are supported only in elements".into()))
+            }
+        }
+    }
+
     pub fn scramble(&mut self) {
         let mut rng = thread_rng();
         match self {
@@ -369,7 +451,8 @@ impl BETransaction {
             }
             BETransaction::Elements(mut tx) => {
                 for input in tx.input.iter_mut() {
-                    let mut tx_wit = TxInWitness::default();
+                    // keep the issuance rangeproofs, if any
+                    let mut tx_wit = input.witness.clone();
                     tx_wit.script_witness = script_type.mock_witness().to_vec();
                     input.witness = tx_wit;
                     input.script_sig = script_type.mock_script_sig().into();
@@ -388,8 +471,12 @@ impl BETransaction {
                     tx.output.push(new_out);
                 }
 
+                // explicit outputs are not blinded and have no proofs
                 let proofs_size = (DEFAULT_RANGEPROOF_SIZE + DEFAULT_SURJECTIONPROOF_SIZE)
-                    * tx.output.iter().filter(|o| o.witness.is_empty()).count();
+                    * tx.output
+                        .iter()
+                        .filter(|o| o.witness.is_empty() && o.nonce.is_confidential())
+                        .count();
 
                 tx.output.push(elements::TxOut::new_fee(
                     0,
@@ -464,9 +551,11 @@ impl BETransaction {
             }
             Self::Elements(tx) => {
                 let policy_asset = policy_asset.expect("policy asset empty in elements");
+                let issued = issued_assets(tx);
                 let mut outputs: HashMap<elements::issuance::AssetId, u64> = HashMap::new();
                 for output in tx.output.iter() {
                     match (output.asset, output.value) {
+                        (Asset::Explicit(asset), _) if issued.contains(&asset) => {}
                         (Asset::Explicit(asset), Value::Explicit(value)) => {
                             *outputs.entry(asset.clone()).or_insert(0) += value;
                         }
@@ -537,10 +626,12 @@ impl BETransaction {
                 }
             }
             Self::Elements(tx) => {
+                let issued = issued_assets(tx);
                 let mut outputs_asset_amounts: HashMap<elements::issuance::AssetId, u64> =
                     HashMap::new();
                 for output in tx.output.iter() {
                     match (output.asset, output.value) {
+                        (Asset::Explicit(asset), _) if issued.contains(&asset) => {}
                         (Asset::Explicit(asset), Value::Explicit(value)) => {
                             *outputs_asset_amounts.entry(asset).or_insert(0) += value;
                         }
@@ -660,8 +751,14 @@ impl BETransaction {
                     Ok(tx.fee_in(policy_asset))
                 } else {
                     // while we are not filtering assets, the following holds for valid tx because
-                    // sum of input assets = sum of output assets
-                    let sum_outputs: u64 = tx.output.iter().map(|o| o.minimum_value()).sum();
+                    // sum of input assets = sum of output assets, excluding the issued assets
+                    let issued = issued_assets(tx);
+                    let sum_outputs: u64 = tx
+                        .output
+                        .iter()
+                        .filter(|o| !o.asset.explicit().map_or(false, |a| issued.contains(&a)))
+                        .map(|o| o.minimum_value())
+                        .sum();
                     let sum_inputs: u64 = tx
                         .input
                         .iter()
@@ -846,7 +943,8 @@ impl BETransaction {
     /// can be managed using gdk-rust. Implemented for Bitcoin only.
     ///
     /// The `hashcache` argument should be initialized as None for every tx and
-    /// reused for its inputs.
+    /// reused for its inputs. `prevouts` are required for taproot inputs only.
+    #[allow(clippy::too_many_arguments)]
     pub fn verify_input_sig<'a>(
         &'a self,
         secp: &Secp256k1<impl secp256k1::Verification>,
@@ -854,6 +952,7 @@ impl BETransaction {
         inv: usize,
         public_key: &PublicKey,
         value: u64,
+        prevouts: Option<&[bitcoin::TxOut]>,
         script_type: ScriptType,
     ) -> Result<(), Error> {
         let tx = if let BETransaction::Bitcoin(tx) = self {
@@ -862,8 +961,25 @@ impl BETransaction {
             // Signature verification is currently only used on Bitcoin
             unimplemented!();
         };
+        if script_type.is_taproot() {
+            // the taproot sighash commits to all the previous outputs
+            let prevouts = prevouts.ok_or(Error::InputValidationFailed)?;
+            let sig = tx.input[inv].witness.to_vec().first().cloned();
+            let sig = SchnorrSig::from_slice(&sig.ok_or(Error::InputValidationFailed)?)
+                .map_err(|_| Error::InputValidationFailed)?;
+            let hashcache = hashcache.get_or_insert_with(|| SighashCache::new(tx));
+            let hash = hashcache.taproot_key_spend_signature_hash(
+                inv,
+                &Prevouts::All(prevouts),
+                sig.hash_ty,
+            )?;
+            let message = Message::from_slice(&hash.into_inner()[..]).unwrap();
+            let (output_key, _) = public_key.inner.x_only_public_key().0.tap_tweak(secp, None);
+            secp.verify_schnorr(&sig.sig, &message, &output_key.to_inner())?;
+            return Ok(());
+        }
         let mut sig = match script_type {
-            ScriptType::P2wpkh | ScriptType::P2shP2wpkh => {
+            ScriptType::P2wpkh | ScriptType::P2shP2wpkh | ScriptType::P2tr => {
                 tx.input[inv].witness.to_vec().get(0).cloned().ok_or(Error::InputValidationFailed)
             }
             ScriptType::P2pkh => match tx.input[inv].script_sig.instructions().next() {
@@ -920,6 +1036,21 @@ fn mock_value() -> elements::secp256k1_zkp::PedersenCommitment {
     elements::secp256k1_zkp::PedersenCommitment::from_slice(&mock_value).unwrap()
 }
 
+/// The assets and reissuance tokens created by the issuances in the transaction inputs
+fn issued_assets(tx: &elements::Transaction) -> HashSet<elements::issuance::AssetId> {
+    let mut issued = HashSet::new();
+    for input in tx.input.iter().filter(|i| i.has_issuance()) {
+        let (asset_id, token_id) = input.issuance_ids();
+        if !input.asset_issuance.amount.is_null() {
+            issued.insert(asset_id);
+        }
+        if !input.asset_issuance.inflation_keys.is_null() {
+            issued.insert(token_id);
+        }
+    }
+    issued
+}
+
 fn sum_inputs(tx: &bitcoin::Transaction, all_txs: &BETransactions) -> u64 {
     tx.input
         .iter()
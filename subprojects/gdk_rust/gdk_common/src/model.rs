@@ -92,6 +92,12 @@ pub struct AddressAmount {
     pub satoshi: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub asset_id: Option<String>,
+    /// Whether `address` is a mainchain bitcoin address to peg out to, liquid only
+    #[serde(default)]
+    pub is_pegout: bool,
+    /// Whether the output is left unblinded, allowing unconfidential addresses, liquid only
+    #[serde(default)]
+    pub is_explicit: bool,
 }
 
 impl AddressAmount {
@@ -113,6 +119,20 @@ pub enum UtxoStrategy {
 
     /// Uses all and only the utxos specified by the caller
     Manual,
+
+    /// Look for a changeless selection with branch and bound,
+    /// falling back to largest first if none is found (Bitcoin only)
+    BranchAndBound,
+
+    /// Add utxos from the biggest to the smallest until the amounts and fees are covered
+    LargestFirst,
+
+    /// Add utxos from the oldest to the newest until the amounts and fees are covered
+    OldestFirst,
+
+    /// Choose the selection with the lowest waste metric among the other strategies,
+    /// using `fee_rate` and `long_term_fee_rate` (Bitcoin only)
+    MinimizeWaste,
 }
 
 impl Default for UtxoStrategy {
@@ -121,7 +141,7 @@ impl Default for UtxoStrategy {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct CreateTransaction {
     #[serde(default)]
     pub addressees: Vec<AddressAmount>,
@@ -132,6 +152,11 @@ pub struct CreateTransaction {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub previous_transaction: Option<TxListItem>,
+    /// The txid of an unconfirmed transaction with outputs we own to bump with a child paying
+    /// for the fees of both, so that the package reaches `fee_rate`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpfp_txid: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub memo: Option<String>,
     #[serde(default)]
@@ -143,6 +168,59 @@ pub struct CreateTransaction {
     pub confidential_utxos_only: bool,
     #[serde(default)]
     pub utxo_strategy: UtxoStrategy,
+    /// The fee rate we expect to pay in the long term to spend utxos, in satoshi/kbyte,
+    /// used to compute the waste metric
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_term_fee_rate: Option<u64>,
+    /// Liquid only, an asset issuance or reissuance to add to the transaction
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuance: Option<AssetIssuanceRequest>,
+    /// Liquid only, the asset amounts to destroy with provably unspendable outputs
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub burns: Vec<BurnAmount>,
+    /// Bitcoin only, the private keys in WIF, possibly BIP38 encrypted, whose funds are swept
+    /// into the subaccount
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub private_keys: Vec<String>,
+    /// The passphrase decrypting the BIP38 encrypted `private_keys`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
+}
+
+// Requests are logged, the private keys and their passphrase must not be
+impl fmt::Debug for CreateTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |secret: bool| {
+            if secret {
+                "REDACTED"
+            } else {
+                ""
+            }
+        };
+        f.debug_struct("CreateTransaction")
+            .field("addressees", &self.addressees)
+            .field("fee_rate", &self.fee_rate)
+            .field("subaccount", &self.subaccount)
+            .field("send_all", &self.send_all)
+            .field("previous_transaction", &self.previous_transaction)
+            .field("cpfp_txid", &self.cpfp_txid)
+            .field("memo", &self.memo)
+            .field("utxos", &self.utxos)
+            .field("num_confs", &self.num_confs)
+            .field("confidential_utxos_only", &self.confidential_utxos_only)
+            .field("utxo_strategy", &self.utxo_strategy)
+            .field("long_term_fee_rate", &self.long_term_fee_rate)
+            .field("issuance", &self.issuance)
+            .field("burns", &self.burns)
+            .field("private_keys", &redacted(!self.private_keys.is_empty()))
+            .field("passphrase", &redacted(self.passphrase.is_some()))
+            .finish()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -167,7 +245,105 @@ pub struct GetUnspentOpt {
     pub num_confs: Option<u32>,
     #[serde(rename = "confidential")]
     pub confidential_utxos_only: Option<bool>,
-    pub all_coins: Option<bool>, // unused
+    /// Include the frozen utxos
+    pub all_coins: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UtxoStatus {
+    /// Spendable by the coin selection
+    #[default]
+    Default,
+
+    /// Not spent unless explicitly selected with the `manual` utxo strategy
+    Frozen,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnspentOutputStatus {
+    pub txhash: String,
+    pub pt_idx: u32,
+    pub user_status: UtxoStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetUnspentOutputsStatusOpt {
+    pub list: Vec<UnspentOutputStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelType {
+    Tx,
+    Addr,
+    Pubkey,
+    Input,
+    Output,
+    Xpub,
+    /// Record types not defined by BIP329, ignored on import
+    #[serde(other)]
+    Unknown,
+}
+
+/// A wallet label record as defined by BIP329
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Bip329Label {
+    #[serde(rename = "type")]
+    pub label_type: LabelType,
+
+    /// The labelled item, e.g. a txid, an address or an outpoint in the `txid:vout` form
+    #[serde(rename = "ref")]
+    pub reference: String,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// The descriptor of the wallet the record belongs to
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+
+    /// Only for outputs, `false` if the output is frozen
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<bool>,
+}
+
+impl Bip329Label {
+    /// Parse the records of a JSON Lines export, blank lines are ignored
+    pub fn from_jsonl(jsonl: &str) -> Result<Vec<Self>, serde_json::Error> {
+        jsonl.lines().filter(|l| !l.trim().is_empty()).map(serde_json::from_str).collect()
+    }
+
+    /// Serialize the records in the JSON Lines format, one record per line
+    pub fn to_jsonl(labels: &[Self]) -> Result<String, serde_json::Error> {
+        let mut jsonl = String::new();
+        for label in labels {
+            jsonl.push_str(&serde_json::to_string(label)?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportLabelsOpt {
+    /// BIP329 records in the JSON Lines format
+    pub labels: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportLabelsResult {
+    /// Number of records applied to the wallet, unsupported records are skipped
+    pub imported: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportLabelsResult {
+    /// BIP329 records in the JSON Lines format
+    pub labels: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -213,6 +389,9 @@ pub struct DiscoverAccountOpt {
     #[serde(rename = "type")]
     pub script_type: ScriptType,
     pub xpub: ExtendedPubKey,
+    /// Number of addresses to check, None for the default
+    #[serde(default)]
+    pub gap_limit: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -225,6 +404,39 @@ pub struct GetAccountPathResult {
     pub path: Vec<ChildNumber>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetSubaccountDescriptorsOpt {
+    pub subaccount: u32,
+}
+
+/// Descriptor in the format expected by Bitcoin Core `importdescriptors`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CoreImportDescriptor {
+    pub desc: String,
+    pub active: bool,
+    pub internal: bool,
+    /// Always 0, so that Core rescans the whole chain
+    pub timestamp: u32,
+}
+
+/// BIP388 wallet policy
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WalletPolicy {
+    pub name: String,
+    pub descriptor_template: String,
+    pub keys_info: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubaccountDescriptors {
+    /// External (receive) descriptor with key origin and checksum
+    pub external: String,
+    /// Internal (change) descriptor with key origin and checksum
+    pub internal: String,
+    pub core_import: Vec<CoreImportDescriptor>,
+    pub wallet_policy: WalletPolicy,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetNextAccountOpt {
     #[serde(rename = "type")]
@@ -336,6 +548,25 @@ pub struct TransactionMeta {
     pub transaction_outputs: Vec<TransactionOutput>,
 }
 
+/// A request to an external signer to sign the inputs of a transaction
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignTransactionRequest {
+    /// The transaction to sign, already blinded on Liquid
+    pub transaction: String,
+
+    /// The utxos spent by the transaction inputs, in the same order, with the full derivation
+    /// path and the sighash to use. Inputs with `skip_signing` must not be signed.
+    pub used_utxos: Vec<UnspentOutput>,
+}
+
+/// The response of an external signer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignTransactionResponse {
+    /// One signature per input in hex, empty for the inputs not signed. ECDSA signatures are DER
+    /// encoded followed by the sighash byte, taproot signatures are 64 bytes Schnorr signatures.
+    pub signatures: Vec<String>,
+}
+
 impl From<BETransaction> for TransactionMeta {
     fn from(transaction: BETransaction) -> Self {
         let txid = transaction.txid().to_string();
@@ -643,6 +874,23 @@ pub struct Credentials {
     pub bip39_passphrase: String,
 }
 
+/// Credentials of a watch-only wallet, one account for each key or descriptor
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WatchOnlyCredentials {
+    /// Account extended public keys, the script type is implied by the SLIP-132 version
+    #[serde(default)]
+    pub slip132_extended_pubkeys: Vec<String>,
+
+    /// Account descriptors, e.g. `wpkh([d34db33f/84'/0'/0']xpub.../0/*)` or `sh(wpkh(xpub...))`,
+    /// on Liquid also ELIP-150 descriptors like `ct(slip77(...),elwpkh(xpub...))`
+    #[serde(default)]
+    pub core_descriptors: Vec<String>,
+
+    /// Liquid only, needed to unblind the outputs unless given by the descriptors
+    #[serde(default)]
+    pub master_blinding_key: Option<MasterBlindingKey>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AddressPointer {
     pub subaccount: u32,
@@ -701,6 +949,9 @@ impl Settings {
 pub struct AccountSettings {
     pub name: String,
     pub hidden: bool,
+    /// Number of consecutive unused addresses scanned, None for the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gap_limit: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -708,6 +959,8 @@ pub struct UpdateAccountOpt {
     pub subaccount: u32,
     pub name: Option<String>,
     pub hidden: Option<bool>,
+    #[serde(default)]
+    pub gap_limit: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -856,6 +1109,11 @@ pub struct UnspentOutput {
     pub public_key: String,
     #[serde(default)]
     pub skip_signing: bool,
+    #[serde(default)]
+    pub user_status: UtxoStatus,
+    /// The BIP329 label of the output, empty if none
+    #[serde(default)]
+    pub label: String,
 
     // liquid fields
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -919,6 +1177,8 @@ impl TryFrom<Txo> for UnspentOutput {
             sequence: txo.sequence,
             sighash: None,
             skip_signing: false,
+            user_status: UtxoStatus::Default,
+            label: String::new(),
             confidential,
             asset_id,
             asset_blinder,
@@ -1025,6 +1285,10 @@ pub struct PreviousAddress {
     /// this address.
     pub tx_count: u32,
 
+    /// The BIP329 label of the address, empty if none
+    #[serde(default)]
+    pub label: String,
+
     // Liquid fields, None if Bitcoin
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_blinded: Option<bool>,
@@ -1058,6 +1322,28 @@ pub struct PsbtGetDetailsParams {
     pub utxos: Vec<UnspentOutput>,
 }
 
+/// Object returned by `ElectrumSession::create_psbt`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePsbtResult {
+    /// Base64 encoded Bitcoin PSBT
+    pub psbt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignPsbtParams {
+    /// Base64 encoded Bitcoin PSBT
+    pub psbt: String,
+    pub subaccount: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignPsbtResult {
+    /// Base64 encoded Bitcoin PSBT
+    pub psbt: String,
+    /// Number of inputs signed by the subaccount
+    pub signed_inputs: usize,
+}
+
 /// Object returned by [`ElectrumSession::psbt_get_details`].
 #[derive(Debug, Clone, Serialize)]
 pub struct PsbtGetDetailsResult {
@@ -1091,9 +1377,216 @@ impl PsbtGetDetailsOut {
     }
 }
 
+/// An asset issuance, or a reissuance if `reissue_asset_id` is set
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AssetIssuanceRequest {
+    /// The amount of asset to issue, can be 0 for a new issuance creating only tokens
+    pub asset_amount: u64,
+    /// The amount of reissuance tokens to create, new issuances only
+    #[serde(default)]
+    pub token_amount: u64,
+    /// Where to send the issued asset, a new wallet address if missing
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_address: Option<String>,
+    /// Where to send the reissuance tokens, a new wallet address if missing
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_address: Option<String>,
+    /// The registry contract whose hash is committed in the issuance, new issuances only
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract: Option<serde_json::Value>,
+    /// The asset to reissue, spending one of its reissuance tokens
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reissue_asset_id: Option<String>,
+    /// Whether to blind the issued amounts
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BurnAmount {
+    pub asset_id: String,
+    pub satoshi: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateIssuanceOpt {
+    pub subaccount: u32,
+    pub fee_rate: Option<u64>,
+    /// The utxos to spend, all the subaccount utxos if empty
+    #[serde(default)]
+    pub utxos: CreateTxUtxos,
+    pub asset_amount: u64,
+    #[serde(default)]
+    pub token_amount: u64,
+    pub asset_address: Option<String>,
+    pub token_address: Option<String>,
+    pub contract: Option<serde_json::Value>,
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateReissuanceOpt {
+    pub subaccount: u32,
+    pub fee_rate: Option<u64>,
+    /// The utxos to spend, all the subaccount utxos if empty
+    #[serde(default)]
+    pub utxos: CreateTxUtxos,
+    pub asset_id: String,
+    pub asset_amount: u64,
+    pub asset_address: Option<String>,
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateBurnOpt {
+    pub subaccount: u32,
+    pub fee_rate: Option<u64>,
+    /// The utxos to spend, all the subaccount utxos if empty
+    #[serde(default)]
+    pub utxos: CreateTxUtxos,
+    pub asset_id: String,
+    pub satoshi: u64,
+}
+
+/// Object returned by `ElectrumSession::create_issuance` and `create_reissuance`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IssuanceTransaction {
+    #[serde(flatten)]
+    pub transaction: TransactionMeta,
+    pub asset_id: String,
+    /// None for reissuances
+    pub token_id: Option<String>,
+    pub asset_entropy: String,
+    /// None for reissuances and issuances without a contract
+    pub contract_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetPeginAddressOpt {
+    pub subaccount: u32,
+}
+
+/// Object returned by `ElectrumSession::get_pegin_address`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PeginAddress {
+    /// The bitcoin address to send the coins to peg in
+    pub mainchain_address: String,
+    /// The hex of the script needed to claim the pegin, committed in `mainchain_address`
+    pub claim_script: String,
+    /// The liquid address of `claim_script`
+    pub address: String,
+    pub pointer: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreatePeginClaimOpt {
+    pub subaccount: u32,
+    pub fee_rate: Option<u64>,
+    /// The hex of the bitcoin transaction sending to the `mainchain_address`
+    pub bitcoin_tx: String,
+    /// The hex of the proof of inclusion of `bitcoin_tx`, as returned by `gettxoutproof`
+    pub txout_proof: String,
+    pub claim_script: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateSwapProposalOpt {
+    pub subaccount: u32,
+    /// The utxo offered in the swap, spent entirely
+    pub utxo: CreateTxUtxo,
+    /// The asset requested in exchange
+    pub receive_asset_id: String,
+    pub receive_satoshi: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompleteSwapOpt {
+    pub subaccount: u32,
+    /// The hex of the PSET returned by `create_swap_proposal`
+    pub psbt_hex: String,
+    pub fee_rate: Option<u64>,
+    /// The utxos that can be spent to pay the requested asset and the fee, all the subaccount
+    /// utxos if empty
+    #[serde(default)]
+    pub utxos: CreateTxUtxos,
+}
+
+/// Object returned by `ElectrumSession::create_swap_proposal` and `complete_swap`
+///
+/// Amounts are from the point of view of the wallet creating the object.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SwapTransaction {
+    pub psbt_hex: String,
+    pub send_asset_id: String,
+    pub send_satoshi: u64,
+    pub receive_asset_id: String,
+    pub receive_satoshi: u64,
+    /// The hex of the final transaction, None for proposals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<String>,
+    /// The fee paid by the wallet, zero for proposals
+    pub fee: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetUnblindedDataOpt {
+    pub txid: String,
+    /// Unblinded values of outputs not of the wallet, as a `#blinded=` fragment or an explorer
+    /// URL containing it, for instance provided by the sender of a transaction
+    #[serde(default)]
+    pub blinded: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UnblindedOutput {
+    pub vout: u32,
+    pub asset_id: String,
+    pub satoshi: u64,
+    #[serde(rename = "assetblinder")]
+    pub asset_blinder: String,
+    #[serde(rename = "amountblinder")]
+    pub amount_blinder: String,
+    /// Whether the output is of the wallet, otherwise it was unblinded with the given data
+    pub is_relevant: bool,
+}
+
+/// Object returned by `ElectrumSession::get_unblinded_data`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnblindedData {
+    pub txid: String,
+    pub outputs: Vec<UnblindedOutput>,
+    /// The fragment to append to the transaction URL of a Liquid explorer to show the outputs
+    pub url_fragment: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignMessageOpt {
+    /// An address of the wallet, in any subaccount
+    pub address: String,
+    pub message: String,
+    /// Produce a BIP322 simple signature instead of a legacy BIP137 one, always the case for
+    /// taproot addresses and supported only for native segwit ones
+    #[serde(default)]
+    pub bip322: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignMessageResult {
+    /// The signature in base64
+    pub signature: String,
+}
+
 #[cfg(test)]
 mod test {
-    use crate::model::{parse_path, CreateTxUtxos, GetUnspentOutputs};
+    use crate::model::{
+        parse_path, Bip329Label, CreateTransaction, CreateTxUtxos, GetUnspentOutputs, LabelType,
+    };
     use bitcoin::util::bip32::DerivationPath;
 
     #[test]
@@ -1110,4 +1603,40 @@ mod test {
         let _json: GetUnspentOutputs = serde_json::from_str(json_str).unwrap();
         let _json: CreateTxUtxos = serde_json::from_str(json_str).unwrap();
     }
+
+    #[test]
+    fn test_create_transaction_redacted() {
+        let request = CreateTransaction {
+            private_keys: vec!["cVt4o7BGAig1UXywgGSmARhxMdzP5qvQsxKkSsc1XEkw3tDTQFpy".into()],
+            passphrase: Some("TestingOneTwoThree".into()),
+            ..Default::default()
+        };
+        let debug = format!("{:?}", request);
+        assert!(!debug.contains("cVt4o7"));
+        assert!(!debug.contains("TestingOneTwoThree"));
+        assert!(debug.contains("private_keys: \"REDACTED\""));
+    }
+
+    #[test]
+    fn test_bip329() {
+        let jsonl = r#"{"type":"tx","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd","label":"Transaction","origin":"wpkh([d34db33f/84'/0'/0'])"}
+{"type":"addr","ref":"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c","label":"Address"}
+
+{"type":"output","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:1","label":"Output","spendable":false}
+{"type":"unknown_type","ref":"foo","label":"bar"}
+"#;
+        let labels = Bip329Label::from_jsonl(jsonl).unwrap();
+        assert_eq!(labels.len(), 4);
+        assert_eq!(labels[0].label_type, LabelType::Tx);
+        assert_eq!(labels[0].origin.as_deref(), Some("wpkh([d34db33f/84'/0'/0'])"));
+        assert_eq!(labels[1].spendable, None);
+        assert_eq!(labels[2].spendable, Some(false));
+        assert_eq!(labels[3].label_type, LabelType::Unknown);
+
+        let exported = Bip329Label::to_jsonl(&labels[..3]).unwrap();
+        assert_eq!(exported.lines().count(), 3);
+        assert_eq!(Bip329Label::from_jsonl(&exported).unwrap(), labels[..3]);
+
+        assert!(Bip329Label::from_jsonl("{\"type\":\"tx\"}").is_err());
+    }
 }
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::str::FromStr;
 use std::time::Duration;
 
@@ -25,8 +26,22 @@ pub struct NetworkParameters {
     pub electrum_tls: Option<bool>,
     pub electrum_url: Option<String>,
     pub electrum_onion_url: Option<String>,
+    /// More electrum servers to fail over to, in order of preference, as `<host>:<port>:<t|s>`
+    pub electrum_servers: Option<Vec<String>>,
+    /// Whether to fail over also to the builtin lists of public servers, bitcoin only
+    pub electrum_fallback: Option<bool>,
+    /// The base url of an Esplora REST API, if set it's used instead of the electrum server
+    pub esplora_url: Option<String>,
+    /// The url of a bitcoind JSON-RPC server, without credentials, used by rpc sessions
+    pub rpc_url: Option<String>,
+    /// The credentials of the bitcoind JSON-RPC server
+    pub rpc_credentials: Option<RpcCredentials>,
+    /// The name of the bitcoind watch-only wallet of rpc sessions, `gdk` if missing
+    pub rpc_wallet: Option<String>,
     pub validate_domain: Option<bool>,
     pub policy_asset: Option<String>,
+    /// The hex of the federation script controlling the two-way peg, liquid only
+    pub fedpeg_script: Option<String>,
     pub sync_interval: Option<u32>,
     pub spv_enabled: Option<bool>,
     asset_registry_url: Option<String>,
@@ -38,6 +53,10 @@ pub struct NetworkParameters {
 
     pub spv_multi: Option<bool>,
     pub spv_servers: Option<Vec<String>>,
+    /// A trusted block to start the headers chain from instead of the genesis block, bitcoin only
+    pub spv_checkpoint: Option<SpvCheckpoint>,
+    /// A trusted dynafed epoch start to start the headers chain from, liquid only
+    pub spv_liquid_anchor: Option<SpvLiquidAnchor>,
 
     pub proxy: Option<String>,
     pub use_tor: Option<bool>,
@@ -55,7 +74,43 @@ pub struct NetworkParameters {
     pub state_dir: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A block trusted to be in the best chain, at a difficulty retarget height
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SpvCheckpoint {
+    pub height: u32,
+    /// The hex of the block header
+    pub header: String,
+    /// The hex of the total work of the chain up to the block included, as `chainwork` of
+    /// bitcoind `getblockheader`
+    pub chainwork: String,
+}
+
+/// A block trusted to be in the best chain, at a dynafed epoch start
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SpvLiquidAnchor {
+    pub height: u32,
+    /// The hex of the signblockscript of the dynafed params in use from the block
+    pub signblockscript: String,
+}
+
+/// The user and password of a bitcoind JSON-RPC server, as in its cookie file
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RpcCredentials {
+    pub user: String,
+    pub password: String,
+}
+
+// Network parameters are logged, the password must not be
+impl fmt::Debug for RpcCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RpcCredentials")
+            .field("user", &self.user)
+            .field("password", &"REDACTED")
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ElementsNetwork {
     Liquid,
     LiquidTestnet,
@@ -99,6 +154,15 @@ impl ElementsNetwork {
             ElementsNetwork::ElementsRegtest => &elements::AddressParams::ELEMENTS,
         }
     }
+
+    /// The bitcoin network the two-way peg of this network is anchored to
+    pub fn mainchain_network(self: ElementsNetwork) -> bitcoin::Network {
+        match self {
+            ElementsNetwork::Liquid => bitcoin::Network::Bitcoin,
+            ElementsNetwork::LiquidTestnet => bitcoin::Network::Testnet,
+            ElementsNetwork::ElementsRegtest => bitcoin::Network::Regtest,
+        }
+    }
 }
 
 impl NetworkParameters {
@@ -122,6 +186,17 @@ impl NetworkParameters {
         }
     }
 
+    /// The federation script of the two-way peg, elements regtest defaults to `OP_TRUE`
+    pub fn fedpeg_script(&self) -> Result<bitcoin::Script, Error> {
+        match (self.fedpeg_script.as_ref(), self.id()) {
+            (Some(script), NetworkId::Elements(_)) => Ok(bitcoin::Script::from_str(script)?),
+            (None, NetworkId::Elements(ElementsNetwork::ElementsRegtest)) => {
+                Ok(bitcoin::Script::from(vec![0x51]))
+            }
+            _ => Err("no fedpeg script".to_string().into()),
+        }
+    }
+
     pub fn use_tor(&self) -> bool {
         self.use_tor.unwrap_or(false)
     }
@@ -82,6 +82,19 @@ pub struct BlockNotification {
     pub previous_hash: bitcoin::BlockHash,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReorgNotification {
+    /// The height of the last block still in the best chain.
+    pub fork_height: u32,
+
+    /// The wallet transactions confirmed after the fork, to be verified again.
+    #[serde(rename = "txhashes")]
+    pub txids: Vec<bitcoin::Txid>,
+
+    /// The wallet subaccounts the transactions affect.
+    pub subaccounts: Vec<u32>,
+}
+
 impl Notification {
     pub fn new_network(current: State, next: State) -> Self {
         Notification {
@@ -182,6 +195,16 @@ impl NativeNotif {
         self.notify(Notification::new_transaction(ntf));
     }
 
+    pub fn gap_limit_exceeded(&self, subaccount: u32, gap_limit: u32) {
+        let data = json!({"gap_limit":{"subaccount":subaccount,"gap_limit":gap_limit},"event":"gap_limit"});
+        self.notify(data);
+    }
+
+    pub fn reorg(&self, ntf: &ReorgNotification) {
+        let data = json!({"reorg":ntf,"event":"reorg"});
+        self.notify(data);
+    }
+
     pub fn network(&self, current: State, desired: State) {
         self.notify(Notification::new_network(current, desired));
     }
@@ -219,7 +242,7 @@ impl NativeNotif {
 #[cfg(test)]
 mod test {
     use bitcoin::hashes::Hash;
-    use elements::bitcoin::BlockHash;
+    use elements::bitcoin::{BlockHash, Txid};
 
     use super::*;
     use crate::State;
@@ -254,4 +277,15 @@ mod test {
         };
         assert_eq!(expected, serde_json::to_value(&obj).unwrap());
     }
+
+    #[test]
+    fn test_reorg_json() {
+        let expected = json!({"fork_height":10,"txhashes":["0000000000000000000000000000000000000000000000000000000000000000"],"subaccounts":[0,1]});
+        let obj = ReorgNotification {
+            fork_height: 10,
+            txids: vec![Txid::all_zeros()],
+            subaccounts: vec![0, 1],
+        };
+        assert_eq!(expected, serde_json::to_value(&obj).unwrap());
+    }
 }
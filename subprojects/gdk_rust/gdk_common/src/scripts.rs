@@ -15,9 +15,12 @@ pub enum ScriptType {
     P2wpkh = 1,
     #[serde(rename = "p2pkh")]
     P2pkh = 2,
+    #[serde(rename = "p2tr")]
+    P2tr = 3,
 }
 
-const TYPES: [ScriptType; 3] = [ScriptType::P2shP2wpkh, ScriptType::P2wpkh, ScriptType::P2pkh];
+const TYPES: [ScriptType; 4] =
+    [ScriptType::P2shP2wpkh, ScriptType::P2wpkh, ScriptType::P2pkh, ScriptType::P2tr];
 
 impl fmt::Display for ScriptType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -25,6 +28,7 @@ impl fmt::Display for ScriptType {
             Self::P2shP2wpkh => write!(f, "p2sh-p2wpkh"),
             Self::P2wpkh => write!(f, "p2wpkh"),
             Self::P2pkh => write!(f, "p2pkh"),
+            Self::P2tr => write!(f, "p2tr"),
         }
     }
 }
@@ -57,15 +61,23 @@ impl ScriptType {
         self as u32
     }
 
+    /// Whether the script type is segwit v0, taproot is not included since it uses a
+    /// different signature hash and signature algorithm
     pub fn is_segwit(self) -> bool {
         matches!(self, ScriptType::P2wpkh | ScriptType::P2shP2wpkh)
     }
 
+    pub fn is_taproot(self) -> bool {
+        matches!(self, ScriptType::P2tr)
+    }
+
     /// Returns a mock witness with the expected size
     pub fn mock_witness(self) -> Witness {
         Witness::from_vec(match self {
             // signature (72) + compressed public key (33)
             ScriptType::P2wpkh | ScriptType::P2shP2wpkh => vec![vec![0u8; 72], vec![0u8; 33]],
+            // key path spend schnorr signature (64) with the default sighash
+            ScriptType::P2tr => vec![vec![0u8; 64]],
             // empty for non-witness inputs
             ScriptType::P2pkh => vec![],
         })
@@ -75,7 +87,7 @@ impl ScriptType {
     pub fn mock_script_sig(self) -> Vec<u8> {
         match self {
             // empty for native segwit
-            ScriptType::P2wpkh => vec![],
+            ScriptType::P2wpkh | ScriptType::P2tr => vec![],
             // OP_PUSHBYTES <22 bytes>
             ScriptType::P2shP2wpkh => vec![0u8; 23],
             // OP_PUSHBYTES <72 bytes sig> OP_PUSHBYTES <33 bytes compressed key>
@@ -92,6 +104,8 @@ impl ScriptType {
             ScriptType::P2shP2wpkh => vec![0u8; 23],
             // OP_DUP OP_HASH160 OP_PUSHBYTES <20 bytes hash> OP_EQUALVERIFY OP_CHECKSIG
             ScriptType::P2pkh => vec![0u8; 25],
+            // OP_1 OP_PUSHBYTES <32 bytes x-only output key>
+            ScriptType::P2tr => vec![0u8; 34],
         }
     }
 }
@@ -17,6 +17,13 @@ pub const WALLY_TX_FLAG_USE_WITNESS: u32 = 0x1;
 #[allow(unused)]
 pub const WALLY_TX_FLAG_USE_ELEMENTS: u32 = 0x2;
 
+/// Indicate that the BIP38 key is for the mainnet.
+pub const BIP38_KEY_MAINNET: u32 = 0;
+/// Indicate that the BIP38 key is for the testnet.
+pub const BIP38_KEY_TESTNET: u32 = 7;
+/// Indicate that the BIP38 key is for a compressed public key.
+pub const BIP38_KEY_COMPRESSED: u32 = 256;
+
 #[repr(C)]
 #[allow(unused)]
 pub struct WallyTx {
@@ -131,4 +138,25 @@ extern "C" {
         len: size_t,
     ) -> c_int;
 
+    //WALLY_CORE_API int bip38_get_flags(
+    //    const char *bip38,
+    //    size_t *written);
+    pub fn bip38_get_flags(bip38: *const c_char, written: *mut size_t) -> c_int;
+
+    //WALLY_CORE_API int bip38_to_private_key(
+    //    const char *bip38,
+    //    const unsigned char *pass,
+    //    size_t pass_len,
+    //    uint32_t flags,
+    //    unsigned char *bytes_out,
+    //    size_t len);
+    pub fn bip38_to_private_key(
+        bip38: *const c_char,
+        pass: *const c_uchar,
+        pass_len: size_t,
+        flags: u32,
+        bytes_out: *mut c_uchar,
+        len: size_t,
+    ) -> c_int;
+
 }
@@ -161,6 +161,42 @@ pub fn pbkdf2_hmac_sha512_256(password: Vec<u8>, salt: Vec<u8>, cost: u32) -> [u
     out
 }
 
+/// Decrypt a non EC-multiplied BIP38 private key with `passphrase`, returning the key and whether
+/// its public key is compressed, `None` if the key is invalid or the passphrase is wrong.
+pub fn bip38_to_private_key(
+    bip38: &str,
+    passphrase: &str,
+    network: bitcoin::Network,
+) -> Option<(secp256k1::SecretKey, bool)> {
+    let network_flag = match network {
+        bitcoin::Network::Bitcoin => ffi::BIP38_KEY_MAINNET,
+        _ => ffi::BIP38_KEY_TESTNET,
+    };
+    let c_bip38 = make_str(bip38);
+    let mut flags = 0usize;
+    let mut out = [0u8; 32];
+    let ret = unsafe {
+        let mut ret = ffi::bip38_get_flags(c_bip38, &mut flags);
+        if ret == ffi::WALLY_OK {
+            ret = ffi::bip38_to_private_key(
+                c_bip38,
+                passphrase.as_ptr(),
+                passphrase.len(),
+                network_flag,
+                out.as_mut_ptr(),
+                out.len(),
+            );
+        }
+        let _ = CString::from_raw(c_bip38);
+        ret
+    };
+    if ret != ffi::WALLY_OK {
+        return None;
+    }
+    let compressed = flags as u32 & ffi::BIP38_KEY_COMPRESSED != 0;
+    secp256k1::SecretKey::from_slice(&out).ok().map(|key| (key, compressed))
+}
+
 pub fn make_str<'a, S: Into<Cow<'a, str>>>(data: S) -> *mut c_char {
     CString::new(data.into().into_owned()).unwrap().into_raw()
 }
@@ -236,6 +272,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bip38_to_private_key() {
+        // test vectors from the BIP spec, no compression and compression without EC multiply
+        let vectors = [
+            (
+                "6PRVWUbkzzsbcVac2qwfssoUJAN1Xhrg6bNk8J7Nzm5H7kxEbn2Nh2ZoGg",
+                "5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR",
+            ),
+            (
+                "6PYNKZ1EAgYgmQfmNVamxyXVWHzK5s6DGhwP4J5o44cvXdoY7sRzhtpUeo",
+                "L44B5gGEpqEDRS9vVPz7QT35jcBG2r3CZwSwQ4fCewXAhAhqGVpP",
+            ),
+        ];
+        for (bip38, wif) in vectors.iter() {
+            let expected = bitcoin::PrivateKey::from_wif(wif).unwrap();
+            let (key, compressed) =
+                bip38_to_private_key(bip38, "TestingOneTwoThree", bitcoin::Network::Bitcoin)
+                    .unwrap();
+            assert_eq!(key, expected.inner);
+            assert_eq!(compressed, expected.compressed);
+            assert!(bip38_to_private_key(bip38, "wrong", bitcoin::Network::Bitcoin).is_none());
+        }
+    }
+
     #[test]
     fn test_master_blinding_key_serde() {
         let m = MasterBlindingKey((0..64).collect::<Vec<_>>().try_into().unwrap());
@@ -3,9 +3,17 @@ use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::str::FromStr;
 
-use bitcoin::util::sighash::SighashCache;
+use bitcoin::secp256k1::KeyPair;
+use bitcoin::util::bip32::KeySource;
+use bitcoin::util::merkleblock::MerkleBlock;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::util::schnorr::SchnorrSig;
+use bitcoin::util::schnorr::TapTweak;
+use bitcoin::util::sighash::{Prevouts, SighashCache};
+use bitcoin::{EcdsaSig, EcdsaSighashType, SchnorrSighashType};
 use log::{info, warn};
 
+use bitcoin::blockdata::constants::genesis_block;
 use bitcoin::blockdata::script;
 use bitcoin::hashes::hex::{FromHex, ToHex};
 use bitcoin::hashes::Hash;
@@ -14,6 +22,8 @@ use bitcoin::util::address::Payload;
 use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey};
 use bitcoin::{PublicKey, Witness};
 use elements::confidential::Value;
+use elements::secp256k1_zkp::ZERO_TWEAK;
+use elements::{AssetIssuance, ContractHash};
 
 use gdk_common::be::{
     BEAddress, BEOutPoint, BEScript, BEScriptConvert, BESigHashType, BETransaction, BETxid,
@@ -21,10 +31,13 @@ use gdk_common::be::{
 };
 use gdk_common::error::fn_err;
 use gdk_common::model::{
-    parse_path, AccountInfo, AddressAmount, AddressPointer, CreateTransaction,
-    GetPreviousAddressesOpt, GetTransactionsOpt, GetTxInOut, PreviousAddress, PreviousAddresses,
-    SPVVerifyTxResult, TransactionMeta, TransactionOutput, TxListItem, Txo, UnspentOutput,
-    UpdateAccountOpt, UtxoStrategy,
+    parse_path, AccountInfo, AddressAmount, AddressPointer, AssetIssuanceRequest, CompleteSwapOpt,
+    CoreImportDescriptor, CreatePeginClaimOpt, CreateSwapProposalOpt, CreateTransaction,
+    CreateTxUtxos, GetPreviousAddressesOpt, GetTransactionsOpt, GetTxInOut, PeginAddress,
+    PreviousAddress, PreviousAddresses, SPVVerifyTxResult, SignMessageOpt, SignMessageResult,
+    SignTransactionRequest, SubaccountDescriptors, SwapTransaction, TransactionMeta,
+    TransactionOutput, TxListItem, Txo, UnspentOutput, UpdateAccountOpt, UtxoStrategy,
+    WalletPolicy,
 };
 use gdk_common::scripts::{p2pkh_script, p2shwpkh_script_sig, ScriptType};
 use gdk_common::util::{now, weight_to_vsize};
@@ -33,14 +46,25 @@ use gdk_common::wally::{
 };
 use gdk_common::{ElementsNetwork, NetworkId, NetworkParameters};
 
+use crate::backend::BackendUrl;
+use crate::coin_selection::{self, Candidate, OutputGroup};
+use crate::descriptor::descriptor_checksum;
 use crate::error::Error;
-use crate::interface::ElectrumUrl;
-use crate::store::{Store, BATCH_SIZE};
+use crate::issuance::{self, MAX_ISSUANCE_AMOUNT};
+use crate::message;
+use crate::peg;
+use crate::signer::Signer;
+use crate::store::{RawAccountCache, Store, DEFAULT_GAP_LIMIT, MAX_GAP_LIMIT};
+use crate::swap;
+use crate::sweep;
 
 // The number of account types, including these reserved for future use.
 // Currently only 3 are used: P2SH-P2WPKH, P2WPKH and P2PKH
 const NUM_RESERVED_ACCOUNT_TYPES: u32 = 16;
 
+/// Long term fee rate in satoshi/kbyte used for the waste metric if not specified
+const DEFAULT_LONG_TERM_FEE_RATE: u64 = 10_000;
+
 #[derive(Clone)]
 pub struct Account {
     account_num: u32,
@@ -60,6 +84,9 @@ pub struct Account {
     master_blinding: Option<MasterBlindingKey>,
 
     path: DerivationPath,
+    /// The master fingerprint and the derivation path of the account xpub, None if unknown as
+    /// for some watch-only accounts
+    key_origin: Option<KeySource>,
 }
 
 /// Compare xpub ignoring the fingerprint (which computation might be skipped),
@@ -75,10 +102,12 @@ pub fn xpubs_equivalent(xpub1: &ExtendedPubKey, xpub2: &ExtendedPubKey) -> Resul
 }
 
 impl Account {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         network: NetworkParameters,
         master_xprv: &Option<ExtendedPrivKey>,
         account_xpub: &Option<ExtendedPubKey>,
+        key_origin: Option<KeySource>,
         master_blinding: Option<MasterBlindingKey>,
         store: Store,
         account_num: u32,
@@ -120,6 +149,7 @@ impl Account {
             store,
             master_blinding,
             path,
+            key_origin,
         })
     }
 
@@ -127,20 +157,75 @@ impl Account {
         self.account_num
     }
 
-    fn descriptor(&self, is_internal: bool) -> String {
-        let internal_idx = if is_internal {
-            1
-        } else {
-            0
-        };
-        let (prefix, suffix) = match self.script_type {
-            ScriptType::P2shP2wpkh => ("sh(wpkh", ")"),
-            ScriptType::P2wpkh => ("wpkh", ""),
-            ScriptType::P2pkh => ("pkh", ""),
+    pub fn xpub(&self) -> &ExtendedPubKey {
+        &self.xpub
+    }
+
+    /// The account xpub, prefixed by its key origin if known
+    fn key_info(&self) -> String {
+        match &self.key_origin {
+            Some((fingerprint, path)) => format!(
+                "[{}{}]{}",
+                fingerprint,
+                path.to_string().trim_start_matches('m'),
+                self.xpub
+            ),
+            None => self.xpub.to_string(),
+        }
+    }
+
+    /// The key origin of the key at `account_path` of this account, if known
+    fn key_source(&self, account_path: &DerivationPath) -> Option<KeySource> {
+        self.key_origin
+            .as_ref()
+            .map(|(fingerprint, path)| (*fingerprint, path.extend(account_path)))
+    }
+
+    fn descriptor_with_key(&self, key: &str) -> String {
+        descriptor_with_key(self.script_type, key)
+    }
+
+    /// The descriptor of the external or internal chain, with key origin and checksum
+    pub(crate) fn descriptor(&self, is_internal: bool) -> Result<String, Error> {
+        let key = format!("{}/{}/*", self.key_info(), is_internal as u32);
+        let desc = self.descriptor_with_key(&key);
+        let checksum = descriptor_checksum(&desc)?;
+        Ok(format!("{}#{}", desc, checksum))
+    }
+
+    /// Return the account descriptors, also in the Bitcoin Core and BIP388 formats
+    pub fn descriptors(&self) -> Result<SubaccountDescriptors, Error> {
+        if self.network.liquid {
+            return Err(Error::Generic("descriptors are not supported on Liquid".into()));
+        }
+        let external = self.descriptor(false)?;
+        let internal = self.descriptor(true)?;
+        let core_import = vec![
+            CoreImportDescriptor {
+                desc: external.clone(),
+                active: true,
+                internal: false,
+                timestamp: 0,
+            },
+            CoreImportDescriptor {
+                desc: internal.clone(),
+                active: true,
+                internal: true,
+                timestamp: 0,
+            },
+        ];
+        let settings = self.store.read()?.get_account_settings(self.account_num).cloned();
+        let wallet_policy = WalletPolicy {
+            name: settings.unwrap_or_default().name,
+            descriptor_template: self.descriptor_with_key("@0/**"),
+            keys_info: vec![self.key_info()],
         };
-        // TODO: add key origin identification
-        // TODO: add checksum
-        format!("{}({}/{}/*){}", prefix, self.xpub, internal_idx, suffix)
+        Ok(SubaccountDescriptors {
+            external,
+            internal,
+            core_import,
+            wallet_policy,
+        })
     }
 
     fn slip132_extended_pubkey(&self) -> Option<String> {
@@ -149,12 +234,13 @@ impl Account {
         } else {
             let mut xpub_bytes = self.xpub.encode();
             let slip132_version = match (self.network.mainnet, self.script_type) {
-                (true, ScriptType::P2pkh) => [0x04, 0x88, 0xb2, 0x1e], // xpub
-                (true, ScriptType::P2shP2wpkh) => [0x04, 0x9d, 0x7c, 0xb2], // ypub
-                (true, ScriptType::P2wpkh) => [0x04, 0xb2, 0x47, 0x46], // zpub
-                (false, ScriptType::P2pkh) => [0x04, 0x35, 0x87, 0xcf], // tpub
-                (false, ScriptType::P2shP2wpkh) => [0x04, 0x4a, 0x52, 0x62], // upub
-                (false, ScriptType::P2wpkh) => [0x04, 0x5f, 0x1c, 0xf6], // vpub
+                // there is no SLIP-132 version for taproot, use the standard one
+                (true, ScriptType::P2pkh | ScriptType::P2tr) => [0x04, 0x88, 0xb2, 0x1e], // xpub
+                (true, ScriptType::P2shP2wpkh) => [0x04, 0x9d, 0x7c, 0xb2],               // ypub
+                (true, ScriptType::P2wpkh) => [0x04, 0xb2, 0x47, 0x46],                   // zpub
+                (false, ScriptType::P2pkh | ScriptType::P2tr) => [0x04, 0x35, 0x87, 0xcf], // tpub
+                (false, ScriptType::P2shP2wpkh) => [0x04, 0x4a, 0x52, 0x62],              // upub
+                (false, ScriptType::P2wpkh) => [0x04, 0x5f, 0x1c, 0xf6],                  // vpub
             };
             xpub_bytes[0..4].copy_from_slice(&slip132_version[0..4]);
             Some(bitcoin::util::base58::check_encode_slice(&xpub_bytes))
@@ -182,7 +268,7 @@ impl Account {
             receiving_id: "".to_string(),
             bip44_discovered: self.has_transactions()?,
             user_path: self.path.clone().into(),
-            core_descriptors: vec![self.descriptor(false), self.descriptor(true)],
+            core_descriptors: vec![self.descriptor(false)?, self.descriptor(true)?],
             slip132_extended_pubkey: self.slip132_extended_pubkey(),
         })
     }
@@ -197,10 +283,34 @@ impl Account {
         if let Some(hidden) = opt.hidden {
             settings.hidden = hidden;
         }
+        if let Some(gap_limit) = opt.gap_limit {
+            if !(1..=MAX_GAP_LIMIT).contains(&gap_limit) {
+                return Err(Error::InvalidGapLimit(MAX_GAP_LIMIT));
+            }
+            settings.gap_limit = Some(gap_limit);
+        }
         store_write.set_account_settings(self.account_num, settings)?;
         Ok(true)
     }
 
+    /// Number of consecutive unused addresses scanned by sync
+    pub fn gap_limit(&self) -> Result<u32, Error> {
+        let store_read = self.store.read()?;
+        let settings = store_read.get_account_settings(self.account_num);
+        Ok(settings.and_then(|s| s.gap_limit).unwrap_or(DEFAULT_GAP_LIMIT))
+    }
+
+    /// Number of addresses handed out after the last used one
+    pub fn unused_addresses(&self, is_internal: bool) -> Result<u32, Error> {
+        let store_read = self.store.read()?;
+        let acc_store = store_read.account_cache(self.account_num)?;
+        Ok(if is_internal {
+            acc_store.indexes.internal.saturating_sub(acc_store.last_used.internal)
+        } else {
+            acc_store.indexes.external.saturating_sub(acc_store.last_used.external)
+        })
+    }
+
     pub fn set_name(&self, name: &str) -> Result<bool, Error> {
         self.set_settings(UpdateAccountOpt {
             name: Some(name.into()),
@@ -290,6 +400,11 @@ impl Account {
                 Some(_pubkey) => Some(script_pubkey.to_hex()),
             };
             let tx_count = acc_store.all_txs.tx_count(&script_pubkey);
+            let label = store
+                .get_address_label(&address.to_string())
+                .or_else(|| store.get_address_label(unblinded_address.as_ref()?))
+                .cloned()
+                .unwrap_or_default();
             previous_addresses.push(PreviousAddress {
                 address: address.to_string(),
                 address_type: self.script_type.to_string(),
@@ -299,6 +414,7 @@ impl Account {
                 script_pubkey: script_pubkey.to_hex(),
                 user_path: self.get_full_path(&account_path).into(),
                 tx_count,
+                label,
                 is_blinded,
                 unblinded_address,
                 blinding_script: blinding_script_hex,
@@ -316,6 +432,7 @@ impl Account {
     }
 
     pub fn list_tx(&self, opt: &GetTransactionsOpt) -> Result<Vec<TxListItem>, Error> {
+        let unspents = self.unspents()?;
         let store = self.store.read()?;
         let acc_store = store.account_cache(self.account_num)?;
 
@@ -376,6 +493,9 @@ impl Account {
 
             let rbf_optin = tx.rbf_optin();
             let can_rbf = height.is_none() && rbf_optin && user_signed;
+            let can_cpfp = height.is_none()
+                && !self.network.liquid
+                && unspents.iter().any(|o| &o.txid() == *tx_id);
 
             let inputs = tx
                 .previous_outputs()
@@ -511,7 +631,7 @@ impl Account {
                 txhash: tx_id.to_string(),
                 satoshi,
                 rbf_optin,
-                can_cpfp: false,
+                can_cpfp,
                 can_rbf,
                 spv_verified: spv_verified.to_string(),
                 fee,
@@ -683,6 +803,538 @@ impl Account {
         create_tx(self, request)
     }
 
+    /// Create a transaction sweeping `utxos`, owned by the private keys in the request, to a new
+    /// address of this subaccount
+    pub fn create_sweep_tx(
+        &self,
+        request: &mut CreateTransaction,
+        utxos: Vec<UnspentOutput>,
+    ) -> Result<TransactionMeta, Error> {
+        if request.subaccount != self.account_num {
+            return Err(Error::InvalidSubaccount(request.subaccount));
+        }
+        if self.network.liquid {
+            return Err(Error::InvalidSweepRequest("supported only on bitcoin".into()));
+        }
+        if utxos.is_empty() {
+            return Err(Error::InvalidSweepRequest("no funds to sweep".into()));
+        }
+        let fee_rate = request_fee_rate(&self.network, request)?;
+
+        let mut tx = BETransaction::new(self.network.id());
+        for utxo in utxos.iter() {
+            let txid = bitcoin::Txid::from_hex(&utxo.txhash)?;
+            tx.add_input(BEOutPoint::new_bitcoin(txid, utxo.pt_idx));
+        }
+        let address = self.get_next_address(false)?.address;
+        tx.add_output(&address, 0, None, self.network.id())?;
+        let total = utxos.iter().map(|u| u.satoshi).sum::<u64>();
+        let mut fee = 0;
+        if let BETransaction::Bitcoin(ref mut tx) = tx {
+            fee = sweep::estimated_fee(tx, &utxos, fee_rate);
+            tx.output[0].value = total
+                .checked_sub(fee)
+                .filter(|s| *s > DUST_VALUE)
+                .ok_or(Error::InsufficientFunds)?;
+        }
+        let satoshi = total - fee;
+        info!("created sweep tx of {} with fee {}", satoshi, fee);
+
+        let tx_outputs = self.tx_outputs(&tx)?;
+        let mut used_utxos = utxos;
+        for (utxo, (sequence, _)) in used_utxos.iter_mut().zip(tx.previous_sequence_and_outpoints())
+        {
+            utxo.sequence = Some(sequence);
+        }
+        let mut balances = HashMap::new();
+        balances.insert("btc".to_string(), satoshi as i64);
+        let mut created_tx = TransactionMeta::new(
+            tx,
+            None,
+            None,
+            balances,
+            fee,
+            self.network.id().get_bitcoin_network().unwrap_or(bitcoin::Network::Bitcoin),
+            "incoming".to_string(),
+            request.clone(),
+            SPVVerifyTxResult::InProgress,
+        );
+        created_tx.is_sweep = true;
+        created_tx.used_utxos = used_utxos;
+        created_tx.transaction_outputs = tx_outputs;
+        created_tx.addressees_read_only = true;
+        Ok(created_tx)
+    }
+
+    /// Sign `opt.message` with the key of `opt.address`, which must belong to this subaccount
+    pub fn sign_message(&self, opt: &SignMessageOpt) -> Result<SignMessageResult, Error> {
+        let xprv = self
+            .xprv
+            .ok_or_else(|| Error::Generic("Internal software signing is not supported".into()))?;
+        if self.network.liquid {
+            return Err(Error::InvalidMessageRequest("supported only on bitcoin".into()));
+        }
+        let script_pubkey = bitcoin::Address::from_str(&opt.address)
+            .map_err(|_| Error::InvalidAddress)?
+            .script_pubkey();
+        let path = {
+            let store_read = self.store.read()?;
+            let acc_store = store_read.account_cache(self.account_num)?;
+            acc_store.get_path(&BEScript::Bitcoin(script_pubkey.clone()))?.clone()
+        };
+        let private_key = xprv.derive_priv(&crate::EC, &path)?.to_priv();
+
+        let signature = if opt.bip322 || self.script_type.is_taproot() {
+            if !matches!(self.script_type, ScriptType::P2wpkh | ScriptType::P2tr) {
+                return Err(Error::InvalidMessageRequest(
+                    "BIP322 simple signatures require native segwit addresses".into(),
+                ));
+            }
+            let to_spend = message::to_spend(&script_pubkey, &opt.message);
+            let to_sign = message::to_sign(&to_spend, Witness::default());
+            let sighash = BESigHashType::from_u32(EcdsaSighashType::All as u32, false)?;
+            let (public_key, signature) = bitcoin_signature(
+                &to_sign,
+                0,
+                &private_key,
+                &to_spend.output,
+                self.script_type,
+                &sighash,
+            )?;
+            let (_, witness) = prepare_input(&public_key, signature, self.script_type);
+            message::encode_bip322_simple(witness)
+        } else {
+            message::sign_bip137(&private_key, self.script_type, &opt.message)?
+        };
+        Ok(SignMessageResult {
+            signature,
+        })
+    }
+
+    /// Sign a sweep transaction with the private keys in the request
+    pub fn sign_sweep(&self, request: &TransactionMeta) -> Result<TransactionMeta, Error> {
+        info!("sign sweep");
+        let create_tx = request
+            .create_transaction
+            .as_ref()
+            .ok_or_else(|| Error::Generic("Cannot sign without tx data".into()))?;
+        let network = self
+            .network
+            .id()
+            .get_bitcoin_network()
+            .ok_or_else(|| Error::InvalidSweepRequest("supported only on bitcoin".into()))?;
+        let keys = create_tx
+            .private_keys
+            .iter()
+            .map(|k| sweep::decode_private_key(k, create_tx.passphrase.as_deref(), network))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tx: bitcoin::Transaction =
+            bitcoin::consensus::deserialize(&Vec::<u8>::from_hex(&request.hex)?)?;
+        if request.used_utxos.len() != tx.input.len() {
+            return Err(Error::Generic("Mismatching used_utxos and transaction".into()));
+        }
+
+        let mut inputs = vec![];
+        for utxo in request.used_utxos.iter() {
+            let (key, script_type, prevout) = sweep::sweep_key(&keys, utxo).ok_or_else(|| {
+                Error::InvalidSweepRequest(format!("missing the key of {}", utxo.public_key))
+            })?;
+            inputs.push((key, script_type, prevout));
+        }
+        let prevouts = inputs.iter().map(|(_, _, prevout)| prevout.clone()).collect::<Vec<_>>();
+        let unsigned_tx = tx.clone();
+        for (i, (key, script_type, _)) in inputs.iter().enumerate() {
+            let sighash = request.used_utxos[i].sighash().map_err(|_| Error::InvalidSigHash)?;
+            let (public_key, signature) =
+                bitcoin_signature(&unsigned_tx, i, key, &prevouts, *script_type, &sighash)?;
+            let (script_sig, witness) = prepare_input(&public_key, signature, *script_type);
+            tx.input[i].script_sig = script_sig;
+            tx.input[i].witness = Witness::from_vec(witness);
+        }
+        let mut betx: TransactionMeta = BETransaction::Bitcoin(tx).into();
+        betx.is_sweep = true;
+        self.finalize_signed_tx(request, betx)
+    }
+
+    /// A new mainchain address to peg in to this subaccount, with the claim script it commits to
+    pub fn get_pegin_address(&self) -> Result<PeginAddress, Error> {
+        let net = self.network.id().get_elements_network().ok_or_else(|| {
+            Error::InvalidPegRequest("pegins are supported only on liquid".into())
+        })?;
+        let fedpeg_script = self.network.fedpeg_script()?;
+        let address = self.get_next_address(false)?;
+        let claim_script =
+            self.derive_address(false, address.pointer)?.script_pubkey().into_elements();
+        let mainchain_address = peg::mainchain_address(
+            &fedpeg_script,
+            claim_script.as_bytes(),
+            net.mainchain_network(),
+        )?;
+        Ok(PeginAddress {
+            mainchain_address: mainchain_address.to_string(),
+            claim_script: claim_script.to_hex(),
+            address: address.address,
+            pointer: address.pointer,
+        })
+    }
+
+    /// Create and sign a transaction claiming a pegin to an address returned by
+    /// `get_pegin_address`, the pegged in amount minus the fee goes to a change address
+    pub fn create_pegin_claim(&self, opt: &CreatePeginClaimOpt) -> Result<TransactionMeta, Error> {
+        let net = self.network.id().get_elements_network().ok_or_else(|| {
+            Error::InvalidPegRequest("pegins are supported only on liquid".into())
+        })?;
+        let xprv = self
+            .xprv
+            .ok_or_else(|| Error::Generic("Internal software signing is not supported".into()))?;
+        let fee_rate_sat_kb = opt.fee_rate.unwrap_or(100);
+        if fee_rate_sat_kb < 100 {
+            return Err(Error::FeeRateBelowMinimum(100));
+        }
+
+        let claim_script = elements::Script::from(Vec::<u8>::from_hex(&opt.claim_script)?);
+        let derivation_path = {
+            let store_read = self.store.read()?;
+            let acc_store = store_read.account_cache(self.account_num)?;
+            acc_store.get_path(&claim_script.clone().into()).cloned().map_err(|_| {
+                Error::InvalidPegRequest("the claim script is not of this subaccount".into())
+            })?
+        };
+        let bitcoin_tx: bitcoin::Transaction =
+            bitcoin::consensus::deserialize(&Vec::<u8>::from_hex(&opt.bitcoin_tx)?)?;
+        let proof: MerkleBlock =
+            bitcoin::consensus::deserialize(&Vec::<u8>::from_hex(&opt.txout_proof)?)?;
+        let fedpeg_script = self.network.fedpeg_script()?;
+        let (vout, value) =
+            peg::find_pegin_output(&bitcoin_tx, &proof, &fedpeg_script, claim_script.as_bytes())?;
+
+        let policy_asset = self.network.policy_asset_id()?;
+        let genesis_hash = genesis_block(net.mainchain_network()).block_hash();
+        let input = peg::pegin_input(
+            &bitcoin_tx,
+            &proof,
+            vout,
+            claim_script.as_bytes(),
+            policy_asset,
+            genesis_hash,
+        );
+        let mut tx = BETransaction::Elements(elements::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![input],
+            output: vec![],
+        });
+        let change = self.get_next_address(true)?;
+        tx.add_output(&change.address, value, Some(policy_asset), self.network.id())?;
+        let fee = tx.estimated_fee(fee_rate_sat_kb as f64 / 1000.0, 0, self.script_type);
+        let to_claim =
+            value.checked_sub(fee).filter(|v| *v > DUST_VALUE).ok_or(Error::InsufficientFunds)?;
+        let mut tx = match tx {
+            BETransaction::Elements(mut tx) => {
+                tx.output[0].value = Value::Explicit(to_claim);
+                tx.output.push(elements::TxOut::new_fee(fee, policy_asset));
+                tx
+            }
+            BETransaction::Bitcoin(_) => unreachable!(),
+        };
+
+        let pegin_secrets = elements::TxOutSecrets::new(
+            policy_asset,
+            elements::confidential::AssetBlindingFactor::zero(),
+            value,
+            elements::confidential::ValueBlindingFactor::zero(),
+        );
+        tx.blind(&mut rand::thread_rng(), &crate::EC, &[pegin_secrets], false)?;
+
+        let sighash = BESigHashType::from_u32(elements::EcdsaSigHashType::All as u32, true)?;
+        let (script_sig, witness) = internal_sign_elements(
+            &tx,
+            0,
+            &xprv,
+            &derivation_path,
+            Value::Explicit(value),
+            self.script_type,
+            &sighash,
+        )?;
+        tx.input[0].script_sig = script_sig;
+        tx.input[0].witness.script_witness = witness;
+
+        let mut satoshi = HashMap::new();
+        satoshi.insert(policy_asset.to_string(), to_claim as i64);
+        Ok(TransactionMeta::new(
+            BETransaction::Elements(tx),
+            None,
+            None,
+            satoshi,
+            fee,
+            bitcoin::Network::Bitcoin,
+            "incoming".to_string(),
+            CreateTransaction {
+                subaccount: self.account_num,
+                fee_rate: Some(fee_rate_sat_kb),
+                ..Default::default()
+            },
+            SPVVerifyTxResult::InProgress,
+        ))
+    }
+
+    /// Create a PSET offering the utxo `opt.utxo` in exchange of an amount of another asset
+    ///
+    /// The input is signed with SIGHASH_SINGLE|ANYONECANPAY, committing only to the output
+    /// receiving the requested asset, so a counterparty can complete it with `complete_swap`.
+    pub fn create_swap_proposal(
+        &self,
+        opt: &CreateSwapProposalOpt,
+    ) -> Result<SwapTransaction, Error> {
+        if !self.network.liquid {
+            return Err(Error::InvalidSwap("supported only on liquid".into()));
+        }
+        let xprv = self
+            .xprv
+            .ok_or_else(|| Error::Generic("Internal software signing is not supported".into()))?;
+        let receive_asset = elements::AssetId::from_str(&opt.receive_asset_id)
+            .map_err(|_| Error::InvalidAssetId)?;
+        if opt.receive_satoshi == 0 {
+            return Err(Error::InvalidAmount);
+        }
+        let outpoint = opt.utxo.outpoint(self.network.id())?;
+        if !self.unspents()?.contains(&outpoint) {
+            return Err(Error::InvalidSwap(format!("utxo {:?} is not spendable", outpoint)));
+        }
+        let (mut input, secrets, path) = self.swap_input(&outpoint)?;
+        if secrets.asset == receive_asset {
+            return Err(Error::InvalidSwap("cannot swap an asset with itself".into()));
+        }
+
+        let mut pset = elements::pset::PartiallySignedTransaction::new_v2();
+        input.sighash_type = Some(swap::proposal_sighash());
+        swap::set_input_secrets(&mut input, &secrets);
+        pset.add_input(input);
+        let mut output = self.new_pset_output(false, opt.receive_satoshi, receive_asset)?;
+        output.blinder_index = Some(0);
+        pset.add_output(output);
+        swap::blind_proposal(&mut pset, &secrets)?;
+        sign_pset_inputs(
+            &mut pset,
+            &[(0, path)],
+            &xprv,
+            self.script_type,
+            elements::EcdsaSigHashType::SinglePlusAnyoneCanPay,
+        )?;
+
+        Ok(SwapTransaction {
+            psbt_hex: elements::encode::serialize(&pset).to_hex(),
+            send_asset_id: secrets.asset.to_string(),
+            send_satoshi: secrets.value,
+            receive_asset_id: receive_asset.to_string(),
+            receive_satoshi: opt.receive_satoshi,
+            transaction: None,
+            fee: 0,
+        })
+    }
+
+    /// Complete the swap proposal `opt.psbt_hex`, paying the requested asset and the fee with
+    /// `utxos` and receiving the offered utxo, returning the final PSET and transaction
+    pub fn complete_swap(
+        &self,
+        opt: &CompleteSwapOpt,
+        utxos: &CreateTxUtxos,
+    ) -> Result<SwapTransaction, Error> {
+        if !self.network.liquid {
+            return Err(Error::InvalidSwap("supported only on liquid".into()));
+        }
+        let xprv = self
+            .xprv
+            .ok_or_else(|| Error::Generic("Internal software signing is not supported".into()))?;
+        let fee_rate_sat_kb = opt.fee_rate.unwrap_or(100);
+        if fee_rate_sat_kb < 100 {
+            return Err(Error::FeeRateBelowMinimum(100));
+        }
+        let mut pset: elements::pset::PartiallySignedTransaction =
+            elements::encode::deserialize(&Vec::<u8>::from_hex(&opt.psbt_hex)?)?;
+        let (offered, (pay_asset, pay_satoshi)) = swap::check_proposal(&pset)?;
+        let policy_asset = self.network.policy_asset_id()?;
+
+        let unspents = self.unspents()?;
+        let offered_input = &pset.inputs()[0];
+        let offered_outpoint = BEOutPoint::Elements(elements::OutPoint::new(
+            offered_input.previous_txid,
+            offered_input.previous_output_index,
+        ));
+        let mut candidates = vec![];
+        for utxo in utxos.values().flatten() {
+            let outpoint = utxo.outpoint(self.network.id())?;
+            if outpoint == offered_outpoint || !unspents.contains(&outpoint) {
+                continue;
+            }
+            let candidate = self.swap_input(&outpoint)?;
+            if candidate.1.asset == pay_asset || candidate.1.asset == policy_asset {
+                candidates.push(candidate);
+            }
+        }
+        // spend the largest utxos first
+        candidates.sort_by_key(|c| std::cmp::Reverse(c.1.value));
+
+        let selected_value = |selected: &[usize], asset| -> u64 {
+            selected
+                .iter()
+                .map(|i| &candidates[*i].1)
+                .filter(|s| s.asset == asset)
+                .map(|s| s.value)
+                .sum()
+        };
+        let needed_value = |asset, fee| -> u64 {
+            let mut value = 0;
+            if asset == pay_asset {
+                value += pay_satoshi;
+            }
+            if asset == policy_asset {
+                value += fee;
+            }
+            value
+        };
+        let more_changes = if pay_asset == policy_asset {
+            1
+        } else {
+            2
+        };
+        let mut receive = self.new_pset_output(false, offered.value, offered.asset)?;
+        let mut selected: Vec<usize> = vec![];
+        let mut fee = loop {
+            let mut template = pset.clone();
+            for i in selected.iter() {
+                template.add_input(candidates[*i].0.clone());
+            }
+            template.add_output(receive.clone());
+            let fee = BETransaction::Elements(template.extract_tx()?).estimated_fee(
+                fee_rate_sat_kb as f64 / 1000.0,
+                more_changes,
+                self.script_type,
+            );
+            let missing = [pay_asset, policy_asset]
+                .iter()
+                .find(|a| selected_value(&selected, **a) < needed_value(**a, fee))
+                .cloned();
+            match missing {
+                None => break fee,
+                Some(asset) => {
+                    let next = (0..candidates.len())
+                        .find(|i| !selected.contains(i) && candidates[*i].1.asset == asset)
+                        .ok_or(Error::InsufficientFunds)?;
+                    selected.push(next);
+                }
+            }
+        };
+
+        let mut changes = vec![];
+        let mut change_assets = vec![pay_asset];
+        if pay_asset != policy_asset {
+            change_assets.push(policy_asset);
+        }
+        for asset in change_assets {
+            let change = selected_value(&selected, asset) - needed_value(asset, fee);
+            if asset == policy_asset && change <= DUST_VALUE {
+                fee += change;
+            } else if change > 0 {
+                changes.push((asset, change));
+            }
+        }
+
+        let first_input = pset.inputs().len();
+        let mut inp_txout_sec = HashMap::new();
+        inp_txout_sec.insert(0, offered);
+        let mut paths = vec![];
+        for (n, i) in selected.iter().enumerate() {
+            let (input, secrets, path) = candidates[*i].clone();
+            pset.add_input(input);
+            inp_txout_sec.insert(first_input + n, secrets);
+            paths.push((first_input + n, path));
+        }
+        receive.blinder_index = Some(first_input as u32);
+        pset.add_output(receive);
+        for (asset, satoshi) in changes {
+            let mut change = self.new_pset_output(true, satoshi, asset)?;
+            change.blinder_index = Some(first_input as u32);
+            pset.add_output(change);
+        }
+        pset.add_output(elements::pset::Output::new_explicit(
+            elements::Script::new(),
+            fee,
+            policy_asset,
+            None,
+        ));
+
+        // the offered input is already accounted for in the scalar of the proposal and the
+        // requested output is already blinded, but it must be proven against all the inputs
+        pset.global.scalars.push(swap::input_offset(&offered));
+        pset.outputs_mut()[0].blinder_index = None;
+        pset.blind_last(&mut rand::thread_rng(), &crate::EC, &inp_txout_sec)?;
+        pset.outputs_mut()[0].blinder_index = Some(0);
+        swap::prove_requested_output(&mut pset, &inp_txout_sec)?;
+
+        sign_pset_inputs(
+            &mut pset,
+            &paths,
+            &xprv,
+            self.script_type,
+            elements::EcdsaSigHashType::All,
+        )?;
+        let tx = pset.extract_tx()?;
+
+        Ok(SwapTransaction {
+            psbt_hex: elements::encode::serialize(&pset).to_hex(),
+            send_asset_id: pay_asset.to_string(),
+            send_satoshi: pay_satoshi,
+            receive_asset_id: offered.asset.to_string(),
+            receive_satoshi: offered.value,
+            transaction: Some(elements::encode::serialize(&tx).to_hex()),
+            fee,
+        })
+    }
+
+    /// The PSET input spending the utxo at `outpoint`, with its unblinded values and the path
+    /// deriving its key
+    fn swap_input(
+        &self,
+        outpoint: &BEOutPoint,
+    ) -> Result<(elements::pset::Input, elements::TxOutSecrets, DerivationPath), Error> {
+        let outpoint = match outpoint {
+            BEOutPoint::Elements(outpoint) => *outpoint,
+            BEOutPoint::Bitcoin(_) => {
+                return Err(Error::InvalidSwap("supported only on liquid".into()))
+            }
+        };
+        let store_read = self.store.read()?;
+        let acc_store = store_read.account_cache(self.account_num)?;
+        let secrets = acc_store
+            .unblinded
+            .get(&outpoint)
+            .cloned()
+            .ok_or_else(|| Error::InvalidSwap(format!("cannot unblind utxo {}", outpoint)))?;
+        let txout = acc_store.get_liquid_tx(&outpoint.txid)?.output[outpoint.vout as usize].clone();
+        let path = acc_store.get_path(&txout.script_pubkey.clone().into())?.clone();
+        let mut input = elements::pset::Input::from_prevout(outpoint);
+        input.witness_utxo = Some(txout);
+        Ok((input, secrets, path))
+    }
+
+    /// A PSET output paying `satoshi` of `asset` to a new address of the subaccount
+    fn new_pset_output(
+        &self,
+        is_internal: bool,
+        satoshi: u64,
+        asset: elements::AssetId,
+    ) -> Result<elements::pset::Output, Error> {
+        let pointer = self.get_next_address(is_internal)?.pointer;
+        let address = self.derive_address(is_internal, pointer)?;
+        Ok(elements::pset::Output::new_explicit(
+            address.script_pubkey().into_elements(),
+            satoshi,
+            asset,
+            address.blinding_pubkey().map(PublicKey::new),
+        ))
+    }
+
     // TODO when we can serialize psbt
     //pub fn sign(&self, psbt: PartiallySignedTransaction) -> Result<PartiallySignedTransaction, Error> { Err(Error::Generic("NotImplemented".to_string())) }
     pub fn sign(&self, request: &TransactionMeta) -> Result<TransactionMeta, Error> {
@@ -706,9 +1358,10 @@ impl Account {
             return Err(Error::Generic("Mismatching used_utxos and transaction".into()));
         }
 
-        let mut betx: TransactionMeta = match be_tx {
+        let betx: TransactionMeta = match be_tx {
             BETransaction::Bitcoin(tx) => {
                 let mut out_tx = tx.clone();
+                let prevouts = self.bitcoin_prevouts(&tx, &request.used_utxos, acc_store)?;
 
                 for i in 0..tx.input.len() {
                     if request.used_utxos[i].skip_signing {
@@ -716,8 +1369,7 @@ impl Account {
                     }
                     let prev_output = tx.input[i].previous_output;
                     info!("input#{} prev_output:{:?}", i, prev_output);
-                    let prev_tx = acc_store.get_bitcoin_tx(&prev_output.txid)?;
-                    let out = prev_tx.output[prev_output.vout as usize].clone();
+                    let out = prevouts[i].clone();
                     let derivation_path = acc_store.get_path(&out.script_pubkey.into())?;
                     info!(
                         "input#{} prev_output:{:?} derivation_path:{:?}",
@@ -729,7 +1381,7 @@ impl Account {
                         i,
                         &xprv,
                         &derivation_path,
-                        out.value,
+                        &prevouts,
                         self.script_type,
                         &sighashes[i],
                     )?;
@@ -787,82 +1439,368 @@ impl Account {
             }
         };
 
-        betx.fee = request.fee;
-        betx.create_transaction = request.create_transaction.clone();
-        betx.used_utxos = request.used_utxos.clone();
-
         drop(acc_store);
         drop(store_read);
-        let mut store_write = self.store.write()?;
-        let mut acc_store = store_write.account_cache_mut(self.account_num)?;
+        self.finalize_signed_tx(request, betx)
+    }
 
-        let changes_used = request.changes_used.unwrap_or(0);
-        if changes_used > 0 {
-            info!("tx used {} changes", changes_used);
-            // The next sync would update the internal index but we increment the internal index also
-            // here after sign so that if we immediately create another tx we are not reusing addresses
-            // This implies signing multiple times without broadcasting leads to gaps in the internal chain
-            acc_store.indexes.internal += changes_used;
+    /// Sign the transaction with an external signer
+    ///
+    /// The signatures returned by the signer are verified before being added.
+    pub fn sign_with_signer(
+        &self,
+        request: &TransactionMeta,
+        signer: &dyn Signer,
+    ) -> Result<TransactionMeta, Error> {
+        info!("sign with external signer");
+        let be_tx =
+            BETransaction::deserialize(&Vec::<u8>::from_hex(&request.hex)?, self.network.id())?;
+        let sighashes = request
+            .used_utxos
+            .iter()
+            .map(|u| u.sighash())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::InvalidSigHash)?;
+        if sighashes.len() != be_tx.input_len() {
+            return Err(Error::Generic("Mismatching used_utxos and transaction".into()));
         }
+        let be_tx = match be_tx {
+            BETransaction::Elements(tx) => BETransaction::Elements(blind_tx(self, &tx)?),
+            tx => tx,
+        };
 
-        if let Some(memo) = request.create_transaction.as_ref().and_then(|c| c.memo.as_ref()) {
-            let txid = BETxid::from_hex(&betx.txid, self.network.id())?;
-            store_write.insert_memo(txid, memo)?;
+        let response = signer.sign_transaction(&SignTransactionRequest {
+            transaction: be_tx.serialize().to_hex(),
+            used_utxos: request.used_utxos.clone(),
+        })?;
+        if response.signatures.len() != be_tx.input_len() {
+            return Err(Error::InvalidSignerResponse("wrong number of signatures".into()));
         }
-
-        Ok(betx)
-    }
-
-    pub fn get_script_batch(&self, is_internal: bool, batch: u32) -> Result<ScriptBatch, Error> {
-        let store = self.store.read()?;
-        let acc_store = store.account_cache(self.account_num)?;
-
-        let mut result = ScriptBatch::default();
-        result.cached = true;
-
-        let start = batch * BATCH_SIZE;
-        let end = start + BATCH_SIZE;
-        for j in start..end {
-            let path = DerivationPath::from(&[(is_internal as u32).into(), j.into()][..]);
-            let script = acc_store.scripts.get(&path).cloned().map_or_else(
-                || -> Result<BEScript, Error> {
-                    result.cached = false;
-                    Ok(self.derive_address(is_internal, j)?.script_pubkey())
-                },
-                Ok,
-            )?;
-            result.value.push((script, path));
+        let mut signatures = vec![];
+        for (utxo, signature) in request.used_utxos.iter().zip(response.signatures.iter()) {
+            if utxo.skip_signing {
+                signatures.push(None);
+                continue;
+            }
+            // the account path is made of the last two steps of the full path
+            let account_path = utxo
+                .user_path
+                .len()
+                .checked_sub(2)
+                .map(|start| DerivationPath::from(&utxo.user_path[start..]))
+                .ok_or_else(|| Error::Generic("Invalid user path".into()))?;
+            signatures
+                .push(Some((self.public_key(&account_path), Vec::<u8>::from_hex(signature)?)));
         }
-        Ok(result)
-    }
-
-    /// Get the chain number for the given address (0 for receive or 1 for change)
-    pub fn get_wallet_chain_type(&self, script: &BEScript) -> Option<u32> {
-        let store_read = self.store.read().unwrap();
-        let acc_store = store_read.account_cache(self.account_num).unwrap();
 
-        if let Some(path) = acc_store.paths.get(&script) {
-            if let ChildNumber::Normal {
-                index,
-            } = path[0]
-            {
-                return Some(index);
+        let store_read = self.store.read()?;
+        let acc_store = store_read.account_cache(self.account_num)?;
+        let betx: TransactionMeta = match be_tx {
+            BETransaction::Bitcoin(mut tx) => {
+                let prevouts = self.bitcoin_prevouts(&tx, &request.used_utxos, acc_store)?;
+                add_bitcoin_signatures(
+                    &mut tx,
+                    &signatures,
+                    &prevouts,
+                    self.script_type,
+                    &sighashes,
+                )?;
+                BETransaction::Bitcoin(tx).into()
             }
-        }
-        None
+            BETransaction::Elements(mut tx) => {
+                // the values of the outputs spent by the inputs to sign
+                let values = tx
+                    .input
+                    .iter()
+                    .zip(signatures.iter())
+                    .map(|(input, signature)| {
+                        if signature.is_none() {
+                            return Ok(Value::Null);
+                        }
+                        let prev_output = input.previous_output;
+                        let prev_tx = acc_store.get_liquid_tx(&prev_output.txid)?;
+                        Ok(prev_tx.output[prev_output.vout as usize].value)
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                add_elements_signatures(
+                    &mut tx,
+                    &signatures,
+                    &values,
+                    self.script_type,
+                    &sighashes,
+                )?;
+                BETransaction::Elements(tx).into()
+            }
+        };
+        drop(store_read);
+        self.finalize_signed_tx(request, betx)
     }
 
-    /// Verify that our own (outgoing) transactions were properly signed by the wallet.
-    /// This is needed to prevent malicious servers from getting the user to fee-bump a
-    /// transaction that they never signed in the first place.
+    /// Create a PSBT from the transaction returned by `create_tx`
     ///
-    /// Invalid transactions will be removed from the db and result in an Ok(false).
-    pub fn verify_own_txs(&self, txs: &[(BETxid, BETransaction)]) -> Result<bool, Error> {
-        let mut all_valid = true;
-        let mut store_write = self.store.write().unwrap();
-        let acc_store = store_write.account_cache_mut(self.account_num).unwrap();
+    /// The previous outputs and the key origins of the wallet inputs and outputs are included,
+    /// so that signers can verify the transaction amounts and sign it. The key origins are
+    /// omitted if unknown.
+    pub fn create_psbt(
+        &self,
+        request: &TransactionMeta,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        let mut tx = match BETransaction::from_hex(&request.hex, self.network.id())? {
+            BETransaction::Bitcoin(tx) => tx,
+            BETransaction::Elements(_) => {
+                return Err(Error::Generic("PSBT are supported on Bitcoin only".into()))
+            }
+        };
+        for input in tx.input.iter_mut() {
+            input.script_sig = bitcoin::Script::new();
+            input.witness = Witness::default();
+        }
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx)?;
 
-        for (txid, tx) in txs {
+        let store_read = self.store.read()?;
+        let acc_store = store_read.account_cache(self.account_num)?;
+        for (i, input) in psbt.inputs.iter_mut().enumerate() {
+            let prev_output = psbt.unsigned_tx.input[i].previous_output;
+            // foreign inputs might not be in the store
+            let prev_tx = match acc_store.get_bitcoin_tx(&prev_output.txid) {
+                Ok(prev_tx) => prev_tx,
+                Err(_) => continue,
+            };
+            let prevout = prev_tx.output[prev_output.vout as usize].clone();
+            let path = acc_store.get_path(&prevout.script_pubkey.clone().into());
+            input.witness_utxo = Some(prevout);
+            if !self.script_type.is_taproot() {
+                // needed by some signers to verify the input amounts
+                input.non_witness_utxo = Some(prev_tx);
+            }
+            let path = match path {
+                Ok(path) => path,
+                Err(_) => continue,
+            };
+            let public_key = self.public_key(path);
+            let key_source = self.key_source(path);
+            if self.script_type.is_taproot() {
+                let internal_key = public_key.inner.x_only_public_key().0;
+                input.tap_internal_key = Some(internal_key);
+                if let Some(key_source) = key_source {
+                    input.tap_key_origins.insert(internal_key, (vec![], key_source));
+                }
+            } else if let Some(key_source) = key_source {
+                input.bip32_derivation.insert(public_key.inner, key_source);
+            }
+            if self.script_type == ScriptType::P2shP2wpkh {
+                input.redeem_script =
+                    Some(bitcoin::Script::new_v0_p2wpkh(&public_key.wpubkey_hash().unwrap()));
+            }
+            let sighash = match request.used_utxos.get(i) {
+                Some(u) => {
+                    u.sighash().and_then(|s| s.into_bitcoin()).map_err(|_| Error::InvalidSigHash)?
+                }
+                None => EcdsaSighashType::All,
+            };
+            if sighash != EcdsaSighashType::All {
+                input.sighash_type = Some(sighash.into());
+            }
+        }
+        for (i, output) in psbt.outputs.iter_mut().enumerate() {
+            let script_pubkey = psbt.unsigned_tx.output[i].script_pubkey.clone();
+            let path = match acc_store.get_path(&script_pubkey.into()) {
+                Ok(path) => path,
+                Err(_) => continue,
+            };
+            let public_key = self.public_key(path);
+            let key_source = self.key_source(path);
+            if self.script_type.is_taproot() {
+                let internal_key = public_key.inner.x_only_public_key().0;
+                output.tap_internal_key = Some(internal_key);
+                if let Some(key_source) = key_source {
+                    output.tap_key_origins.insert(internal_key, (vec![], key_source));
+                }
+            } else if let Some(key_source) = key_source {
+                output.bip32_derivation.insert(public_key.inner, key_source);
+            }
+            if self.script_type == ScriptType::P2shP2wpkh {
+                output.redeem_script =
+                    Some(bitcoin::Script::new_v0_p2wpkh(&public_key.wpubkey_hash().unwrap()));
+            }
+        }
+        Ok(psbt)
+    }
+
+    /// Sign the PSBT inputs spending outputs of this account, returning the number of inputs signed
+    pub fn sign_psbt(&self, psbt: &mut PartiallySignedTransaction) -> Result<usize, Error> {
+        let xprv = self
+            .xprv
+            .ok_or_else(|| Error::Generic("Internal software signing is not supported".into()))?;
+        let prevouts = psbt
+            .inputs
+            .iter()
+            .zip(psbt.unsigned_tx.input.iter())
+            .map(|(input, txin)| match (&input.witness_utxo, &input.non_witness_utxo) {
+                (Some(txout), _) => Some(txout.clone()),
+                (None, Some(tx)) => tx.output.get(txin.previous_output.vout as usize).cloned(),
+                (None, None) => None,
+            })
+            .collect::<Vec<_>>();
+        if self.script_type.is_taproot() && prevouts.iter().any(Option::is_none) {
+            return Err(Error::Generic("taproot signing requires all the input utxos".into()));
+        }
+        let prevouts_or_default =
+            prevouts.iter().map(|p| p.clone().unwrap_or_default()).collect::<Vec<_>>();
+
+        let store_read = self.store.read()?;
+        let acc_store = store_read.account_cache(self.account_num)?;
+        let mut signed = 0;
+        for (i, prevout) in prevouts.iter().enumerate() {
+            let path = match prevout {
+                Some(prevout) => match acc_store.get_path(&prevout.script_pubkey.clone().into()) {
+                    Ok(path) => path,
+                    Err(_) => continue,
+                },
+                None => continue,
+            };
+            let input = &psbt.inputs[i];
+            let sighash = if self.script_type.is_taproot() {
+                // taproot inputs are signed with SIGHASH_DEFAULT only
+                match input.schnorr_hash_ty() {
+                    Ok(SchnorrSighashType::Default) => EcdsaSighashType::All,
+                    _ => return Err(Error::UnsupportedSigHash),
+                }
+            } else {
+                input.ecdsa_hash_ty().map_err(|_| Error::UnsupportedSigHash)?
+            };
+            let (public_key, signature) = internal_bitcoin_signature(
+                &psbt.unsigned_tx,
+                i,
+                &xprv,
+                path,
+                &prevouts_or_default,
+                self.script_type,
+                &BESigHashType::Bitcoin(sighash),
+            )?;
+            let input = &mut psbt.inputs[i];
+            if self.script_type.is_taproot() {
+                input.tap_key_sig =
+                    Some(SchnorrSig::from_slice(&signature).map_err(|_| Error::InvalidSigHash)?);
+            } else {
+                input.partial_sigs.insert(
+                    public_key,
+                    EcdsaSig::from_slice(&signature).map_err(|_| Error::InvalidSigHash)?,
+                );
+            }
+            signed += 1;
+        }
+        Ok(signed)
+    }
+
+    /// The outputs spent by the transaction inputs
+    ///
+    /// The taproot sighash commits to all the previous outputs, otherwise only the ones being
+    /// signed are needed and the others might not be in the store.
+    fn bitcoin_prevouts(
+        &self,
+        tx: &bitcoin::Transaction,
+        used_utxos: &[UnspentOutput],
+        acc_store: &RawAccountCache,
+    ) -> Result<Vec<bitcoin::TxOut>, Error> {
+        let mut prevouts = vec![];
+        for (i, input) in tx.input.iter().enumerate() {
+            let prev_output = input.previous_output;
+            prevouts.push(if self.script_type.is_taproot() || !used_utxos[i].skip_signing {
+                let prev_tx = acc_store.get_bitcoin_tx(&prev_output.txid)?;
+                prev_tx.output[prev_output.vout as usize].clone()
+            } else {
+                bitcoin::TxOut::default()
+            });
+        }
+        Ok(prevouts)
+    }
+
+    /// Update the store after a transaction has been signed
+    fn finalize_signed_tx(
+        &self,
+        request: &TransactionMeta,
+        mut betx: TransactionMeta,
+    ) -> Result<TransactionMeta, Error> {
+        betx.fee = request.fee;
+        betx.create_transaction = request.create_transaction.clone();
+        betx.used_utxos = request.used_utxos.clone();
+
+        let mut store_write = self.store.write()?;
+        let mut acc_store = store_write.account_cache_mut(self.account_num)?;
+
+        let changes_used = request.changes_used.unwrap_or(0);
+        if changes_used > 0 {
+            info!("tx used {} changes", changes_used);
+            // The next sync would update the internal index but we increment the internal index also
+            // here after sign so that if we immediately create another tx we are not reusing addresses
+            // This implies signing multiple times without broadcasting leads to gaps in the internal chain
+            acc_store.indexes.internal += changes_used;
+        }
+
+        if let Some(memo) = request.create_transaction.as_ref().and_then(|c| c.memo.as_ref()) {
+            let txid = BETxid::from_hex(&betx.txid, self.network.id())?;
+            store_write.insert_memo(txid, memo)?;
+        }
+
+        Ok(betx)
+    }
+
+    pub fn get_script_batch(
+        &self,
+        is_internal: bool,
+        batch: u32,
+        batch_size: u32,
+    ) -> Result<ScriptBatch, Error> {
+        let store = self.store.read()?;
+        let acc_store = store.account_cache(self.account_num)?;
+
+        let mut result = ScriptBatch::default();
+        result.cached = true;
+
+        let start = batch * batch_size;
+        let end = start + batch_size;
+        for j in start..end {
+            let path = DerivationPath::from(&[(is_internal as u32).into(), j.into()][..]);
+            let script = acc_store.scripts.get(&path).cloned().map_or_else(
+                || -> Result<BEScript, Error> {
+                    result.cached = false;
+                    Ok(self.derive_address(is_internal, j)?.script_pubkey())
+                },
+                Ok,
+            )?;
+            result.value.push((script, path));
+        }
+        Ok(result)
+    }
+
+    /// Get the chain number for the given address (0 for receive or 1 for change)
+    pub fn get_wallet_chain_type(&self, script: &BEScript) -> Option<u32> {
+        let store_read = self.store.read().unwrap();
+        let acc_store = store_read.account_cache(self.account_num).unwrap();
+
+        if let Some(path) = acc_store.paths.get(&script) {
+            if let ChildNumber::Normal {
+                index,
+            } = path[0]
+            {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Verify that our own (outgoing) transactions were properly signed by the wallet.
+    /// This is needed to prevent malicious servers from getting the user to fee-bump a
+    /// transaction that they never signed in the first place.
+    ///
+    /// Invalid transactions will be removed from the db and result in an Ok(false).
+    pub fn verify_own_txs(&self, txs: &[(BETxid, BETransaction)]) -> Result<bool, Error> {
+        let mut all_valid = true;
+        let mut store_write = self.store.write().unwrap();
+        let acc_store = store_write.account_cache_mut(self.account_num).unwrap();
+
+        for (txid, tx) in txs {
             info!("verifying tx: {}", txid);
             // Confirmed transactions and Elements transactions cannot be fee-bumped and therefore don't require verification
             if !matches!(tx, BETransaction::Bitcoin(_))
@@ -871,6 +1809,30 @@ impl Account {
                 continue;
             }
             let mut hashcache = None;
+            let prevouts = if self.script_type.is_taproot() {
+                let prevouts = tx
+                    .previous_outputs()
+                    .iter()
+                    .map(|outpoint| {
+                        Some(bitcoin::TxOut {
+                            value: acc_store
+                                .all_txs
+                                .get_previous_output_value(outpoint, &acc_store.unblinded)?,
+                            script_pubkey: acc_store
+                                .all_txs
+                                .get_previous_output_script_pubkey(outpoint)?
+                                .into_bitcoin(),
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>();
+                if prevouts.is_none() {
+                    // cannot compute the sighash, the tx will fail the verification
+                    warn!("tx {} has unknown previous outputs", txid);
+                }
+                prevouts
+            } else {
+                None
+            };
             for (vin, outpoint) in tx.previous_outputs().iter().enumerate() {
                 let script = acc_store
                     .all_txs
@@ -892,6 +1854,7 @@ impl Account {
                     vin,
                     &public_key,
                     value,
+                    prevouts.as_deref(),
                     self.script_type,
                 ) {
                     warn!("tx {} verification failed: {:?}", txid, err);
@@ -929,6 +1892,7 @@ pub fn get_account_script_purpose(account_num: u32) -> Result<(ScriptType, u32),
         0 => (ScriptType::P2shP2wpkh, 49),
         1 => (ScriptType::P2wpkh, 84),
         2 => (ScriptType::P2pkh, 44),
+        3 => (ScriptType::P2tr, 86),
         _ => return Err(Error::InvalidSubaccount(account_num)),
     })
 }
@@ -939,6 +1903,10 @@ pub fn get_account_derivation(
 ) -> Result<(ScriptType, DerivationPath), Error> {
     let coin_type = get_coin_type(network_id);
     let (script_type, purpose) = get_account_script_purpose(account_num)?;
+    if script_type.is_taproot() && matches!(network_id, NetworkId::Elements(_)) {
+        // taproot signing is implemented for bitcoin only
+        return Err(Error::InvalidSubaccount(account_num));
+    }
     let bip32_account_num = account_num / NUM_RESERVED_ACCOUNT_TYPES;
 
     // BIP44: m / purpose' / coin_type' / account' / change / address_index
@@ -1003,6 +1971,10 @@ fn bitcoin_address(
         ScriptType::P2shP2wpkh => Address::p2shwpkh(public_key, net).expect("no compressed keys"),
         ScriptType::P2wpkh => Address::p2wpkh(public_key, net).expect("no compressed keys"),
         ScriptType::P2pkh => Address::p2pkh(public_key, net),
+        // BIP86: key path only, the internal key is tweaked without a script tree
+        ScriptType::P2tr => {
+            Address::p2tr(&crate::EC, public_key.inner.x_only_public_key().0, None, net)
+        }
     }
 }
 
@@ -1017,6 +1989,13 @@ fn elements_address(
         ScriptType::P2pkh => elements::Address::p2pkh(public_key, None, addr_params),
         ScriptType::P2shP2wpkh => elements::Address::p2shwpkh(public_key, None, addr_params),
         ScriptType::P2wpkh => elements::Address::p2wpkh(public_key, None, addr_params),
+        ScriptType::P2tr => elements::Address::p2tr(
+            &crate::EC,
+            public_key.inner.x_only_public_key().0,
+            None,
+            None,
+            addr_params,
+        ),
     };
     let script_pubkey = address.script_pubkey();
     let blinding_prv = asset_blinding_key_to_ec_private_key(master_blinding_key, &script_pubkey);
@@ -1024,44 +2003,47 @@ fn elements_address(
     address.to_confidential(blinding_pub)
 }
 
+fn descriptor_with_key(script_type: ScriptType, key: &str) -> String {
+    let (prefix, suffix) = match script_type {
+        ScriptType::P2shP2wpkh => ("sh(wpkh", ")"),
+        ScriptType::P2wpkh => ("wpkh", ""),
+        ScriptType::P2pkh => ("pkh", ""),
+        ScriptType::P2tr => ("tr", ""),
+    };
+    format!("{}({}){}", prefix, key, suffix)
+}
+
 pub fn discover_account(
-    electrum_url: &ElectrumUrl,
+    backend_url: &BackendUrl,
     proxy: Option<&str>,
     account_xpub: &ExtendedPubKey,
     script_type: ScriptType,
+    gap_limit: u32,
 ) -> Result<bool, Error> {
-    use electrum_client::ElectrumApi;
+    let client = backend_url.build_client(proxy, None)?;
 
-    // build our own client so that the subscriptions are dropped at the end
-    let client = electrum_url.build_client(proxy, None)?;
-
-    // the batch size is the effective gap limit for our purposes. in reality it is a lower bound.
-    let gap_limit = BATCH_SIZE;
+    let desc = descriptor_with_key(script_type, &format!("{}/0/*", account_xpub));
+    let desc = format!("{}#{}", desc, descriptor_checksum(&desc)?);
+    client.import_descriptors(&[(desc, gap_limit - 1)])?;
 
     let external_xpub = account_xpub.ckd_pub(&crate::EC, 0.into())?;
+    let mut scripts = vec![];
     for index in 0..gap_limit {
         let child_key = external_xpub.ckd_pub(&crate::EC, index.into())?;
         // Every network has the same scriptpubkey
         let script = bitcoin_address(&child_key.to_pub(), script_type, bitcoin::Network::Bitcoin)
             .script_pubkey();
-
-        if client.script_subscribe(&script)?.is_some() {
-            return Ok(true);
-        }
+        scripts.push(script);
     }
 
-    Ok(false)
+    Ok(client.batch_script_get_history(&scripts)?.iter().any(|history| !history.is_empty()))
 }
 
-#[allow(clippy::cognitive_complexity)]
-pub fn create_tx(
-    account: &Account,
+/// The fee rate of `request` in satoshi/byte, set to the minimum one if missing
+fn request_fee_rate(
+    network: &NetworkParameters,
     request: &mut CreateTransaction,
-) -> Result<TransactionMeta, Error> {
-    info!("create_tx {:?}", request);
-
-    let network = &account.network;
-
+) -> Result<f64, Error> {
     let default_min_fee_rate = match network.id() {
         NetworkId::Bitcoin(_) => 1000,
         NetworkId::Elements(_) => 100,
@@ -1074,12 +2056,34 @@ pub fn create_tx(
     // convert from satoshi/kbyte to satoshi/byte
     let fee_rate = (*fee_rate_sat_kb as f64) / 1000.0;
     info!("target fee_rate {:?} satoshi/byte", fee_rate);
+    Ok(fee_rate)
+}
+
+#[allow(clippy::cognitive_complexity)]
+pub fn create_tx(
+    account: &Account,
+    request: &mut CreateTransaction,
+) -> Result<TransactionMeta, Error> {
+    info!("create_tx {:?}", request);
+
+    let network = &account.network;
+
+    let fee_rate = request_fee_rate(network, request)?;
 
     // TODO put checks into CreateTransaction::validate
     // eagerly check for address validity
     for addressee in request.addressees.iter() {
+        if addressee.is_pegout {
+            check_pegout(addressee, network)?;
+            continue;
+        }
         match network.id() {
             NetworkId::Bitcoin(network) => {
+                if addressee.is_explicit {
+                    return Err(Error::Generic(
+                        "explicit outputs are supported only on liquid".into(),
+                    ));
+                }
                 if let Ok(address) = bitcoin::Address::from_str(&addressee.address) {
                     info!("address.network:{} network:{}", address.network, network);
                     if address.network == network
@@ -1107,7 +2111,7 @@ pub fn create_tx(
                     &addressee.address,
                     network.address_params(),
                 ) {
-                    if !address.is_blinded() {
+                    if !address.is_blinded() && !addressee.is_explicit {
                         return Err(Error::NonConfidentialAddress);
                     }
                     if let elements::address::Payload::WitnessProgram {
@@ -1141,6 +2145,23 @@ pub fn create_tx(
         return Err(Error::InvalidAmount);
     }
 
+    if request.issuance.is_some() || !request.burns.is_empty() {
+        if !network.liquid {
+            return Err(Error::InvalidIssuanceRequest("supported only on liquid".into()));
+        }
+        if send_all || request.previous_transaction.is_some() || request.cpfp_txid.is_some() {
+            return Err(Error::InvalidIssuanceRequest(
+                "cannot be combined with send_all, replacements or cpfp".into(),
+            ));
+        }
+    }
+    for burn in request.burns.iter() {
+        elements::issuance::AssetId::from_str(&burn.asset_id).map_err(|_| Error::InvalidAssetId)?;
+        if burn.satoshi == 0 {
+            return Err(Error::InvalidAmount);
+        }
+    }
+
     let mut template_tx = None;
     let mut change_addresses = vec![];
 
@@ -1182,6 +2203,7 @@ pub fn create_tx(
                             .to_string(),
                         satoshi: o.value,
                         asset_id: None,
+                        ..Default::default()
                     })
                 })
                 .collect();
@@ -1194,7 +2216,13 @@ pub fn create_tx(
             request.memo = Some(prev_txitem.memo.clone());
         }
     } else {
-        if request.addressees.is_empty() {
+        // a cpfp child without addressees sends everything to a change address,
+        // issuances and burns do not need addressees either
+        if request.addressees.is_empty()
+            && request.cpfp_txid.is_none()
+            && request.issuance.is_none()
+            && request.burns.is_empty()
+        {
             return Err(Error::EmptyAddressees);
         }
 
@@ -1236,11 +2264,37 @@ pub fn create_tx(
             if request.confidential_utxos_only && !utxo.is_confidential() {
                 continue;
             }
+            // frozen utxos are spent only if explicitly requested
+            if request.utxo_strategy != UtxoStrategy::Manual
+                && account.store.read()?.is_utxo_frozen(&outpoint)
+            {
+                continue;
+            }
             utxos.push(utxo);
         }
     }
     info!("utxos len:{} utxos:{:?}", utxos.len(), utxos);
 
+    // When a transaction is bumped with CPFP, all its outputs we own and not frozen are spent by
+    // the child
+    let mut cpfp_utxos = vec![];
+    if let Some(ref parent_txid) = request.cpfp_txid {
+        if send_all || network.liquid || request.previous_transaction.is_some() {
+            return Err(Error::InvalidCpfpRequest);
+        }
+        let parent_txid = BETxid::from_hex(parent_txid, id)?;
+        for outpoint in account.unspents()?.iter().filter(|o| o.txid() == parent_txid) {
+            if account.store.read()?.is_utxo_frozen(outpoint) {
+                continue;
+            }
+            cpfp_utxos.push(account.txo(outpoint)?);
+        }
+        if cpfp_utxos.is_empty() {
+            return Err(Error::InvalidCpfpRequest);
+        }
+        utxos.retain(|u| u.outpoint.txid() != parent_txid);
+    }
+
     if send_all {
         // send_all works by creating a dummy tx with all utxos, estimate the fee and set the
         // sending amount to `total_amount_utxos - estimated_fee`
@@ -1258,9 +2312,7 @@ pub fn create_tx(
                 dummy_tx.add_input(utxo.outpoint.clone());
             }
             let out = &request.addressees[0]; // safe because we checked we have exactly one recipient
-            dummy_tx
-                .add_output(&out.address, out.satoshi, out.asset_id(), network.id())
-                .map_err(|_| Error::InvalidAddress)?;
+            add_addressee(&mut dummy_tx, out, network)?;
             // estimating 2 satoshi more as estimating less would later result in InsufficientFunds
             let estimated_fee = dummy_tx.estimated_fee(fee_rate, 0, account.script_type) + 2;
             total_amount_utxos.checked_sub(estimated_fee).ok_or_else(|| Error::InsufficientFunds)?
@@ -1284,18 +2336,34 @@ pub fn create_tx(
         || -> Result<_, Error> {
             let mut new_tx = BETransaction::new(network.id());
             for out in request.addressees.iter() {
-                new_tx
-                    .add_output(&out.address, out.satoshi, out.asset_id(), network.id())
-                    .map_err(|_| Error::InvalidAddress)?;
+                add_addressee(&mut new_tx, out, network)?;
+            }
+            for burn in request.burns.iter() {
+                let asset = elements::issuance::AssetId::from_str(&burn.asset_id)
+                    .map_err(|_| Error::InvalidAssetId)?;
+                new_tx.add_burn_output(burn.satoshi, asset)?;
             }
             Ok(new_tx)
         },
         Ok,
     )?;
+    if let Some(ref issuance) = request.issuance {
+        add_issuance(account, &mut tx, &mut utxos, issuance)?;
+    }
 
     // STEP 2) add utxos until tx outputs are covered (including fees) or fail
     let store_read = account.store.read()?;
     let acc_store = store_read.account_cache(account.num())?;
+    let fee_rate = if cpfp_utxos.is_empty() {
+        fee_rate
+    } else {
+        for utxo in cpfp_utxos.iter() {
+            tx.add_input(utxo.outpoint.clone());
+        }
+        let parent_txid = cpfp_utxos[0].outpoint.txid();
+        cpfp_fee_rate(&tx, &parent_txid, fee_rate, account.script_type, acc_store)?
+    };
+    let mut changeless = false;
     match request.utxo_strategy {
         UtxoStrategy::Default => {
             let mut used_utxo: HashSet<BEOutPoint> = HashSet::new();
@@ -1365,12 +2433,93 @@ pub fn create_tx(
                 return Err(Error::InsufficientFunds);
             }
         }
+        ref strategy => {
+            let is_bitcoin = matches!(network.id(), NetworkId::Bitcoin(_));
+            // UTXO with same script must be spent together in bitcoin, see the default strategy
+            let mut groups = coin_selection::group_utxos(&utxos, is_bitcoin);
+            if *strategy == UtxoStrategy::OldestFirst {
+                groups.sort_by_key(|g| g.height());
+            } else {
+                groups.sort_by_key(|g| std::cmp::Reverse(g.value()));
+            }
+            let mut used_groups = vec![false; groups.len()];
+
+            let tx_needs = |tx: &BETransaction, no_change: bool| {
+                tx.needs(
+                    fee_rate,
+                    no_change,
+                    network.policy_asset_id().ok(),
+                    &acc_store.all_txs,
+                    &acc_store.unblinded,
+                    account.script_type,
+                )
+            };
+
+            // branch and bound and waste minimization are applied to bitcoin only, liquid
+            // requires changes for every asset and falls back to largest first
+            let wants_waste_selection =
+                matches!(strategy, UtxoStrategy::BranchAndBound | UtxoStrategy::MinimizeWaste);
+            if is_bitcoin && !send_all && wants_waste_selection {
+                let long_term_fee_rate =
+                    request.long_term_fee_rate.unwrap_or(DEFAULT_LONG_TERM_FEE_RATE) as f64
+                        / 1000.0;
+                let selection = select_minimizing_waste(
+                    &tx,
+                    &groups,
+                    strategy,
+                    fee_rate,
+                    long_term_fee_rate,
+                    account.script_type,
+                    |no_change| tx_needs(&tx, no_change).pop().map_or(0, |n| n.satoshi),
+                );
+                if let Some((indexes, is_changeless)) = selection {
+                    for i in indexes {
+                        used_groups[i] = true;
+                        for utxo in groups[i].utxos.iter() {
+                            tx.add_input(utxo.outpoint.clone());
+                        }
+                    }
+                    // double check the estimates, rounding could make the selection
+                    // insufficient or leave an excess that becomes a change
+                    changeless = is_changeless
+                        && tx_needs(&tx, true).is_empty()
+                        && tx
+                            .changes(
+                                tx.estimated_fee(fee_rate, 0, account.script_type),
+                                network.policy_asset_id().ok(),
+                                &acc_store.all_txs,
+                                &acc_store.unblinded,
+                            )
+                            .is_empty();
+                }
+            }
+
+            loop {
+                let mut needs = tx_needs(&tx, send_all || changeless); // "policy asset" is last, in bitcoin max 1 element
+                info!("needs: {:?}", needs);
+                if needs.is_empty() {
+                    // SUCCESS tx doesn't need other inputs
+                    break;
+                }
+                let current_need = needs.pop().unwrap(); // safe to unwrap just checked it's not empty
+
+                let (i, group) = groups
+                    .iter()
+                    .enumerate()
+                    .find(|(i, g)| !used_groups[*i] && g.asset_id() == current_need.asset)
+                    .ok_or(Error::InsufficientFunds)?;
+                used_groups[i] = true;
+                for utxo in group.utxos.iter() {
+                    tx.add_input(utxo.outpoint.clone());
+                }
+            }
+        }
     }
 
     // STEP 3) adding change(s)
     let estimated_fee = tx.estimated_fee(
         fee_rate,
-        tx.estimated_changes(send_all, &acc_store.all_txs, &acc_store.unblinded),
+        tx.estimated_changes(send_all || changeless, &acc_store.all_txs, &acc_store.unblinded),
         account.script_type,
     );
     let changes = tx.changes(
@@ -1394,6 +2543,11 @@ pub fn create_tx(
         tx.add_output(&change_address, change.satoshi, change.asset, network.id())?;
     }
 
+    if tx.output_len() == 0 {
+        // a cpfp child without addressees and a change below the dust
+        return Err(Error::InsufficientFunds);
+    }
+
     // randomize inputs and outputs, BIP69 has been rejected because lacks wallets adoption
     tx.scramble();
 
@@ -1433,21 +2587,374 @@ pub fn create_tx(
     Ok(created_tx)
 }
 
+/// Check that `addressee` pegs out the policy asset to an address of the mainchain of `network`
+fn check_pegout(addressee: &AddressAmount, network: &NetworkParameters) -> Result<(), Error> {
+    let mainchain = match network.id() {
+        NetworkId::Elements(ElementsNetwork::Liquid) => {
+            // liquid requires a PAK proof in every pegout, which we cannot produce
+            return Err(Error::InvalidPegRequest("pegouts are not supported on liquid".into()));
+        }
+        NetworkId::Elements(net) => net.mainchain_network(),
+        NetworkId::Bitcoin(_) => {
+            return Err(Error::InvalidPegRequest("pegouts are supported only on liquid".into()))
+        }
+    };
+    let address =
+        bitcoin::Address::from_str(&addressee.address).map_err(|_| Error::InvalidAddress)?;
+    if address.network != mainchain
+        && !(address.network == bitcoin::Network::Testnet && mainchain == bitcoin::Network::Regtest)
+    {
+        return Err(Error::InvalidAddress);
+    }
+    if addressee.asset_id.is_none() || addressee.asset_id != network.policy_asset {
+        return Err(Error::InvalidPegRequest("only the policy asset can be pegged out".into()));
+    }
+    Ok(())
+}
+
+/// Add the output requested by `out` to `tx`
+fn add_addressee(
+    tx: &mut BETransaction,
+    out: &AddressAmount,
+    network: &NetworkParameters,
+) -> Result<(), Error> {
+    if out.is_pegout {
+        let mainchain = network
+            .id()
+            .get_elements_network()
+            .ok_or_else(|| Error::InvalidPegRequest("pegouts are supported only on liquid".into()))?
+            .mainchain_network();
+        let genesis_hash = genesis_block(mainchain).block_hash();
+        let address =
+            bitcoin::Address::from_str(&out.address).map_err(|_| Error::InvalidAddress)?;
+        let asset = out.asset_id().ok_or(Error::InvalidAssetId)?;
+        tx.add_pegout_output(out.satoshi, asset, genesis_hash, &address.script_pubkey())?;
+    } else if out.is_explicit {
+        if !network.liquid {
+            return Err(Error::Generic("explicit outputs are supported only on liquid".into()));
+        }
+        let asset = out.asset_id().ok_or(Error::InvalidAssetId)?;
+        tx.add_explicit_output(&out.address, out.satoshi, asset, network.id())
+            .map_err(|_| Error::InvalidAddress)?;
+    } else {
+        tx.add_output(&out.address, out.satoshi, out.asset_id(), network.id())
+            .map_err(|_| Error::InvalidAddress)?;
+    }
+    Ok(())
+}
+
+/// Add an input with the requested issuance or reissuance and the outputs receiving the issued
+/// amounts, the spent utxo is removed from `utxos`
+fn add_issuance(
+    account: &Account,
+    tx: &mut BETransaction,
+    utxos: &mut Vec<Txo>,
+    request: &AssetIssuanceRequest,
+) -> Result<(), Error> {
+    let invalid = |msg: &str| Error::InvalidIssuanceRequest(msg.into());
+    let network = &account.network;
+    let policy_asset = network.policy_asset_id()?;
+    if request.asset_amount > MAX_ISSUANCE_AMOUNT || request.token_amount > MAX_ISSUANCE_AMOUNT {
+        return Err(Error::InvalidAmount);
+    }
+
+    let (index, asset_issuance) = match request.reissue_asset_id {
+        None => {
+            if request.asset_amount == 0 && request.token_amount == 0 {
+                return Err(Error::InvalidAmount);
+            }
+            let contract_hash = match request.contract {
+                Some(ref contract) => issuance::contract_hash(contract)?,
+                None => ContractHash::all_zeros(),
+            };
+            // the largest policy asset utxo also contributes to the fees
+            let index = utxos
+                .iter()
+                .enumerate()
+                .filter(|(_, u)| u.asset_id() == Some(policy_asset))
+                .max_by_key(|(_, u)| u.satoshi)
+                .map(|(i, _)| i)
+                .ok_or(Error::InsufficientFunds)?;
+            let asset_issuance = AssetIssuance {
+                asset_blinding_nonce: ZERO_TWEAK,
+                asset_entropy: contract_hash.into_inner(),
+                amount: issuance_value(request.asset_amount),
+                inflation_keys: issuance_value(request.token_amount),
+            };
+            (index, asset_issuance)
+        }
+        Some(ref asset_id) => {
+            if request.token_amount > 0 || request.token_address.is_some() {
+                return Err(invalid("reissuances cannot create tokens"));
+            }
+            if request.contract.is_some() {
+                return Err(invalid("reissuances cannot commit to a contract"));
+            }
+            if request.asset_amount == 0 {
+                return Err(Error::InvalidAmount);
+            }
+            let asset_id = elements::issuance::AssetId::from_str(asset_id)
+                .map_err(|_| Error::InvalidAssetId)?;
+            if let BETransaction::Elements(tx) = tx {
+                if tx.output.iter().any(|o| o.asset.explicit() == Some(asset_id)) {
+                    return Err(invalid("the reissued asset cannot be sent or burnt"));
+                }
+            }
+            let (entropy, is_confidential) = {
+                let store_read = account.store.read()?;
+                let acc_store = store_read.account_cache(account.num())?;
+                issuance::find_issuance(&acc_store.all_txs, &asset_id)
+                    .ok_or_else(|| invalid("issuance not found in the subaccount"))?
+            };
+            let token_id = elements::issuance::AssetId::reissuance_token_from_entropy(
+                entropy,
+                is_confidential,
+            );
+            let index = utxos
+                .iter()
+                .position(|u| u.asset_id() == Some(token_id))
+                .ok_or_else(|| invalid("no reissuance token available"))?;
+            // the nonce is the token asset blinding factor, which must not be zero
+            let asset_blinding_nonce = utxos[index]
+                .txoutsecrets
+                .as_ref()
+                .map(|s| s.asset_bf.into_inner())
+                .filter(|abf| *abf != ZERO_TWEAK)
+                .ok_or_else(|| invalid("cannot reissue with an explicit token"))?;
+            let asset_issuance = AssetIssuance {
+                asset_blinding_nonce,
+                asset_entropy: entropy.into_inner(),
+                amount: issuance_value(request.asset_amount),
+                inflation_keys: Value::Null,
+            };
+            (index, asset_issuance)
+        }
+    };
+    let previous_output = match utxos.remove(index).outpoint {
+        BEOutPoint::Elements(outpoint) => outpoint,
+        BEOutPoint::Bitcoin(_) => return Err(invalid("unexpected bitcoin utxo")),
+    };
+    let mut input = elements::TxIn {
+        previous_output,
+        sequence: 0xffff_fffe,
+        asset_issuance,
+        ..Default::default()
+    };
+    let entropy = issuance::issuance_entropy(&input);
+    let asset_id = elements::issuance::AssetId::from_entropy(entropy);
+    let token_id =
+        elements::issuance::AssetId::reissuance_token_from_entropy(entropy, request.confidential);
+    if request.confidential {
+        let master_blinding =
+            account.master_blinding.as_ref().ok_or(Error::MissingMasterBlindingKey)?;
+        let blinding_key = issuance::issuance_blinding_key(master_blinding, &previous_output);
+        issuance::blind_issuance(&mut input, blinding_key)?;
+    }
+    if let BETransaction::Elements(tx) = tx {
+        tx.input.push(input);
+    }
+
+    let outputs = [
+        (request.asset_amount, &request.asset_address, asset_id),
+        (request.token_amount, &request.token_address, token_id),
+    ];
+    for (amount, address, asset) in outputs.iter().filter(|(amount, _, _)| *amount > 0) {
+        let address = match address {
+            Some(address) => address.clone(),
+            None => account.get_next_address(false)?.address,
+        };
+        tx.add_output(&address, *amount, Some(*asset), network.id())
+            .map_err(|_| Error::InvalidAddress)?;
+    }
+    Ok(())
+}
+
+fn issuance_value(amount: u64) -> Value {
+    if amount == 0 {
+        Value::Null
+    } else {
+        Value::Explicit(amount)
+    }
+}
+
+/// Compute the fee rate of a cpfp child such that the package with its parent reaches `fee_rate`
+///
+/// The missing parent fee is added proportionally to the child fee, so the package fee rate is
+/// reached even if the child later grows with more inputs.
+fn cpfp_fee_rate(
+    child: &BETransaction,
+    parent_txid: &BETxid,
+    fee_rate: f64,
+    script_type: ScriptType,
+    acc_store: &RawAccountCache,
+) -> Result<f64, Error> {
+    if acc_store.heights.get(parent_txid) != Some(&None) {
+        // the parent is confirmed, replaced or dropped
+        return Err(Error::InvalidCpfpRequest);
+    }
+    let parent =
+        acc_store.all_txs.get(parent_txid).ok_or_else(|| Error::TxNotFound(*parent_txid))?;
+    let parent_fee = parent.tx.fee(&acc_store.all_txs, &acc_store.unblinded, &None)?;
+    let parent_vsize = weight_to_vsize(parent.weight) as f64;
+    let missing_fee = (parent_vsize * fee_rate - parent_fee as f64).max(0.0);
+    let child_fee = child.estimated_fee(fee_rate, 1, script_type) as f64;
+    let child_fee_rate = fee_rate * (child_fee + missing_fee) / child_fee;
+    info!(
+        "cpfp parent {} fee:{} vsize:{} child fee_rate {:?} satoshi/byte",
+        parent_txid, parent_fee, parent_vsize, child_fee_rate
+    );
+    Ok(child_fee_rate)
+}
+
+/// Select utxo groups with branch and bound or, if `strategy` is `MinimizeWaste`, choose the
+/// selection with the lowest waste among branch and bound, largest first and oldest first
+///
+/// `target` returns the amount needed by `tx` without or with a change.
+/// Returns the indexes of the selected groups and whether the selection is changeless.
+fn select_minimizing_waste(
+    tx: &BETransaction,
+    groups: &[OutputGroup],
+    strategy: &UtxoStrategy,
+    fee_rate: f64,
+    long_term_fee_rate: f64,
+    script_type: ScriptType,
+    target: impl Fn(bool) -> u64,
+) -> Option<(Vec<usize>, bool)> {
+    let (target, target_with_change) = (target(true), target(false));
+    if target == 0 || groups.is_empty() {
+        return None;
+    }
+
+    // marginal fees are computed with the same estimation used to build the transaction,
+    // adding a satoshi per input to account for rounding
+    let mut with_input = tx.clone();
+    with_input.add_input(groups[0].utxos[0].outpoint.clone());
+    let marginal_fee = |more: &BETransaction, fee_rate: f64, changes: u8| {
+        more.estimated_fee(fee_rate, changes, script_type).saturating_sub(tx.estimated_fee(
+            fee_rate,
+            0,
+            script_type,
+        ))
+    };
+    let input_fee = marginal_fee(&with_input, fee_rate, 0) + 1;
+    let long_term_input_fee = marginal_fee(&with_input, long_term_fee_rate, 0);
+    let cost_of_change = marginal_fee(tx, fee_rate, 1) + long_term_input_fee;
+
+    // groups not worth spending at the current fee rate are excluded
+    let (positions, candidates): (Vec<usize>, Vec<Candidate>) = groups
+        .iter()
+        .enumerate()
+        .filter_map(|(i, g)| {
+            let n = g.utxos.len() as u64;
+            let effective_value = g.value().checked_sub(n * input_fee).filter(|v| *v > 0)?;
+            let waste = n as i64 * (input_fee as i64 - long_term_input_fee as i64);
+            Some((
+                i,
+                Candidate {
+                    effective_value,
+                    waste,
+                },
+            ))
+        })
+        .unzip();
+
+    // changes below dust are not created, thus the window can't be wider
+    let bnb = coin_selection::branch_and_bound(&candidates, target, cost_of_change.min(DUST_VALUE));
+    let selection = if *strategy == UtxoStrategy::BranchAndBound {
+        bnb.map(|s| (s, true))
+    } else {
+        let mut options = vec![];
+        if let Some(s) = bnb {
+            options.push((coin_selection::waste(&candidates, &s, target, None), s, true));
+        }
+        let mut by_value: Vec<usize> = (0..candidates.len()).collect();
+        by_value.sort_by_key(|i| std::cmp::Reverse(candidates[*i].effective_value));
+        let mut by_height: Vec<usize> = (0..candidates.len()).collect();
+        by_height.sort_by_key(|i| groups[positions[*i]].height());
+        for order in &[by_value, by_height] {
+            let accumulated =
+                coin_selection::accumulate(&candidates, order, target_with_change, DUST_VALUE);
+            if let Some((s, has_change)) = accumulated {
+                let cost_of_change = if has_change {
+                    Some(cost_of_change)
+                } else {
+                    None
+                };
+                options.push((
+                    coin_selection::waste(&candidates, &s, target, cost_of_change),
+                    s,
+                    false,
+                ));
+            }
+        }
+        info!("waste of the selection options: {:?}", options);
+        options.into_iter().min_by_key(|(waste, _, _)| *waste).map(|(_, s, c)| (s, c))
+    };
+
+    selection
+        .map(|(s, is_changeless)| (s.into_iter().map(|i| positions[i]).collect(), is_changeless))
+}
+
 fn internal_sign_bitcoin(
     tx: &bitcoin::Transaction,
     input_index: usize,
     xprv: &ExtendedPrivKey,
     path: &DerivationPath,
-    value: u64,
+    prevouts: &[bitcoin::TxOut],
     script_type: ScriptType,
     sighash: &BESigHashType,
 ) -> Result<(bitcoin::Script, Vec<Vec<u8>>), Error> {
+    let (public_key, signature) =
+        internal_bitcoin_signature(tx, input_index, xprv, path, prevouts, script_type, sighash)?;
+    Ok(prepare_input(&public_key, signature, script_type))
+}
+
+/// Sign the input returning the public key and the signature, with the sighash byte appended
+/// for ECDSA signatures
+fn internal_bitcoin_signature(
+    tx: &bitcoin::Transaction,
+    input_index: usize,
+    xprv: &ExtendedPrivKey,
+    path: &DerivationPath,
+    prevouts: &[bitcoin::TxOut],
+    script_type: ScriptType,
+    sighash: &BESigHashType,
+) -> Result<(PublicKey, Vec<u8>), Error> {
     let xprv = xprv.derive_priv(&crate::EC, &path).unwrap();
-    let private_key = &xprv.to_priv();
+    bitcoin_signature(tx, input_index, &xprv.to_priv(), prevouts, script_type, sighash)
+}
+
+/// Sign the input with `private_key`, as in [`internal_bitcoin_signature`]
+fn bitcoin_signature(
+    tx: &bitcoin::Transaction,
+    input_index: usize,
+    private_key: &bitcoin::PrivateKey,
+    prevouts: &[bitcoin::TxOut],
+    script_type: ScriptType,
+    sighash: &BESigHashType,
+) -> Result<(PublicKey, Vec<u8>), Error> {
     let public_key = &PublicKey::from_private_key(&crate::EC, private_key);
     let script_code = p2pkh_script(public_key);
 
     let sighash = sighash.into_bitcoin()?;
+    if script_type.is_taproot() {
+        // only SIGHASH_ALL is allowed, which is signed as SIGHASH_DEFAULT (64 bytes signature)
+        if sighash != EcdsaSighashType::All {
+            return Err(Error::UnsupportedSigHash);
+        }
+        let hash = SighashCache::new(tx).taproot_key_spend_signature_hash(
+            input_index,
+            &Prevouts::All(prevouts),
+            SchnorrSighashType::Default,
+        )?;
+        let message = Message::from_slice(&hash.into_inner()[..]).unwrap();
+        let keypair = KeyPair::from_secret_key(&crate::EC, &private_key.inner)
+            .tap_tweak(&crate::EC, None)
+            .to_inner();
+        let signature = crate::EC.sign_schnorr(&message, &keypair);
+        return Ok((*public_key, signature.as_ref().to_vec()));
+    }
+    let value = prevouts[input_index].value;
     let hash = if script_type.is_segwit() {
         SighashCache::new(tx).segwit_signature_hash(input_index, &script_code, value, sighash)?
     } else {
@@ -1460,7 +2967,7 @@ fn internal_sign_bitcoin(
     let mut signature = signature.serialize_der().to_vec();
     signature.push(sighash as u8);
 
-    Ok(prepare_input(&public_key, signature, script_type))
+    Ok((*public_key, signature))
 }
 
 fn internal_sign_elements(
@@ -1497,6 +3004,127 @@ fn internal_sign_elements(
     Ok((script_sig.into_elements(), witness))
 }
 
+/// Sign the inputs of `pset` at the given indexes with the keys derived with the given paths
+fn sign_pset_inputs(
+    pset: &mut elements::pset::PartiallySignedTransaction,
+    inputs: &[(usize, DerivationPath)],
+    xprv: &ExtendedPrivKey,
+    script_type: ScriptType,
+    sighash: elements::EcdsaSigHashType,
+) -> Result<(), Error> {
+    let tx = pset.extract_tx()?;
+    let sighash = BESigHashType::from_u32(sighash as u32, true)?;
+    for (index, path) in inputs {
+        let value =
+            pset.inputs()[*index].witness_utxo.as_ref().map(|o| o.value).ok_or_else(|| {
+                Error::Generic(format!("missing the output spent by input {}", index))
+            })?;
+        let (script_sig, witness) =
+            internal_sign_elements(&tx, *index, xprv, path, value, script_type, &sighash)?;
+        let input = &mut pset.inputs_mut()[*index];
+        if !script_sig.is_empty() {
+            input.final_script_sig = Some(script_sig);
+        }
+        input.final_script_witness = Some(witness);
+    }
+    Ok(())
+}
+
+/// Add the signatures made by an external signer to the transaction inputs, after verifying them
+fn add_bitcoin_signatures(
+    tx: &mut bitcoin::Transaction,
+    signatures: &[Option<(PublicKey, Vec<u8>)>],
+    prevouts: &[bitcoin::TxOut],
+    script_type: ScriptType,
+    sighashes: &[BESigHashType],
+) -> Result<(), Error> {
+    let mut signed_tx = BETransaction::Bitcoin(tx.clone());
+    for (i, signature) in signatures.iter().enumerate() {
+        let (public_key, signature) = match signature {
+            Some(signature) => signature,
+            None => continue,
+        };
+        let expected_sighash = sighashes[i].into_bitcoin()?;
+        let sighash_matches = if script_type.is_taproot() {
+            signature.len() == 64 && expected_sighash == EcdsaSighashType::All
+        } else {
+            signature.last() == Some(&(expected_sighash as u8))
+        };
+        if !sighash_matches {
+            return Err(Error::InvalidSignerResponse(format!(
+                "unexpected sighash for input {}",
+                i
+            )));
+        }
+        let (script_sig, witness) = prepare_input(public_key, signature.clone(), script_type);
+        if let BETransaction::Bitcoin(ref mut signed_tx) = signed_tx {
+            signed_tx.input[i].script_sig = script_sig;
+            signed_tx.input[i].witness = Witness::from_vec(witness);
+        }
+        signed_tx
+            .verify_input_sig(
+                &crate::EC,
+                &mut None,
+                i,
+                public_key,
+                prevouts[i].value,
+                Some(prevouts),
+                script_type,
+            )
+            .map_err(|_| {
+                Error::InvalidSignerResponse(format!("invalid signature for input {}", i))
+            })?;
+    }
+    if let BETransaction::Bitcoin(signed_tx) = signed_tx {
+        *tx = signed_tx;
+    }
+    Ok(())
+}
+
+/// Add the signatures made by an external signer to the Elements transaction inputs, after
+/// verifying them against the values of the spent outputs
+fn add_elements_signatures(
+    tx: &mut elements::Transaction,
+    signatures: &[Option<(PublicKey, Vec<u8>)>],
+    values: &[Value],
+    script_type: ScriptType,
+    sighashes: &[BESigHashType],
+) -> Result<(), Error> {
+    let mut cache = elements::sighash::SigHashCache::new(&*tx);
+    for (i, signature) in signatures.iter().enumerate() {
+        let (public_key, signature) = match signature {
+            Some(signature) => signature,
+            None => continue,
+        };
+        let invalid = || Error::InvalidSignerResponse(format!("invalid signature for input {}", i));
+        let expected_sighash = sighashes[i].into_elements()?;
+        let (sighash, der) = signature.split_last().ok_or_else(invalid)?;
+        if *sighash != expected_sighash as u8 {
+            return Err(Error::InvalidSignerResponse(format!(
+                "unexpected sighash for input {}",
+                i
+            )));
+        }
+        let script_code = p2pkh_script(public_key).into_elements();
+        let hash = if script_type.is_segwit() {
+            cache.segwitv0_sighash(i, &script_code, values[i], expected_sighash)
+        } else {
+            cache.legacy_sighash(i, &script_code, expected_sighash)
+        };
+        let message = secp256k1::Message::from_slice(&hash[..]).unwrap();
+        let der = secp256k1::ecdsa::Signature::from_der(der).map_err(|_| invalid())?;
+        crate::EC.verify_ecdsa(&message, &der, &public_key.inner).map_err(|_| invalid())?;
+    }
+    for (i, signature) in signatures.iter().enumerate() {
+        if let Some((public_key, signature)) = signature {
+            let (script_sig, witness) = prepare_input(public_key, signature.clone(), script_type);
+            tx.input[i].script_sig = script_sig.into_elements();
+            tx.input[i].witness.script_witness = witness;
+        }
+    }
+    Ok(())
+}
+
 // Get the input's script sig and witness data
 fn prepare_input(
     public_key: &PublicKey,
@@ -1508,6 +3136,7 @@ fn prepare_input(
     match script_type {
         ScriptType::P2shP2wpkh => (p2shwpkh_script_sig(public_key), vec![signature, pk]),
         ScriptType::P2wpkh => (bitcoin::Script::new(), vec![signature, pk]),
+        ScriptType::P2tr => (bitcoin::Script::new(), vec![signature]),
         ScriptType::P2pkh => (
             script::Builder::new()
                 .push_slice(signature.as_slice())
@@ -1520,13 +3149,18 @@ fn prepare_input(
 
 fn blind_tx(account: &Account, tx: &elements::Transaction) -> Result<elements::Transaction, Error> {
     info!("blind_tx {}", tx.txid());
-    let is_already_blinded = tx.output.iter().all(|o| {
-        o.is_fee()
-            || (o.asset.is_confidential()
-                && o.value.is_confidential()
-                && o.nonce.is_confidential()
-                && !o.witness.is_empty())
-    });
+    // explicit outputs have no nonce and are left unblinded
+    let has_confidential_outputs = tx.output.iter().any(|o| o.nonce.is_confidential());
+    let is_already_blinded = has_confidential_outputs
+        && tx.output.iter().all(|o| {
+            o.is_fee()
+                || o.script_pubkey.is_provably_unspendable()
+                || o.nonce.is_null()
+                || (o.asset.is_confidential()
+                    && o.value.is_confidential()
+                    && o.nonce.is_confidential()
+                    && !o.witness.is_empty())
+        });
     if is_already_blinded {
         return Ok(tx.clone());
     }
@@ -1538,6 +3172,9 @@ fn blind_tx(account: &Account, tx: &elements::Transaction) -> Result<elements::T
     let mut inp_txout_sec: HashMap<usize, elements::TxOutSecrets> = HashMap::new();
 
     for (i, input) in pset.inputs_mut().iter_mut().enumerate() {
+        // PartiallySignedTransaction::from_tx sets the issuance flag in the output index,
+        // which would otherwise end up in the issuance ids and in the extracted outpoint
+        input.previous_output_index &= !(1 << 31);
         let previous_output =
             elements::OutPoint::new(input.previous_txid, input.previous_output_index);
         let unblinded = acc_store
@@ -1551,10 +3188,34 @@ fn blind_tx(account: &Account, tx: &elements::Transaction) -> Result<elements::T
         let txout = prev_tx.output[input.previous_output_index as usize].clone();
         input.witness_utxo = Some(txout);
     }
+    if !has_confidential_outputs {
+        // without blinded outputs the blinding factors of the inputs cannot be balanced
+        let explicit_inputs = inp_txout_sec.values().all(|s| {
+            s.asset_bf == elements::confidential::AssetBlindingFactor::zero()
+                && s.value_bf == elements::confidential::ValueBlindingFactor::zero()
+        });
+        if explicit_inputs {
+            return Ok(tx.clone());
+        }
+        return Err(Error::Generic(
+            "spending confidential inputs requires at least one confidential output".into(),
+        ));
+    }
     for output in pset.outputs_mut().iter_mut() {
         // We are the owner of all inputs and outputs
         output.blinder_index = Some(0);
     }
+    // Confidential issuance amounts are balanced by the last output blinding factor
+    for input in tx.input.iter().filter(|i| i.has_issuance()) {
+        let issuance = &input.asset_issuance;
+        if issuance.amount.is_confidential() || issuance.inflation_keys.is_confidential() {
+            let master_blinding =
+                account.master_blinding.as_ref().ok_or(Error::MissingMasterBlindingKey)?;
+            let blinding_key =
+                issuance::issuance_blinding_key(master_blinding, &input.previous_output);
+            pset.global.scalars.extend(issuance::issuance_value_blinders(input, blinding_key)?);
+        }
+    }
 
     pset.blind_last(&mut rand::thread_rng(), &crate::EC, &inp_txout_sec)?;
     pset.extract_tx().map_err(Into::into)
@@ -1563,6 +3224,10 @@ fn blind_tx(account: &Account, tx: &elements::Transaction) -> Result<elements::T
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::store::StoreMeta;
+    use bitcoin::util::bip32;
+    use gdk_common::model::CreateTxUtxo;
+    use std::sync::{Arc, RwLock};
 
     const NETWORK: NetworkId = NetworkId::Bitcoin(bitcoin::Network::Regtest);
 
@@ -1581,20 +3246,550 @@ mod test {
         test_derivation(0, ScriptType::P2shP2wpkh, "m/49'/1'/0'");
         test_derivation(1, ScriptType::P2wpkh, "m/84'/1'/0'");
         test_derivation(2, ScriptType::P2pkh, "m/44'/1'/0'");
+        test_derivation(3, ScriptType::P2tr, "m/86'/1'/0'");
 
         // reserved for future use, currently rejected
-        for n in 3..=15 {
+        for n in 4..=15 {
             test_derivation_fails(n);
         }
 
         test_derivation(16, ScriptType::P2shP2wpkh, "m/49'/1'/1'");
         test_derivation(17, ScriptType::P2wpkh, "m/84'/1'/1'");
         test_derivation(18, ScriptType::P2pkh, "m/44'/1'/1'");
-        test_derivation_fails(19);
+        test_derivation(19, ScriptType::P2tr, "m/86'/1'/1'");
+        test_derivation_fails(20);
 
         test_derivation(160, ScriptType::P2shP2wpkh, "m/49'/1'/10'");
         test_derivation(161, ScriptType::P2wpkh, "m/84'/1'/10'");
         test_derivation(162, ScriptType::P2pkh, "m/44'/1'/10'");
+        test_derivation(163, ScriptType::P2tr, "m/86'/1'/10'");
+
+        // taproot is not supported on liquid
+        let liquid = NetworkId::Elements(ElementsNetwork::ElementsRegtest);
+        assert!(get_account_derivation(3, liquid).is_err());
+    }
+
+    #[test]
+    fn taproot_bip86() {
+        // test vectors from BIP86
+        let master_xprv = ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap();
+        let path = DerivationPath::from_str("m/86'/0'/0'").unwrap();
+        let xprv = master_xprv.derive_priv(&crate::EC, &path).unwrap();
+        let xpub = ExtendedPubKey::from_priv(&crate::EC, &xprv);
+        assert_eq!(xpub.to_string(), "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ");
+        let network = NetworkId::Bitcoin(bitcoin::Network::Bitcoin);
+        let external = xpub.ckd_pub(&crate::EC, 0.into()).unwrap();
+        let internal = xpub.ckd_pub(&crate::EC, 1.into()).unwrap();
+        let address = |xpub, index| {
+            derive_address(xpub, index, ScriptType::P2tr, network, None).unwrap().to_string()
+        };
+        assert_eq!(
+            address(&external, 0),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+        assert_eq!(
+            address(&external, 1),
+            "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh"
+        );
+        assert_eq!(
+            address(&internal, 0),
+            "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7"
+        );
+
+        // sign a key path spend and verify it
+        let prev_script = bitcoin::Address::from_str(&address(&external, 0)).unwrap();
+        let prevouts = vec![bitcoin::TxOut {
+            value: 100_000,
+            script_pubkey: prev_script.script_pubkey(),
+        }];
+        let mut tx = BETransaction::new(network);
+        tx.add_input(BEOutPoint::Bitcoin(bitcoin::OutPoint::default()));
+        let tx = match tx {
+            BETransaction::Bitcoin(tx) => tx,
+            BETransaction::Elements(_) => unreachable!(),
+        };
+        let (script_sig, witness) = internal_sign_bitcoin(
+            &tx,
+            0,
+            &xprv,
+            &DerivationPath::from_str("m/0/0").unwrap(),
+            &prevouts,
+            ScriptType::P2tr,
+            &BESigHashType::Bitcoin(EcdsaSighashType::All),
+        )
+        .unwrap();
+        assert!(script_sig.is_empty());
+        assert_eq!(witness.len(), 1);
+        assert_eq!(witness[0].len(), 64);
+
+        let mut signed_tx = tx.clone();
+        signed_tx.input[0].witness = Witness::from_vec(witness);
+        let signed_tx = BETransaction::Bitcoin(signed_tx);
+        let public_key = external.ckd_pub(&crate::EC, 0.into()).unwrap().to_pub();
+        let verify = |prevouts| {
+            signed_tx.verify_input_sig(
+                &crate::EC,
+                &mut None,
+                0,
+                &public_key,
+                100_000,
+                prevouts,
+                ScriptType::P2tr,
+            )
+        };
+        verify(Some(&prevouts)).unwrap();
+        assert!(verify(None).is_err());
+        let mut wrong_prevouts = prevouts.clone();
+        wrong_prevouts[0].value += 1;
+        assert!(verify(Some(&wrong_prevouts)).is_err());
+    }
+
+    #[test]
+    fn descriptors() {
+        let master_xprv = ExtendedPrivKey::from_str("xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu").unwrap();
+        let mut network = NetworkParameters::default();
+        network.mainnet = true;
+        let dir = tempfile::TempDir::new().unwrap();
+        let master_xpub = ExtendedPubKey::from_priv(&crate::EC, &master_xprv);
+        let store = StoreMeta::new(dir.path(), &master_xpub, network.id()).unwrap();
+        let store = Arc::new(RwLock::new(store));
+
+        // test vector from BIP84
+        let xpub = "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V";
+        let key_info = format!("[73c5da0a/84'/0'/0']{}", xpub);
+        let key_origin = Some((master_xpub.fingerprint(), "m/84'/0'/0'".parse().unwrap()));
+        let account = Account::new(
+            network.clone(),
+            &Some(master_xprv),
+            &None,
+            key_origin.clone(),
+            None,
+            store.clone(),
+            1,
+            false,
+        )
+        .unwrap();
+        let descriptors = account.descriptors().unwrap();
+        let (external, checksum) = descriptors.external.split_once('#').unwrap();
+        assert_eq!(external, format!("wpkh({}/0/*)", key_info));
+        assert_eq!(checksum, descriptor_checksum(external).unwrap());
+        assert!(descriptors.internal.starts_with(&format!("wpkh({}/1/*)#", key_info)));
+        assert_eq!(descriptors.core_import.len(), 2);
+        assert!(descriptors.core_import[1].internal);
+        assert_eq!(descriptors.wallet_policy.descriptor_template, "wpkh(@0/**)");
+        assert_eq!(descriptors.wallet_policy.keys_info, vec![key_info]);
+
+        // external signers give the account xpub, the key origin comes from the master xpub
+        let account_xpub = Some(*account.xpub());
+        let account = Account::new(
+            network.clone(),
+            &None,
+            &account_xpub,
+            key_origin,
+            None,
+            store.clone(),
+            1,
+            false,
+        )
+        .unwrap();
+        assert_eq!(account.descriptors().unwrap().external, descriptors.external);
+
+        // watch-only accounts from descriptors keep their key origin
+        let origin = "[d34db33f/84'/0'/5']";
+        let fingerprint = bip32::Fingerprint::from_str("d34db33f").unwrap();
+        let key_origin = Some((fingerprint, "m/84'/0'/5'".parse().unwrap()));
+        let account = Account::new(
+            network.clone(),
+            &None,
+            &account_xpub,
+            key_origin,
+            None,
+            store.clone(),
+            0,
+            false,
+        )
+        .unwrap();
+        let descriptors = account.descriptors().unwrap();
+        let key_info = format!("{}{}", origin, account.xpub());
+        assert!(descriptors.external.starts_with(&format!("sh(wpkh({}/0/*))#", key_info)));
+        // and the psbt derivations
+        let path = "m/1/7".parse().unwrap();
+        let key_source = Some((fingerprint, "m/84'/0'/5'/1/7".parse().unwrap()));
+        assert_eq!(account.key_source(&path), key_source);
+
+        // the key origin of watch-only accounts is unknown otherwise
+        let xpub = ExtendedPubKey::from_str(xpub).unwrap();
+        let account =
+            Account::new(network, &None, &Some(xpub), None, None, store, 0, false).unwrap();
+        let descriptors = account.descriptors().unwrap();
+        assert!(descriptors.external.starts_with(&format!("sh(wpkh({}/0/*))#", xpub)));
+        assert_eq!(descriptors.wallet_policy.descriptor_template, "sh(wpkh(@0/**))");
+        assert_eq!(account.key_source(&path), None);
+    }
+
+    #[test]
+    fn gap_limit() {
+        let xpub = ExtendedPubKey::from_str("tpubD6NzVbkrYhZ4XYa9MoLt4BiMZ4gkt2faZ4BcmKu2a9te4LDpQmvEz2L2yDERivHxFPnxXXhqDRkUNnQCpZggCyEZLBktV7VaSmwayqMJy1s").unwrap();
+        let network = NetworkParameters::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = StoreMeta::new(dir.path(), &xpub, network.id()).unwrap();
+        let store = Arc::new(RwLock::new(store));
+        let account =
+            Account::new(network, &None, &Some(xpub), None, None, store, 0, false).unwrap();
+
+        assert_eq!(account.gap_limit().unwrap(), DEFAULT_GAP_LIMIT);
+        let update = |gap_limit| {
+            account.set_settings(UpdateAccountOpt {
+                gap_limit: Some(gap_limit),
+                ..Default::default()
+            })
+        };
+        assert!(update(0).is_err());
+        assert!(update(MAX_GAP_LIMIT + 1).is_err());
+        update(2).unwrap();
+        assert_eq!(account.gap_limit().unwrap(), 2);
+        assert_eq!(account.get_script_batch(false, 1, 2).unwrap().value.len(), 2);
+
+        // fresh addresses keep being handed out beyond the gap limit, they are never reused
+        let pointer = |is_internal| account.get_next_address(is_internal).unwrap().pointer;
+        let pointers: Vec<_> = (0..5).map(|_| pointer(false)).collect();
+        assert_eq!(pointers, vec![1, 2, 3, 4, 5]);
+        assert_eq!(account.unused_addresses(false).unwrap(), 5);
+        assert!(account.unused_addresses(false).unwrap() > account.gap_limit().unwrap());
+        assert_eq!(account.unused_addresses(true).unwrap(), 0);
+
+        // as after a sync finding the address at index 4 used
+        account.store.write().unwrap().account_cache_mut(0).unwrap().last_used.external = 4;
+        assert_eq!(account.unused_addresses(false).unwrap(), 1);
+        assert_eq!(pointer(false), 6);
+    }
+
+    #[test]
+    fn external_signatures() {
+        let xprv = ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPd7Uf69XL1XwhmjHopUGep8GuEiJDZmbQz6o58LninorQAfcKZWARbtRtfnLcJ5MQ2AtHcQJCCRUcMRvmDUjyEmNUWwx8UbK").unwrap();
+        let xpub = ExtendedPubKey::from_priv(&crate::EC, &xprv);
+        let path = DerivationPath::from_str("m/0/0").unwrap();
+        let public_key = xpub.derive_pub(&crate::EC, &path).unwrap().to_pub();
+        let script_type = ScriptType::P2wpkh;
+        let prevouts = vec![bitcoin::TxOut {
+            value: 100_000,
+            script_pubkey: bitcoin_address(&public_key, script_type, bitcoin::Network::Regtest)
+                .script_pubkey(),
+        }];
+        let mut tx = BETransaction::new(NETWORK);
+        tx.add_input(BEOutPoint::Bitcoin(bitcoin::OutPoint::default()));
+        let tx = match tx {
+            BETransaction::Bitcoin(tx) => tx,
+            BETransaction::Elements(_) => unreachable!(),
+        };
+        let sighashes = vec![BESigHashType::Bitcoin(EcdsaSighashType::All)];
+        let sign = |sighash: EcdsaSighashType, xprv: &ExtendedPrivKey| {
+            let (_, witness) = internal_sign_bitcoin(
+                &tx,
+                0,
+                xprv,
+                &path,
+                &prevouts,
+                script_type,
+                &BESigHashType::Bitcoin(sighash),
+            )
+            .unwrap();
+            vec![Some((public_key, witness[0].clone()))]
+        };
+        let add = |signatures: &[Option<(PublicKey, Vec<u8>)>]| {
+            let mut signed_tx = tx.clone();
+            add_bitcoin_signatures(&mut signed_tx, signatures, &prevouts, script_type, &sighashes)
+                .map(|_| signed_tx)
+        };
+
+        let signed_tx = add(&sign(EcdsaSighashType::All, &xprv)).unwrap();
+        assert_eq!(signed_tx.input[0].witness.len(), 2);
+
+        // an unsigned input is left untouched
+        assert_eq!(add(&[None]).unwrap(), tx);
+
+        // a different sighash than requested is rejected
+        assert!(add(&sign(EcdsaSighashType::Single, &xprv)).is_err());
+
+        // a signature made with another key is rejected
+        let other_xprv = xprv.ckd_priv(&crate::EC, 1.into()).unwrap();
+        assert!(add(&sign(EcdsaSighashType::All, &other_xprv)).is_err());
+    }
+
+    #[test]
+    fn sign_psbt_taproot_sighash() {
+        let master_xprv = ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPd7Uf69XL1XwhmjHopUGep8GuEiJDZmbQz6o58LninorQAfcKZWARbtRtfnLcJ5MQ2AtHcQJCCRUcMRvmDUjyEmNUWwx8UbK").unwrap();
+        let master_xpub = ExtendedPubKey::from_priv(&crate::EC, &master_xprv);
+        let network = NetworkParameters::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = StoreMeta::new(dir.path(), &master_xpub, network.id()).unwrap();
+        let store = Arc::new(RwLock::new(store));
+        let account =
+            Account::new(network, &Some(master_xprv), &None, None, None, store.clone(), 3, false)
+                .unwrap();
+        assert!(account.script_type.is_taproot());
+        let scripts = account.get_script_batch(false, 0, 1).unwrap().value;
+        let script_pubkey = scripts[0].0.clone().into_bitcoin();
+        store.write().unwrap().account_cache_mut(3).unwrap().paths.extend(scripts);
+
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime(0),
+            input: vec![Default::default()],
+            output: vec![bitcoin::TxOut {
+                value: 90_000,
+                script_pubkey: bitcoin::Script::new(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(bitcoin::TxOut {
+            value: 100_000,
+            script_pubkey,
+        });
+        let sign = |sighash: Option<SchnorrSighashType>| {
+            let mut psbt = psbt.clone();
+            psbt.inputs[0].sighash_type = sighash.map(Into::into);
+            account.sign_psbt(&mut psbt).map(|signed| (signed, psbt))
+        };
+
+        let (signed, signed_psbt) = sign(None).unwrap();
+        assert_eq!(signed, 1);
+        let signature = signed_psbt.inputs[0].tap_key_sig.unwrap();
+        assert_eq!(signature.hash_ty, SchnorrSighashType::Default);
+        assert_eq!(sign(Some(SchnorrSighashType::Default)).unwrap().0, 1);
+
+        // the other sighashes are not signed as SIGHASH_DEFAULT
+        assert!(sign(Some(SchnorrSighashType::All)).is_err());
+        assert!(sign(Some(SchnorrSighashType::SinglePlusAnyoneCanPay)).is_err());
+    }
+
+    #[test]
+    fn external_elements_signatures() {
+        let xprv = ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPd7Uf69XL1XwhmjHopUGep8GuEiJDZmbQz6o58LninorQAfcKZWARbtRtfnLcJ5MQ2AtHcQJCCRUcMRvmDUjyEmNUWwx8UbK").unwrap();
+        let xpub = ExtendedPubKey::from_priv(&crate::EC, &xprv);
+        let path = DerivationPath::from_str("m/0/0").unwrap();
+        let public_key = xpub.derive_pub(&crate::EC, &path).unwrap().to_pub();
+        let script_type = ScriptType::P2shP2wpkh;
+        let values = vec![Value::Explicit(100_000)];
+        let mut tx = BETransaction::new(NetworkId::Elements(ElementsNetwork::ElementsRegtest));
+        tx.add_input(BEOutPoint::Elements(elements::OutPoint::default()));
+        let tx = match tx {
+            BETransaction::Elements(tx) => tx,
+            BETransaction::Bitcoin(_) => unreachable!(),
+        };
+        let sighashes = vec![BESigHashType::Elements(elements::EcdsaSigHashType::All)];
+        let sign = |sighash: elements::EcdsaSigHashType, xprv: &ExtendedPrivKey, value: Value| {
+            let (_, witness) = internal_sign_elements(
+                &tx,
+                0,
+                xprv,
+                &path,
+                value,
+                script_type,
+                &BESigHashType::Elements(sighash),
+            )
+            .unwrap();
+            vec![Some((public_key, witness[0].clone()))]
+        };
+        let add = |signatures: &[Option<(PublicKey, Vec<u8>)>]| {
+            let mut signed_tx = tx.clone();
+            add_elements_signatures(&mut signed_tx, signatures, &values, script_type, &sighashes)
+                .map(|_| signed_tx)
+        };
+
+        let all = elements::EcdsaSigHashType::All;
+        let signed_tx = add(&sign(all, &xprv, values[0])).unwrap();
+        assert_eq!(signed_tx.input[0].witness.script_witness.len(), 2);
+        assert!(!signed_tx.input[0].script_sig.is_empty());
+
+        // an unsigned input is left untouched
+        assert_eq!(add(&[None]).unwrap(), tx);
+
+        // a different sighash than requested is rejected
+        assert!(add(&sign(elements::EcdsaSigHashType::Single, &xprv, values[0])).is_err());
+
+        // a signature made with another key or committing to another value is rejected
+        let other_xprv = xprv.ckd_priv(&crate::EC, 1.into()).unwrap();
+        assert!(add(&sign(all, &other_xprv, values[0])).is_err());
+        assert!(add(&sign(all, &xprv, Value::Explicit(99_999))).is_err());
+    }
+
+    #[test]
+    fn cpfp_child_fee_rate() {
+        let xpub = ExtendedPubKey::from_str("tpubD6NzVbkrYhZ4XYa9MoLt4BiMZ4gkt2faZ4BcmKu2a9te4LDpQmvEz2L2yDERivHxFPnxXXhqDRkUNnQCpZggCyEZLBktV7VaSmwayqMJy1s").unwrap();
+        let mut acc_store = RawAccountCache::new(xpub, false);
+        let script = bitcoin_address(&xpub.to_pub(), ScriptType::P2wpkh, bitcoin::Network::Regtest)
+            .script_pubkey();
+        let spend = |previous_output: bitcoin::OutPoint, value: u64| {
+            BETransaction::Bitcoin(bitcoin::Transaction {
+                version: 2,
+                lock_time: bitcoin::PackedLockTime(0),
+                input: vec![bitcoin::TxIn {
+                    previous_output,
+                    witness: ScriptType::P2wpkh.mock_witness(),
+                    ..Default::default()
+                }],
+                output: vec![bitcoin::TxOut {
+                    value,
+                    script_pubkey: script.clone(),
+                }],
+            })
+        };
+        let funding = spend(bitcoin::OutPoint::default(), 100_000);
+        let funding_outpoint = bitcoin::OutPoint::new(*funding.txid().ref_bitcoin().unwrap(), 0);
+        let parent = spend(funding_outpoint, 99_900); // 100 sat of fee
+        let parent_txid = parent.txid();
+        acc_store.all_txs.insert(funding.txid(), funding.into());
+        acc_store.all_txs.insert(parent_txid, parent.clone().into());
+
+        let mut child = BETransaction::new(NETWORK);
+        child.add_input(parent.outpoint(0));
+
+        // an unknown or confirmed parent cannot be bumped
+        let child_fee_rate = |acc_store: &RawAccountCache| {
+            cpfp_fee_rate(&child, &parent_txid, 10.0, ScriptType::P2wpkh, acc_store)
+        };
+        assert!(child_fee_rate(&acc_store).is_err());
+        acc_store.heights.insert(parent_txid, Some(100));
+        assert!(child_fee_rate(&acc_store).is_err());
+
+        acc_store.heights.insert(parent_txid, None);
+        let rate = child_fee_rate(&acc_store).unwrap();
+        let parent_vsize = weight_to_vsize(parent.get_weight()) as f64;
+        let child_fee = child.estimated_fee(rate, 1, ScriptType::P2wpkh) as f64;
+        let child_vsize = child_fee / rate;
+        let package_fee_rate = (child_fee + 100.0) / (parent_vsize + child_vsize);
+        assert!(rate > 10.0);
+        assert!(package_fee_rate >= 10.0 && package_fee_rate < 10.5, "{}", package_fee_rate);
+
+        // a parent already paying enough does not increase the child fee rate
+        assert_eq!(
+            cpfp_fee_rate(&child, &parent_txid, 0.5, ScriptType::P2wpkh, &acc_store).unwrap(),
+            0.5
+        );
+    }
+
+    /// An account with an unconfirmed transaction paying 50_000 and 49_900 satoshi to its first
+    /// two external addresses
+    fn funded_account(dir: &tempfile::TempDir) -> (Account, BETxid) {
+        let xpub = ExtendedPubKey::from_str("tpubD6NzVbkrYhZ4XYa9MoLt4BiMZ4gkt2faZ4BcmKu2a9te4LDpQmvEz2L2yDERivHxFPnxXXhqDRkUNnQCpZggCyEZLBktV7VaSmwayqMJy1s").unwrap();
+        let network = NetworkParameters::default();
+        let store = StoreMeta::new(dir.path(), &xpub, network.id()).unwrap();
+        let store = Arc::new(RwLock::new(store));
+        let account =
+            Account::new(network, &None, &Some(xpub), None, None, store.clone(), 0, false).unwrap();
+        let scripts = account.get_script_batch(false, 0, 2).unwrap().value;
+
+        let funding = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime(0),
+            input: vec![Default::default()],
+            output: vec![bitcoin::TxOut {
+                value: 100_000,
+                script_pubkey: bitcoin::Script::new(),
+            }],
+        };
+        let parent = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime(0),
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::new(funding.txid(), 0),
+                witness: account.script_type.mock_witness(),
+                ..Default::default()
+            }],
+            output: [50_000, 49_900]
+                .iter()
+                .zip(scripts.iter())
+                .map(|(value, (script, _))| bitcoin::TxOut {
+                    value: *value,
+                    script_pubkey: script.clone().into_bitcoin(),
+                })
+                .collect(),
+        };
+        let funding = BETransaction::Bitcoin(funding);
+        let parent = BETransaction::Bitcoin(parent);
+        let parent_txid = parent.txid();
+
+        let mut store = store.write().unwrap();
+        let acc_store = store.account_cache_mut(0).unwrap();
+        acc_store.paths.extend(scripts);
+        acc_store.heights.insert(funding.txid(), Some(1));
+        acc_store.all_txs.insert(funding.txid(), funding.into());
+        acc_store.heights.insert(parent_txid, None);
+        acc_store.all_txs.insert(parent_txid, parent.into());
+        drop(store);
+
+        (account, parent_txid)
+    }
+
+    fn used_vouts(tx: TransactionMeta) -> Vec<u32> {
+        let mut vouts: Vec<_> = tx.used_utxos.iter().map(|u| u.pt_idx).collect();
+        vouts.sort();
+        vouts
+    }
+
+    #[test]
+    fn frozen_utxos() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (account, parent_txid) = funded_account(&dir);
+        let address = account.derive_address(false, 10).unwrap().to_string();
+        let utxos: Vec<_> = (0..2)
+            .map(|vout| CreateTxUtxo {
+                txid: parent_txid.to_hex(),
+                vout,
+            })
+            .collect();
+        let create_tx = |satoshi, utxo_strategy| {
+            let mut request = CreateTransaction {
+                addressees: vec![AddressAmount {
+                    address: address.clone(),
+                    satoshi,
+                    asset_id: None,
+                    ..Default::default()
+                }],
+                utxos: HashMap::from([("btc".to_string(), utxos.clone())]),
+                utxo_strategy,
+                ..Default::default()
+            };
+            account.create_tx(&mut request)
+        };
+        assert_eq!(used_vouts(create_tx(60_000, UtxoStrategy::Default).unwrap()), vec![0, 1]);
+
+        account
+            .store
+            .write()
+            .unwrap()
+            .set_utxo_frozen(&BEOutPoint::new(parent_txid, 0), true)
+            .unwrap();
+        assert_eq!(used_vouts(create_tx(45_000, UtxoStrategy::Default).unwrap()), vec![1]);
+        assert!(matches!(create_tx(60_000, UtxoStrategy::Default), Err(Error::InsufficientFunds)));
+
+        // frozen utxos are spent only if explicitly requested
+        assert_eq!(used_vouts(create_tx(60_000, UtxoStrategy::Manual).unwrap()), vec![0, 1]);
+    }
+
+    #[test]
+    fn cpfp_frozen_utxos() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (account, parent_txid) = funded_account(&dir);
+        let cpfp = || {
+            let mut request = CreateTransaction {
+                cpfp_txid: Some(parent_txid.to_hex()),
+                fee_rate: Some(10_000),
+                ..Default::default()
+            };
+            account.create_tx(&mut request)
+        };
+        assert_eq!(used_vouts(cpfp().unwrap()), vec![0, 1]);
+
+        let freeze = |vout| {
+            let outpoint = BEOutPoint::new(parent_txid, vout);
+            account.store.write().unwrap().set_utxo_frozen(&outpoint, true).unwrap();
+        };
+        freeze(0);
+        assert_eq!(used_vouts(cpfp().unwrap()), vec![1]);
+        freeze(1);
+        assert!(matches!(cpfp(), Err(Error::InvalidCpfpRequest)));
     }
 
     #[test]
@@ -1612,4 +3807,55 @@ mod test {
         xpubs_equivalent(&t, &l).unwrap();
         assert!(xpubs_equivalent(&j, &o).is_err());
     }
+
+    #[test]
+    fn explicit_outputs() {
+        let mut network = NetworkParameters::default();
+        network.liquid = true;
+        network.development = true;
+        network.policy_asset =
+            Some("5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225".into());
+        let public_key = PublicKey::from_str(
+            "0283409659355b6d1cc3c32decd5d561abaac86c37a353b52895a5e6c196d6f448",
+        )
+        .unwrap();
+        let params = &elements::AddressParams::ELEMENTS;
+        let confidential = elements::Address::p2wpkh(&public_key, Some(public_key.inner), params);
+        let unconfidential = confidential.to_unconfidential();
+        let addressee = |address: &elements::Address, is_explicit| AddressAmount {
+            address: address.to_string(),
+            satoshi: 1000,
+            asset_id: network.policy_asset.clone(),
+            is_explicit,
+            ..Default::default()
+        };
+
+        let mut tx = BETransaction::new(network.id());
+        add_addressee(&mut tx, &addressee(&confidential, false), &network).unwrap();
+        assert!(matches!(
+            add_addressee(&mut tx, &addressee(&unconfidential, false), &network),
+            Err(Error::InvalidAddress)
+        ));
+        add_addressee(&mut tx, &addressee(&confidential, true), &network).unwrap();
+        add_addressee(&mut tx, &addressee(&unconfidential, true), &network).unwrap();
+        let tx = match tx {
+            BETransaction::Elements(tx) => tx,
+            BETransaction::Bitcoin(_) => unreachable!(),
+        };
+        let nonces: Vec<_> = tx.output.iter().map(|o| o.nonce.is_confidential()).collect();
+        assert_eq!(nonces, vec![true, false, false]);
+        assert!(tx.output.iter().all(|o| o.script_pubkey == unconfidential.script_pubkey()));
+
+        network.liquid = false;
+        let mut tx = BETransaction::new(network.id());
+        let address = bitcoin::Address::p2wpkh(&public_key, bitcoin::Network::Regtest).unwrap();
+        let mut addressee = AddressAmount {
+            address: address.to_string(),
+            satoshi: 1000,
+            ..Default::default()
+        };
+        add_addressee(&mut tx, &addressee, &network).unwrap();
+        addressee.is_explicit = true;
+        assert!(matches!(add_addressee(&mut tx, &addressee, &network), Err(Error::Generic(_))));
+    }
 }
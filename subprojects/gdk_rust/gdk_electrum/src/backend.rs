@@ -0,0 +1,272 @@
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{BlockHeader, Script, Txid};
+use electrum_client::{
+    Client, ElectrumApi, GetHistoryRes, GetMerkleRes, ListUnspentRes, RawHeaderNotification,
+};
+
+use gdk_common::network::RpcCredentials;
+
+use crate::error::Error;
+use crate::esplora::EsploraClient;
+use crate::interface::ElectrumUrl;
+use crate::pool::ElectrumPool;
+use crate::rpc::RpcClient;
+
+/// The server providing the blockchain data to a session
+#[derive(Clone, Debug)]
+pub enum BackendUrl {
+    Electrum(ElectrumUrl),
+    /// Electrum servers to fail over to, in order of preference
+    ElectrumPool(ElectrumPool),
+    /// The base url of an Esplora REST API, without the trailing slash
+    Esplora(String),
+    /// The url of a bitcoind JSON-RPC server, the wallet name and the server credentials
+    Rpc(String, String, Option<RpcCredentials>),
+}
+
+impl BackendUrl {
+    pub fn build_client(
+        &self,
+        proxy: Option<&str>,
+        timeout: Option<u8>,
+    ) -> Result<Box<dyn Backend>, Error> {
+        Ok(match self {
+            BackendUrl::Electrum(url) => Box::new(url.build_client(proxy, timeout)?),
+            BackendUrl::ElectrumPool(pool) => Box::new(pool.build_client(proxy, timeout)?),
+            BackendUrl::Esplora(url) => Box::new(EsploraClient::new(url, proxy, timeout)?),
+            BackendUrl::Rpc(url, wallet, credentials) => {
+                Box::new(RpcClient::new(url, wallet, credentials.as_ref(), proxy, timeout)?)
+            }
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        match self {
+            BackendUrl::Electrum(url) => url.url(),
+            BackendUrl::ElectrumPool(pool) => pool.url(),
+            BackendUrl::Esplora(url) => url,
+            BackendUrl::Rpc(url, ..) => url,
+        }
+    }
+}
+
+/// The blockchain queries a session needs, with the semantics of the electrum protocol
+///
+/// Fee rates are in BTC/kB, as returned by electrum servers.
+pub trait Backend: Send {
+    fn ping(&self) -> Result<(), Error>;
+
+    /// The minimum fee rate to relay a transaction
+    fn relay_fee(&self) -> Result<f64, Error>;
+
+    /// The fee rate to confirm in each number of `blocks`, negative if not available
+    fn batch_estimate_fee(&self, blocks: &[usize]) -> Result<Vec<f64>, Error>;
+
+    /// The height and the raw header of the tip of the best chain
+    fn tip(&self) -> Result<RawHeaderNotification, Error>;
+
+    fn block_header_raw(&self, height: usize) -> Result<Vec<u8>, Error>;
+
+    fn batch_block_header_raw(&self, heights: &[u32]) -> Result<Vec<Vec<u8>>, Error>;
+
+    /// At most `count` bitcoin headers starting from `start_height`
+    fn block_headers(&self, start_height: usize, count: usize) -> Result<Vec<BlockHeader>, Error>;
+
+    fn batch_script_get_history(
+        &self,
+        scripts: &[Script],
+    ) -> Result<Vec<Vec<GetHistoryRes>>, Error>;
+
+    fn batch_script_list_unspent(
+        &self,
+        scripts: &[Script],
+    ) -> Result<Vec<Vec<ListUnspentRes>>, Error>;
+
+    fn batch_transaction_get_raw(&self, txids: &[Txid]) -> Result<Vec<Vec<u8>>, Error>;
+
+    fn transaction_get_merkle(&self, txid: &Txid, height: usize) -> Result<GetMerkleRes, Error>;
+
+    fn transaction_broadcast_raw(&self, raw_tx: &[u8]) -> Result<Txid, Error>;
+
+    /// Called at the start of every sync, to drop the state valid only within a single sync
+    fn begin_sync(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Make the server watch the scripts of the given descriptors up to the paired index, needed
+    /// only by servers without a script index
+    fn import_descriptors(&self, _descriptors: &[(String, u32)]) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Whether the server notifies the new statuses of the subscribed scripts and the new tips
+    fn notifies(&self) -> bool {
+        false
+    }
+
+    /// The status hashes of `scripts`, subscribing to their changes if the server notifies them
+    ///
+    /// Servers without notifications compute the status hashes from the histories, which are
+    /// returned too so that they are not downloaded again.
+    fn batch_script_subscribe(&self, scripts: &[Script]) -> Result<Vec<ScriptStatus>, Error> {
+        let histories = self.batch_script_get_history(scripts)?;
+        Ok(histories
+            .into_iter()
+            .map(|history| ScriptStatus {
+                status: script_status(&history),
+                history: Some(history),
+            })
+            .collect())
+    }
+
+    /// The last status hash notified for the subscribed `script` since the previous call, if any
+    fn script_pop(&self, _script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        Ok(None)
+    }
+
+    /// The last tip notified since the previous call, if any
+    fn tip_pop(&self) -> Result<Option<RawHeaderNotification>, Error> {
+        Ok(None)
+    }
+}
+
+/// The status of a script, as returned by [`Backend::batch_script_subscribe`]
+#[derive(Debug)]
+pub struct ScriptStatus {
+    /// `None` if without history
+    pub status: Option<sha256::Hash>,
+    /// The history, if it was downloaded to compute the status
+    pub history: Option<Vec<GetHistoryRes>>,
+}
+
+/// The status hash of a script with the given history, as defined by the electrum protocol
+pub fn script_status(history: &[GetHistoryRes]) -> Option<sha256::Hash> {
+    if history.is_empty() {
+        return None;
+    }
+    // confirmed transactions by height, then unconfirmed ones
+    let mut history: Vec<_> = history.iter().collect();
+    history.sort_by_key(|h| {
+        if h.height > 0 {
+            h.height
+        } else {
+            i32::MAX
+        }
+    });
+    let status: String = history.iter().map(|h| format!("{}:{}:", h.tx_hash, h.height)).collect();
+    Some(sha256::Hash::hash(status.as_bytes()))
+}
+
+impl Backend for Client {
+    fn ping(&self) -> Result<(), Error> {
+        Ok(ElectrumApi::ping(self)?)
+    }
+
+    fn relay_fee(&self) -> Result<f64, Error> {
+        Ok(ElectrumApi::relay_fee(self)?)
+    }
+
+    fn batch_estimate_fee(&self, blocks: &[usize]) -> Result<Vec<f64>, Error> {
+        Ok(ElectrumApi::batch_estimate_fee(self, blocks.iter().cloned())?)
+    }
+
+    fn tip(&self) -> Result<RawHeaderNotification, Error> {
+        Ok(self.block_headers_subscribe_raw()?)
+    }
+
+    fn block_header_raw(&self, height: usize) -> Result<Vec<u8>, Error> {
+        Ok(ElectrumApi::block_header_raw(self, height)?)
+    }
+
+    fn batch_block_header_raw(&self, heights: &[u32]) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(ElectrumApi::batch_block_header_raw(self, heights.iter().cloned())?)
+    }
+
+    fn block_headers(&self, start_height: usize, count: usize) -> Result<Vec<BlockHeader>, Error> {
+        Ok(ElectrumApi::block_headers(self, start_height, count)?.headers)
+    }
+
+    fn batch_script_get_history(
+        &self,
+        scripts: &[Script],
+    ) -> Result<Vec<Vec<GetHistoryRes>>, Error> {
+        Ok(ElectrumApi::batch_script_get_history(self, scripts.iter())?)
+    }
+
+    fn batch_script_list_unspent(
+        &self,
+        scripts: &[Script],
+    ) -> Result<Vec<Vec<ListUnspentRes>>, Error> {
+        Ok(ElectrumApi::batch_script_list_unspent(self, scripts.iter())?)
+    }
+
+    fn batch_transaction_get_raw(&self, txids: &[Txid]) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(ElectrumApi::batch_transaction_get_raw(self, txids.iter())?)
+    }
+
+    fn transaction_get_merkle(&self, txid: &Txid, height: usize) -> Result<GetMerkleRes, Error> {
+        Ok(ElectrumApi::transaction_get_merkle(self, txid, height)?)
+    }
+
+    fn transaction_broadcast_raw(&self, raw_tx: &[u8]) -> Result<Txid, Error> {
+        Ok(ElectrumApi::transaction_broadcast_raw(self, raw_tx)?)
+    }
+
+    fn notifies(&self) -> bool {
+        true
+    }
+
+    fn batch_script_subscribe(&self, scripts: &[Script]) -> Result<Vec<ScriptStatus>, Error> {
+        // the electrum protocol has no batch subscription
+        let mut statuses = Vec::with_capacity(scripts.len());
+        for script in scripts {
+            let status = self.script_subscribe(script)?;
+            statuses.push(ScriptStatus {
+                status: status.map(|s| sha256::Hash::from_inner(*s)),
+                history: None,
+            });
+        }
+        Ok(statuses)
+    }
+
+    fn script_pop(&self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let mut last = None;
+        while let Some(status) = ElectrumApi::script_pop(self, script)? {
+            last = Some(sha256::Hash::from_inner(*status));
+        }
+        Ok(last)
+    }
+
+    fn tip_pop(&self) -> Result<Option<RawHeaderNotification>, Error> {
+        let mut last = None;
+        while let Some(header) = self.block_headers_pop_raw()? {
+            last = Some(header);
+        }
+        Ok(last)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn history(heights: &[i32]) -> Vec<GetHistoryRes> {
+        heights
+            .iter()
+            .map(|height| GetHistoryRes {
+                height: *height,
+                tx_hash: Txid::hash(&height.to_le_bytes()),
+                fee: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_script_status() {
+        assert_eq!(script_status(&[]), None);
+        let status = script_status(&history(&[10, 0, 2])).unwrap();
+        assert_eq!(script_status(&history(&[2, 10, 0])), Some(status));
+        assert_ne!(script_status(&history(&[2, 10])), Some(status));
+        assert_ne!(script_status(&history(&[2, 11, 0])), Some(status));
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use gdk_common::be::BEScript;
+use gdk_common::model::Txo;
+
+/// Maximum number of branches explored by `branch_and_bound`, as in Bitcoin Core
+const BNB_TOTAL_TRIES: usize = 100_000;
+
+/// A set of utxos that must be spent together
+#[derive(Debug)]
+pub struct OutputGroup<'a> {
+    pub utxos: Vec<&'a Txo>,
+}
+
+impl<'a> OutputGroup<'a> {
+    pub fn value(&self) -> u64 {
+        self.utxos.iter().map(|u| u.satoshi).sum()
+    }
+
+    pub fn asset_id(&self) -> Option<elements::issuance::AssetId> {
+        self.utxos[0].asset_id()
+    }
+
+    /// The height of the oldest utxo in the group, unconfirmed utxos are considered the newest
+    pub fn height(&self) -> u32 {
+        self.utxos.iter().filter_map(|u| u.height).filter(|h| *h > 0).min().unwrap_or(u32::MAX)
+    }
+}
+
+/// Group the utxos, if `by_script` utxos with the same script end up in the same group,
+/// otherwise every utxo has its own group
+pub fn group_utxos(utxos: &[Txo], by_script: bool) -> Vec<OutputGroup<'_>> {
+    let mut groups: Vec<OutputGroup> = vec![];
+    let mut positions: HashMap<&BEScript, usize> = HashMap::new();
+    for utxo in utxos {
+        if by_script {
+            if let Some(pos) = positions.get(&utxo.script_pubkey) {
+                groups[*pos].utxos.push(utxo);
+                continue;
+            }
+            positions.insert(&utxo.script_pubkey, groups.len());
+        }
+        groups.push(OutputGroup {
+            utxos: vec![utxo],
+        });
+    }
+    groups
+}
+
+/// A coin selection candidate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// The value of the candidate minus the fee to spend it at the current fee rate
+    pub effective_value: u64,
+
+    /// The fee to spend the candidate at the current fee rate minus the fee to spend it at the
+    /// long term fee rate, negative when fees are lower than expected in the long term
+    pub waste: i64,
+}
+
+/// Compute the waste metric of `selection`
+///
+/// The waste is the sum of the candidates waste plus the cost of creating and later spending
+/// the change if `cost_of_change` is some, or the excess given to miners otherwise.
+pub fn waste(
+    candidates: &[Candidate],
+    selection: &[usize],
+    target: u64,
+    cost_of_change: Option<u64>,
+) -> i64 {
+    let inputs_waste: i64 = selection.iter().map(|i| candidates[*i].waste).sum();
+    let selected: u64 = selection.iter().map(|i| candidates[*i].effective_value).sum();
+    let excess = cost_of_change.unwrap_or_else(|| selected.saturating_sub(target));
+    inputs_waste + excess as i64
+}
+
+/// Search for a changeless selection whose effective value is in `target..=target+cost_of_change`
+///
+/// Depth first search over the inclusion/omission branches of the candidates sorted by
+/// descending effective value, keeping the solution with the lowest waste.
+/// Returns `None` if no solution is found within `BNB_TOTAL_TRIES` tries.
+pub fn branch_and_bound(
+    candidates: &[Candidate],
+    target: u64,
+    cost_of_change: u64,
+) -> Option<Vec<usize>> {
+    let mut pool: Vec<usize> = (0..candidates.len()).collect();
+    pool.sort_by(|a, b| candidates[*b].effective_value.cmp(&candidates[*a].effective_value));
+
+    let mut available: u64 = candidates.iter().map(|c| c.effective_value).sum();
+    if available < target {
+        return None;
+    }
+    // when fees are higher than in the long term, adding inputs can only increase the waste
+    let fee_rate_high = candidates.iter().all(|c| c.waste > 0);
+
+    let mut selection: Vec<usize> = vec![];
+    let mut value = 0u64;
+    let mut curr_waste = 0i64;
+    let mut best: Option<(Vec<usize>, i64)> = None;
+    let mut index = 0usize;
+
+    for _ in 0..BNB_TOTAL_TRIES {
+        let best_waste = best.as_ref().map_or(i64::MAX, |(_, w)| *w);
+        let mut backtrack = false;
+        if value + available < target
+            || value > target + cost_of_change
+            || (curr_waste > best_waste && fee_rate_high)
+        {
+            backtrack = true;
+        } else if value >= target {
+            let solution_waste = curr_waste + (value - target) as i64;
+            if solution_waste <= best_waste {
+                best = Some((selection.iter().map(|i| pool[*i]).collect(), solution_waste));
+            }
+            backtrack = true;
+        }
+
+        if backtrack {
+            let last = match selection.last() {
+                Some(last) => *last,
+                None => break, // every branch has been explored
+            };
+            // add back the omitted candidates before trying the omission branch of the last one
+            index -= 1;
+            while index > last {
+                available += candidates[pool[index]].effective_value;
+                index -= 1;
+            }
+            let candidate = &candidates[pool[index]];
+            value -= candidate.effective_value;
+            curr_waste -= candidate.waste;
+            selection.pop();
+        } else {
+            let candidate = &candidates[pool[index]];
+            available -= candidate.effective_value;
+            // skip the inclusion branch if an equivalent previous candidate has been omitted
+            let equivalent_omitted = index > 0
+                && selection.last() != Some(&(index - 1))
+                && candidates[pool[index - 1]] == *candidate;
+            if !equivalent_omitted {
+                selection.push(index);
+                value += candidate.effective_value;
+                curr_waste += candidate.waste;
+            }
+        }
+        index += 1;
+    }
+
+    best.map(|(selection, _)| selection)
+}
+
+/// Add the candidates in the given `order` until `target_with_change` is reached
+///
+/// Returns the selection and whether it creates a change (greater than `dust`).
+pub fn accumulate(
+    candidates: &[Candidate],
+    order: &[usize],
+    target_with_change: u64,
+    dust: u64,
+) -> Option<(Vec<usize>, bool)> {
+    let mut selection = vec![];
+    let mut value = 0u64;
+    for i in order {
+        if value >= target_with_change {
+            break;
+        }
+        selection.push(*i);
+        value += candidates[*i].effective_value;
+    }
+    if value < target_with_change {
+        return None;
+    }
+    Some((selection, value - target_with_change > dust))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn candidates(values: &[u64], waste: i64) -> Vec<Candidate> {
+        values
+            .iter()
+            .map(|v| Candidate {
+                effective_value: *v,
+                waste,
+            })
+            .collect()
+    }
+
+    fn sum(candidates: &[Candidate], selection: &[usize]) -> u64 {
+        selection.iter().map(|i| candidates[*i].effective_value).sum()
+    }
+
+    #[test]
+    fn test_branch_and_bound() {
+        let c = candidates(&[1_000, 2_000, 3_000, 4_000, 50_000], 10);
+
+        // exact match
+        let selection = branch_and_bound(&c, 5_000, 0).unwrap();
+        assert_eq!(sum(&c, &selection), 5_000);
+        assert_eq!(selection.len(), 2, "fewer inputs have lower waste at high fee rates");
+
+        // match within the cost of change
+        let selection = branch_and_bound(&c, 9_950, 100).unwrap();
+        assert_eq!(sum(&c, &selection), 10_000);
+        let selection = branch_and_bound(&c, 9_950, 0);
+        assert!(selection.is_none());
+
+        // not enough funds
+        assert!(branch_and_bound(&c, 60_001, 1_000).is_none());
+
+        // equivalent candidates
+        let c = candidates(&[1_000; 30], 10);
+        let selection = branch_and_bound(&c, 29_000, 0).unwrap();
+        assert_eq!(selection.len(), 29);
+        assert!(branch_and_bound(&c, 29_500, 0).is_none());
+    }
+
+    #[test]
+    fn test_branch_and_bound_waste() {
+        // at low fee rates spending more inputs now reduces the waste
+        let c = candidates(&[1_000, 2_000, 3_000], -10);
+        let selection = branch_and_bound(&c, 3_000, 0).unwrap();
+        assert_eq!(selection.len(), 2);
+
+        let c = candidates(&[1_000, 2_000, 3_000], 10);
+        let selection = branch_and_bound(&c, 3_000, 0).unwrap();
+        assert_eq!(selection, vec![2]);
+    }
+
+    #[test]
+    fn test_accumulate_and_waste() {
+        let c = candidates(&[5_000, 1_000, 2_000], 10);
+        let (selection, change) = accumulate(&c, &[0, 2, 1], 6_000, 546).unwrap();
+        assert_eq!(selection, vec![0, 2]);
+        assert!(change);
+        assert_eq!(waste(&c, &selection, 5_500, Some(300)), 20 + 300);
+
+        let (selection, change) = accumulate(&c, &[1, 2, 0], 3_000, 546).unwrap();
+        assert_eq!(selection, vec![1, 2]);
+        assert!(!change);
+        assert_eq!(waste(&c, &selection, 2_900, None), 20 + 100);
+
+        assert!(accumulate(&c, &[0, 1, 2], 9_000, 546).is_none());
+    }
+}
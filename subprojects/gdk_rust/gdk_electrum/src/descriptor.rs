@@ -0,0 +1,269 @@
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::util::base58;
+use bitcoin::util::bip32::{DerivationPath, ExtendedPubKey, Fingerprint, KeySource};
+use gdk_common::scripts::ScriptType;
+use gdk_common::wally::MasterBlindingKey;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// SLIP-132 extended public key versions (version, mainnet, script type)
+const SLIP132_VERSIONS: [([u8; 4], bool, ScriptType); 6] = [
+    ([0x04, 0x88, 0xb2, 0x1e], true, ScriptType::P2pkh), // xpub
+    ([0x04, 0x9d, 0x7c, 0xb2], true, ScriptType::P2shP2wpkh), // ypub
+    ([0x04, 0xb2, 0x47, 0x46], true, ScriptType::P2wpkh), // zpub
+    ([0x04, 0x35, 0x87, 0xcf], false, ScriptType::P2pkh), // tpub
+    ([0x04, 0x4a, 0x52, 0x62], false, ScriptType::P2shP2wpkh), // upub
+    ([0x04, 0x5f, 0x1c, 0xf6], false, ScriptType::P2wpkh), // vpub
+];
+
+fn poly_mod(mut c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    c = ((c & 0x7ffffffff) << 5) ^ val;
+    for (i, generator) in
+        [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd].iter().enumerate()
+    {
+        if c0 & (1 << i) != 0 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// Compute the BIP380 checksum of a descriptor without checksum
+pub fn descriptor_checksum(desc: &str) -> Result<String, Error> {
+    let mut c = 1;
+    let mut cls = 0;
+    let mut cls_count = 0;
+    for ch in desc.chars() {
+        let pos = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| Error::InvalidDescriptor(format!("invalid character {:?}", ch)))?
+            as u64;
+        c = poly_mod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        cls_count += 1;
+        if cls_count == 3 {
+            c = poly_mod(c, cls);
+            cls = 0;
+            cls_count = 0;
+        }
+    }
+    if cls_count > 0 {
+        c = poly_mod(c, cls);
+    }
+    for _ in 0..8 {
+        c = poly_mod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8).map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char).collect())
+}
+
+/// Parse a SLIP-132 extended public key (xpub, ypub, zpub and the testnet counterparts)
+///
+/// Returns the key with the standard xpub/tpub version, whether it is for mainnet and the
+/// script type implied by its version.
+pub fn parse_slip132_extended_pubkey(s: &str) -> Result<(ExtendedPubKey, bool, ScriptType), Error> {
+    let mut data = base58::from_check(s)
+        .map_err(|_| Error::InvalidExtendedPubKey(format!("cannot decode {}", s)))?;
+    if data.len() != 78 {
+        return Err(Error::InvalidExtendedPubKey(format!("invalid length {}", data.len())));
+    }
+    let (_, mainnet, script_type) = SLIP132_VERSIONS
+        .iter()
+        .find(|(version, _, _)| version[..] == data[0..4])
+        .ok_or_else(|| Error::InvalidExtendedPubKey(format!("unknown version of {}", s)))?;
+    let standard_version = if *mainnet {
+        SLIP132_VERSIONS[0].0
+    } else {
+        SLIP132_VERSIONS[3].0
+    };
+    data[0..4].copy_from_slice(&standard_version);
+    Ok((ExtendedPubKey::decode(&data)?, *mainnet, *script_type))
+}
+
+fn strip_checksum(desc: &str) -> Result<&str, Error> {
+    let desc = desc.trim();
+    match desc.split_once('#') {
+        Some((desc, checksum)) => {
+            if descriptor_checksum(desc)? != checksum {
+                return Err(Error::InvalidDescriptor("checksum mismatch".into()));
+            }
+            Ok(desc)
+        }
+        None => Ok(desc),
+    }
+}
+
+/// Parse a single sig descriptor of an account
+///
+/// Supported descriptors are `pkh(KEY)`, `wpkh(KEY)`, `sh(wpkh(KEY))` and `tr(KEY)`, where `KEY`
+/// is an xpub or a tpub with an optional key origin and optionally followed by `/0/*`, `/1/*` or
+/// `/<0;1>/*`. If present, the checksum is verified. The key origin is returned if present.
+pub fn parse_single_sig_descriptor(
+    desc: &str,
+) -> Result<(ExtendedPubKey, Option<KeySource>, ScriptType), Error> {
+    parse_single_sig(strip_checksum(desc)?)
+}
+
+/// Parse an ELIP-150 confidential descriptor of a Liquid account
+///
+/// Supported descriptors are `ct(slip77(MASTER_BLINDING_KEY),DESC)`, where `DESC` is
+/// `elpkh(KEY)`, `elwpkh(KEY)` or `elsh(wpkh(KEY))` with `KEY` as in
+/// [`parse_single_sig_descriptor`]. The SLIP-77 key is returned as a gdk master blinding key.
+pub fn parse_ct_descriptor(
+    desc: &str,
+) -> Result<(MasterBlindingKey, ExtendedPubKey, Option<KeySource>, ScriptType), Error> {
+    let desc = strip_checksum(desc)?;
+    let inner = desc.strip_prefix("ct(").and_then(|d| d.strip_suffix(')')).ok_or_else(|| {
+        Error::InvalidDescriptor(format!("not a confidential descriptor {}", desc))
+    })?;
+    let (slip77, inner) =
+        inner.strip_prefix("slip77(").and_then(|d| d.split_once("),")).ok_or_else(|| {
+            Error::InvalidDescriptor("only slip77 blinding keys are supported".into())
+        })?;
+    let slip77 = <[u8; 32]>::from_hex(slip77)
+        .map_err(|_| Error::InvalidDescriptor("invalid slip77 blinding key".into()))?;
+    // gdk master blinding keys are 64 bytes, of which only the second half is the SLIP-77 key
+    let mut master_blinding_key = [0u8; 64];
+    master_blinding_key[32..].copy_from_slice(&slip77);
+
+    let inner = inner
+        .strip_prefix("el")
+        .ok_or_else(|| Error::InvalidDescriptor(format!("not an elements descriptor {}", inner)))?;
+    let (xpub, origin, script_type) = parse_single_sig(inner)?;
+    if script_type == ScriptType::P2tr {
+        return Err(Error::InvalidDescriptor("taproot is not supported on liquid".into()));
+    }
+    Ok((MasterBlindingKey(master_blinding_key), xpub, origin, script_type))
+}
+
+fn parse_single_sig(desc: &str) -> Result<(ExtendedPubKey, Option<KeySource>, ScriptType), Error> {
+    let (script_type, key) = [
+        (ScriptType::P2shP2wpkh, "sh(wpkh(", "))"),
+        (ScriptType::P2wpkh, "wpkh(", ")"),
+        (ScriptType::P2pkh, "pkh(", ")"),
+        (ScriptType::P2tr, "tr(", ")"),
+    ]
+    .iter()
+    .find_map(|(script_type, prefix, suffix)| {
+        desc.strip_prefix(prefix)?.strip_suffix(suffix).map(|key| (*script_type, key))
+    })
+    .ok_or_else(|| Error::InvalidDescriptor(format!("unsupported descriptor {}", desc)))?;
+
+    let (origin, key) = match key.strip_prefix('[') {
+        Some(key) => {
+            let (origin, key) = key
+                .split_once(']')
+                .ok_or_else(|| Error::InvalidDescriptor("invalid key origin".into()))?;
+            (Some(parse_key_origin(origin)?), key)
+        }
+        None => (None, key),
+    };
+    let (key, derivation) = key.split_once('/').unwrap_or((key, ""));
+    if !matches!(derivation, "" | "0/*" | "1/*" | "<0;1>/*") {
+        return Err(Error::InvalidDescriptor(format!("unsupported derivation /{}", derivation)));
+    }
+    Ok((ExtendedPubKey::from_str(key)?, origin, script_type))
+}
+
+/// Parse a key origin without brackets, e.g. `d34db33f/84h/0h/0h`
+fn parse_key_origin(origin: &str) -> Result<KeySource, Error> {
+    let invalid = || Error::InvalidDescriptor(format!("invalid key origin {}", origin));
+    let (fingerprint, path) = origin.split_once('/').unwrap_or((origin, ""));
+    if fingerprint.len() != 8 {
+        return Err(invalid());
+    }
+    let fingerprint = Fingerprint::from_hex(fingerprint).map_err(|_| invalid())?;
+    let path = DerivationPath::from_str(format!("m/{}", path).trim_end_matches('/'))
+        .map_err(|_| invalid())?;
+    Ok((fingerprint, path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const XPUB: &str = "xpub6DJ2dNUysrn5Vt36jH2KLBT2i1auw1tTSSomg8PhqNiUtx8QX2SvC9nrHu81fT41fvDUnhMjEzQgXnQjKEu3oaqMSzhSrHMxyyoEAmUHQbY";
+
+    #[test]
+    fn test_descriptor_checksum() {
+        assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        let desc = format!("wpkh([d34db33f/84h/0h/0h]{}/0/*)", XPUB);
+        assert_eq!(descriptor_checksum(&desc).unwrap(), "cjjspncu");
+        assert!(descriptor_checksum("wpkh(é)").is_err());
+    }
+
+    #[test]
+    fn test_parse_single_sig_descriptor() {
+        let xpub = ExtendedPubKey::from_str(XPUB).unwrap();
+        let origin = (
+            Fingerprint::from_hex("d34db33f").unwrap(),
+            DerivationPath::from_str("m/84'/0'/0'").unwrap(),
+        );
+        let checked = format!("wpkh([d34db33f/84h/0h/0h]{}/0/*)#cjjspncu", XPUB);
+        assert_eq!(
+            parse_single_sig_descriptor(&checked).unwrap(),
+            (xpub, Some(origin), ScriptType::P2wpkh)
+        );
+        let wrong_checksum = format!("wpkh([d34db33f/84h/0h/0h]{}/0/*)#cjjspncv", XPUB);
+        assert!(parse_single_sig_descriptor(&wrong_checksum).is_err());
+
+        let desc = format!("sh(wpkh({}))", XPUB);
+        assert_eq!(
+            parse_single_sig_descriptor(&desc).unwrap(),
+            (xpub, None, ScriptType::P2shP2wpkh)
+        );
+        let desc = format!("pkh([d34db33f]{}/<0;1>/*)", XPUB);
+        let origin = (Fingerprint::from_hex("d34db33f").unwrap(), DerivationPath::master());
+        assert_eq!(
+            parse_single_sig_descriptor(&desc).unwrap(),
+            (xpub, Some(origin), ScriptType::P2pkh)
+        );
+
+        assert!(parse_single_sig_descriptor(&format!("wsh(pk({}))", XPUB)).is_err());
+        assert!(parse_single_sig_descriptor(&format!("wpkh({}/0/0)", XPUB)).is_err());
+        assert!(parse_single_sig_descriptor(&format!("wpkh([d34db33f{})", XPUB)).is_err());
+        assert!(parse_single_sig_descriptor(&format!("wpkh([d34db3/84h]{})", XPUB)).is_err());
+        assert!(parse_single_sig_descriptor(&format!("wpkh([d34db33f/x]{})", XPUB)).is_err());
+    }
+
+    #[test]
+    fn test_parse_ct_descriptor() {
+        let xpub = ExtendedPubKey::from_str(XPUB).unwrap();
+        let slip77 = "9c8e4f05c7711a98c838be228bcb84924d4570ca53f35fa1c793e58841d47023";
+        let desc = format!("ct(slip77({}),elwpkh({}/<0;1>/*))", slip77, XPUB);
+        let checked = format!("{}#{}", desc, descriptor_checksum(&desc).unwrap());
+        let (master_blinding_key, parsed, origin, script_type) =
+            parse_ct_descriptor(&checked).unwrap();
+        assert_eq!((parsed, origin, script_type), (xpub, None, ScriptType::P2wpkh));
+        assert_eq!(master_blinding_key.0[..32], [0u8; 32]);
+        assert_eq!(master_blinding_key.0[32..].to_vec(), Vec::<u8>::from_hex(slip77).unwrap());
+
+        let desc = format!("ct(slip77({}),elsh(wpkh({})))", slip77, XPUB);
+        assert_eq!(parse_ct_descriptor(&desc).unwrap().3, ScriptType::P2shP2wpkh);
+
+        // only slip77 blinding keys, elements descriptors and no taproot
+        let view_key = "c25deb86fa11e49d651d7eae27c220ef930fbd86ea023eebfa73e54875647963";
+        assert!(parse_ct_descriptor(&format!("ct({},elwpkh({}))", view_key, XPUB)).is_err());
+        assert!(parse_ct_descriptor(&format!("ct(slip77({}),wpkh({}))", slip77, XPUB)).is_err());
+        assert!(parse_ct_descriptor(&format!("ct(slip77({}),eltr({}))", slip77, XPUB)).is_err());
+        assert!(parse_ct_descriptor(&format!("elwpkh({})", XPUB)).is_err());
+    }
+
+    #[test]
+    fn test_parse_slip132() {
+        let xpub = ExtendedPubKey::from_str(XPUB).unwrap();
+        for (version, _, script_type) in SLIP132_VERSIONS.iter().take(3) {
+            let mut data = xpub.encode();
+            data[0..4].copy_from_slice(&version[..]);
+            let slip132 = base58::check_encode_slice(&data);
+            let parsed = parse_slip132_extended_pubkey(&slip132).unwrap();
+            assert_eq!(parsed, (xpub, true, *script_type));
+        }
+        assert!(parse_slip132_extended_pubkey("xpub").is_err());
+    }
+}
@@ -40,12 +40,18 @@ pub enum Error {
     #[error(transparent)]
     BitcoinKeyError(#[from] bitcoin::util::key::Error),
 
+    #[error(transparent)]
+    BitcoinPsbt(#[from] bitcoin::util::psbt::Error),
+
     #[error(transparent)]
     ClientError(#[from] electrum_client::Error),
 
     #[error(transparent)]
     Common(#[from] CommonError),
 
+    #[error(transparent)]
+    ElementsBlind(#[from] elements::BlindError),
+
     #[error(transparent)]
     ElementsEncode(#[from] elements::encode::Error),
 
@@ -76,15 +82,36 @@ pub enum Error {
     #[error("invalid asset id")]
     InvalidAssetId,
 
+    #[error("invalid blinding data: {0}")]
+    InvalidBlindingData(String),
+
+    #[error("invalid cpfp request fields")]
+    InvalidCpfpRequest,
+
+    #[error("invalid descriptor: {0}")]
+    InvalidDescriptor(String),
+
     #[error("Invalid Electrum URL: {0}")]
     InvalidElectrumUrl(String),
 
+    #[error("invalid extended public key: {0}")]
+    InvalidExtendedPubKey(String),
+
+    #[error("gap limit must be between 1 and {0}")]
+    InvalidGapLimit(u32),
+
     #[error("invalid headers")]
     InvalidHeaders,
 
+    #[error("invalid issuance request: {0}")]
+    InvalidIssuanceRequest(String),
+
     #[error(transparent)]
     InvalidKeyIvLength(#[from] block_modes::InvalidKeyIvLength),
 
+    #[error("invalid message request: {0}")]
+    InvalidMessageRequest(String),
+
     #[error("invalid mnemonic")]
     InvalidMnemonic,
 
@@ -94,12 +121,24 @@ pub enum Error {
     #[error("id_invalid_pin")]
     InvalidPin,
 
+    #[error("invalid peg request: {0}")]
+    InvalidPegRequest(String),
+
     #[error("invalid replacement request fields")]
     InvalidReplacementRequest,
 
+    #[error("invalid signer response: {0}")]
+    InvalidSignerResponse(String),
+
     #[error("invalid sighash")]
     InvalidSigHash,
 
+    #[error("invalid swap: {0}")]
+    InvalidSwap(String),
+
+    #[error("invalid sweep request: {0}")]
+    InvalidSweepRequest(String),
+
     #[error(transparent)]
     InvalidStringUtf8(#[from] std::string::FromUtf8Error),
 
@@ -130,6 +169,12 @@ pub enum Error {
     #[error(transparent)]
     PsetBlindError(#[from] elements::pset::PsetBlindError),
 
+    #[error("bitcoind rpc error {code}: {message}")]
+    Rpc {
+        code: i64,
+        message: String,
+    },
+
     #[error("RW lock is poisoned: {0}")]
     RwLockPoisonError(String),
 
@@ -0,0 +1,513 @@
+//! Client of the Esplora REST API, see <https://github.com/Blockstream/esplora/blob/master/API.md>
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::time::Duration;
+
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{BlockHash, BlockHeader, Script, TxMerkleNode, Txid};
+use electrum_client::{GetHistoryRes, GetMerkleRes, ListUnspentRes, RawHeaderNotification};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use gdk_common::network;
+
+use crate::backend::Backend;
+use crate::error::Error;
+
+/// Number of confirmed transactions returned by a page of the script history
+const CHAIN_TXS_PER_PAGE: usize = 25;
+
+/// Number of blocks returned by a page of the blocks list
+const BLOCKS_PER_PAGE: usize = 10;
+
+pub struct EsploraClient {
+    agent: ureq::Agent,
+    url: String,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraTx {
+    txid: Txid,
+    status: TxStatus,
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraUtxo {
+    txid: Txid,
+    vout: usize,
+    status: TxStatus,
+    /// Missing for confidential outputs
+    #[serde(default)]
+    value: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraBlock {
+    height: usize,
+}
+
+/// A block of the blocks list, with the fields of its header
+#[derive(Debug, Deserialize)]
+struct EsploraBlockSummary {
+    id: BlockHash,
+    height: usize,
+    version: i32,
+    timestamp: u32,
+    bits: u32,
+    nonce: u32,
+    merkle_root: TxMerkleNode,
+    /// Missing for the genesis block
+    previousblockhash: Option<BlockHash>,
+}
+
+impl EsploraBlockSummary {
+    /// The header made of the block fields, which must hash to the block id
+    fn header(&self) -> Result<BlockHeader, Error> {
+        let header = BlockHeader {
+            version: self.version,
+            prev_blockhash: self.previousblockhash.unwrap_or_else(BlockHash::all_zeros),
+            merkle_root: self.merkle_root,
+            time: self.timestamp,
+            bits: self.bits,
+            nonce: self.nonce,
+        };
+        if header.block_hash() != self.id {
+            return Err(Error::Generic(format!("esplora block {} has another header", self.id)));
+        }
+        Ok(header)
+    }
+}
+
+impl TxStatus {
+    /// The height with the electrum convention, 0 if unconfirmed
+    fn height(&self) -> usize {
+        match (self.confirmed, self.block_height) {
+            (true, Some(height)) => height,
+            _ => 0,
+        }
+    }
+}
+
+/// Esplora indexes scripts by their sha256, not reversed as electrum does
+fn scripthash(script: &Script) -> String {
+    sha256::Hash::hash(script.as_bytes()).to_hex()
+}
+
+/// Convert a fee rate from satoshi/vbyte to BTC/kB
+fn sat_per_vbyte_to_btc_per_kb(fee_rate: f64) -> f64 {
+    fee_rate * 1000.0 / 100_000_000.0
+}
+
+impl EsploraClient {
+    /// `proxy` and `timeout` have the same meaning as for the electrum client
+    pub fn new(url: &str, proxy: Option<&str>, timeout: Option<u8>) -> Result<Self, Error> {
+        Ok(EsploraClient {
+            agent: network::build_request_agent(proxy)?,
+            url: url.trim_end_matches('/').to_string(),
+            timeout: timeout.map(|t| Duration::from_secs(t.into())),
+        })
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self.agent.request(method, &format!("{}{}", self.url, path));
+        match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+
+    /// Error responses carry a meaningful message in the body, e.g. why a broadcast failed
+    fn check(response: Result<ureq::Response, ureq::Error>) -> Result<ureq::Response, Error> {
+        match response {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::Status(code, response)) => Err(Error::Generic(format!(
+                "esplora returned {}: {}",
+                code,
+                response.into_string().unwrap_or_default()
+            ))),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<ureq::Response, Error> {
+        Self::check(self.request("GET", path).call())
+    }
+
+    fn get_text(&self, path: &str) -> Result<String, Error> {
+        Ok(self.get(path)?.into_string()?)
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        Ok(self.get(path)?.into_json()?)
+    }
+
+    fn get_bytes(&self, path: &str) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![];
+        self.get(path)?.into_reader().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn tip_height(&self) -> Result<usize, Error> {
+        let height = self.get_text("/blocks/tip/height")?;
+        height.trim().parse().map_err(|_| Error::Generic(format!("invalid tip height {}", height)))
+    }
+
+    fn block_header_by_hash(&self, hash: &str) -> Result<Vec<u8>, Error> {
+        Ok(Vec::<u8>::from_hex(self.get_text(&format!("/block/{}/header", hash))?.trim())?)
+    }
+
+    /// Esplora returns the fee rates in satoshi/vbyte for some confirmation targets
+    fn fee_estimates(&self) -> Result<BTreeMap<usize, f64>, Error> {
+        self.get_json("/fee-estimates")
+    }
+
+    fn script_get_history(&self, script: &Script) -> Result<Vec<GetHistoryRes>, Error> {
+        let scripthash = scripthash(script);
+        // the first page has all the mempool transactions, followed by the confirmed ones
+        let mut txs: Vec<EsploraTx> = self.get_json(&format!("/scripthash/{}/txs", scripthash))?;
+        let mut confirmed_in_page = txs.iter().filter(|tx| tx.status.confirmed).count();
+        while confirmed_in_page == CHAIN_TXS_PER_PAGE {
+            let last_seen = txs.last().expect("page is not empty").txid;
+            let page: Vec<EsploraTx> =
+                self.get_json(&format!("/scripthash/{}/txs/chain/{}", scripthash, last_seen))?;
+            confirmed_in_page = page.len();
+            txs.extend(page);
+        }
+        Ok(txs
+            .into_iter()
+            .map(|tx| GetHistoryRes {
+                height: tx.status.height() as i32,
+                tx_hash: tx.txid,
+                fee: tx.fee,
+            })
+            .collect())
+    }
+
+    fn script_list_unspent(&self, script: &Script) -> Result<Vec<ListUnspentRes>, Error> {
+        let utxos: Vec<EsploraUtxo> =
+            self.get_json(&format!("/scripthash/{}/utxo", scripthash(script)))?;
+        Ok(utxos
+            .into_iter()
+            .map(|utxo| ListUnspentRes {
+                height: utxo.status.height(),
+                tx_hash: utxo.txid,
+                tx_pos: utxo.vout,
+                value: utxo.value,
+            })
+            .collect())
+    }
+}
+
+/// Esplora has no batch endpoints, batch calls make one request per element
+impl Backend for EsploraClient {
+    fn ping(&self) -> Result<(), Error> {
+        self.tip_height().map(|_| ())
+    }
+
+    /// Esplora doesn't expose the relay fee, the lowest estimate is returned instead
+    fn relay_fee(&self) -> Result<f64, Error> {
+        self.fee_estimates()?
+            .values()
+            .cloned()
+            .reduce(f64::min)
+            .map(sat_per_vbyte_to_btc_per_kb)
+            .ok_or_else(|| Error::Generic("esplora has no fee estimates".into()))
+    }
+
+    /// Missing targets get the estimate of the nearest lower target
+    fn batch_estimate_fee(&self, blocks: &[usize]) -> Result<Vec<f64>, Error> {
+        let estimates = self.fee_estimates()?;
+        Ok(blocks
+            .iter()
+            .map(|b| match estimates.range(..=b).next_back() {
+                Some((_, fee_rate)) => sat_per_vbyte_to_btc_per_kb(*fee_rate),
+                None => -1.0,
+            })
+            .collect())
+    }
+
+    fn tip(&self) -> Result<RawHeaderNotification, Error> {
+        let hash = self.get_text("/blocks/tip/hash")?;
+        let hash = hash.trim();
+        let block: EsploraBlock = self.get_json(&format!("/block/{}", hash))?;
+        Ok(RawHeaderNotification {
+            height: block.height,
+            header: self.block_header_by_hash(hash)?,
+        })
+    }
+
+    fn block_header_raw(&self, height: usize) -> Result<Vec<u8>, Error> {
+        let hash = self.get_text(&format!("/block-height/{}", height))?;
+        self.block_header_by_hash(hash.trim())
+    }
+
+    fn batch_block_header_raw(&self, heights: &[u32]) -> Result<Vec<Vec<u8>>, Error> {
+        heights.iter().map(|h| self.block_header_raw(*h as usize)).collect()
+    }
+
+    /// The headers are made from the pages of the blocks list, listing the blocks down from a
+    /// given height
+    fn block_headers(&self, start_height: usize, count: usize) -> Result<Vec<BlockHeader>, Error> {
+        let end_height = (start_height + count).min(self.tip_height()? + 1);
+        let mut headers = Vec::with_capacity(end_height.saturating_sub(start_height));
+        for page_start in (start_height..end_height).step_by(BLOCKS_PER_PAGE) {
+            let page_end = (page_start + BLOCKS_PER_PAGE).min(end_height);
+            let blocks: Vec<EsploraBlockSummary> =
+                self.get_json(&format!("/blocks/{}", page_end - 1))?;
+            for height in page_start..page_end {
+                let block = blocks.get(page_end - 1 - height).filter(|b| b.height == height);
+                let block = block.ok_or_else(|| {
+                    Error::Generic(format!("esplora blocks list misses height {}", height))
+                })?;
+                headers.push(block.header()?);
+            }
+        }
+        Ok(headers)
+    }
+
+    fn batch_script_get_history(
+        &self,
+        scripts: &[Script],
+    ) -> Result<Vec<Vec<GetHistoryRes>>, Error> {
+        scripts.iter().map(|s| self.script_get_history(s)).collect()
+    }
+
+    fn batch_script_list_unspent(
+        &self,
+        scripts: &[Script],
+    ) -> Result<Vec<Vec<ListUnspentRes>>, Error> {
+        scripts.iter().map(|s| self.script_list_unspent(s)).collect()
+    }
+
+    fn batch_transaction_get_raw(&self, txids: &[Txid]) -> Result<Vec<Vec<u8>>, Error> {
+        txids.iter().map(|txid| self.get_bytes(&format!("/tx/{}/raw", txid))).collect()
+    }
+
+    /// The merkle proof has the same format of the electrum one
+    fn transaction_get_merkle(&self, txid: &Txid, _height: usize) -> Result<GetMerkleRes, Error> {
+        self.get_json(&format!("/tx/{}/merkle-proof", txid))
+    }
+
+    fn transaction_broadcast_raw(&self, raw_tx: &[u8]) -> Result<Txid, Error> {
+        let response = Self::check(self.request("POST", "/tx").send_string(&raw_tx.to_hex()))?;
+        Ok(Txid::from_hex(response.into_string()?.trim())?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::consensus::deserialize;
+    use httptest::{matchers::*, responders::*, Expectation, Server};
+
+    fn client(server: &Server) -> EsploraClient {
+        EsploraClient::new(&server.url_str("/"), None, None).unwrap()
+    }
+
+    fn txid(i: u8) -> Txid {
+        Txid::from_slice(&[i; 32]).unwrap()
+    }
+
+    fn tx_json(i: u8, height: Option<usize>) -> serde_json::Value {
+        serde_json::json!({
+            "txid": txid(i),
+            "status": { "confirmed": height.is_some(), "block_height": height },
+            "fee": 141,
+        })
+    }
+
+    #[test]
+    fn test_script_get_history_pages() {
+        let server = Server::run();
+        let script = Script::from(vec![0x51]);
+        let hash = scripthash(&script);
+        let first_page: Vec<_> = history_page(0, 25, true);
+        let last_seen = txid(25);
+        server.expect(
+            Expectation::matching(request::method_path("GET", format!("/scripthash/{}/txs", hash)))
+                .respond_with(json_encoded(first_page)),
+        );
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                format!("/scripthash/{}/txs/chain/{}", hash, last_seen),
+            ))
+            .respond_with(json_encoded(history_page(26, 3, false))),
+        );
+
+        let history = client(&server).batch_script_get_history(&[script]).unwrap();
+        assert_eq!(history.len(), 1);
+        let history = &history[0];
+        assert_eq!(history.len(), 29);
+        assert_eq!(history[0].height, 0);
+        assert_eq!(history[0].tx_hash, txid(0));
+        assert_eq!(history[1].height, 1001);
+        assert_eq!(history[28].tx_hash, txid(28));
+        assert_eq!(history[28].fee, Some(141));
+    }
+
+    /// A page with an unconfirmed transaction if `mempool`, followed by `n` confirmed ones
+    fn history_page(start: u8, n: u8, mempool: bool) -> Vec<serde_json::Value> {
+        let mut page = vec![];
+        if mempool {
+            page.push(tx_json(start, None));
+        }
+        let first = if mempool {
+            start + 1
+        } else {
+            start
+        };
+        for i in first..first + n {
+            page.push(tx_json(i, Some(1000 + i as usize)));
+        }
+        page
+    }
+
+    #[test]
+    fn test_fee_estimates() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/fee-estimates"))
+                .times(2)
+                .respond_with(json_encoded(serde_json::json!({
+                    "2": 20.0,
+                    "6": 10.0,
+                    "144": 1.5,
+                }))),
+        );
+        let client = client(&server);
+        assert_eq!(client.relay_fee().unwrap(), 0.000015);
+        let estimates = client.batch_estimate_fee(&[1, 2, 5, 6, 200]).unwrap();
+        assert_eq!(estimates, vec![-1.0, 0.0002, 0.0002, 0.0001, 0.000015]);
+    }
+
+    #[test]
+    fn test_tip_and_broadcast() {
+        let server = Server::run();
+        let header = bitcoin::blockdata::constants::genesis_block(bitcoin::Network::Regtest).header;
+        let hash = header.block_hash().to_string();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/blocks/tip/hash"))
+                .respond_with(status_code(200).body(hash.clone())),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", format!("/block/{}", hash)))
+                .respond_with(json_encoded(serde_json::json!({ "id": hash, "height": 0 }))),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", format!("/block/{}/header", hash)))
+                .respond_with(
+                    status_code(200).body(bitcoin::consensus::serialize(&header).to_hex()),
+                ),
+        );
+        let raw_tx = vec![1u8, 2, 3];
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/tx"),
+                request::body(raw_tx.to_hex()),
+            ])
+            .respond_with(status_code(200).body(txid(7).to_string())),
+        );
+
+        let client = client(&server);
+        let tip = client.tip().unwrap();
+        assert_eq!(tip.height, 0);
+        assert_eq!(deserialize::<BlockHeader>(&tip.header).unwrap(), header);
+        assert_eq!(client.transaction_broadcast_raw(&raw_tx).unwrap(), txid(7));
+    }
+
+    #[test]
+    fn test_block_headers() {
+        let server = Server::run();
+        let mut headers = vec![];
+        let mut prev_blockhash = BlockHash::all_zeros();
+        for height in 0..17u32 {
+            let header = BlockHeader {
+                version: 0x20000000,
+                prev_blockhash,
+                merkle_root: TxMerkleNode::hash(&height.to_le_bytes()),
+                time: 1_600_000_000 + height,
+                bits: 0x207fffff,
+                nonce: height,
+            };
+            prev_blockhash = header.block_hash();
+            headers.push(header);
+        }
+        let block_json = |height: usize| {
+            let header = &headers[height];
+            serde_json::json!({
+                "id": header.block_hash(),
+                "height": height,
+                "version": header.version,
+                "timestamp": header.time,
+                "bits": header.bits,
+                "nonce": header.nonce,
+                "merkle_root": header.merkle_root,
+                "previousblockhash": if height == 0 { None } else { Some(header.prev_blockhash) },
+            })
+        };
+        // the pages list the blocks down from the given height
+        let page = |top: usize| (0..=top).rev().take(10).map(block_json).collect::<Vec<_>>();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/blocks/tip/height"))
+                .times(2)
+                .respond_with(status_code(200).body("16")),
+        );
+        for top in [9, 14, 16] {
+            server.expect(
+                Expectation::matching(request::method_path("GET", format!("/blocks/{}", top)))
+                    .respond_with(json_encoded(page(top))),
+            );
+        }
+
+        // one request per page of headers
+        let client = client(&server);
+        assert_eq!(client.block_headers(0, 10).unwrap(), headers[..10]);
+        assert_eq!(client.block_headers(5, 100).unwrap(), headers[5..]);
+    }
+
+    #[test]
+    fn test_block_headers_mismatch() {
+        let server = Server::run();
+        let header = bitcoin::blockdata::constants::genesis_block(bitcoin::Network::Regtest).header;
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/blocks/tip/height"))
+                .respond_with(status_code(200).body("0")),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/blocks/0")).respond_with(
+                json_encoded(serde_json::json!([{
+                    "id": header.block_hash(),
+                    "height": 0,
+                    "version": header.version,
+                    "timestamp": header.time,
+                    "bits": header.bits,
+                    "nonce": header.nonce + 1,
+                    "merkle_root": header.merkle_root,
+                }])),
+            ),
+        );
+        assert!(client(&server).block_headers(0, 1).is_err());
+    }
+
+    #[test]
+    fn test_error_body() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("POST", "/tx"))
+                .respond_with(status_code(400).body("min relay fee not met")),
+        );
+        let err = client(&server).transaction_broadcast_raw(&[0u8]).unwrap_err();
+        assert!(err.to_string().contains("min relay fee not met"));
+    }
+}
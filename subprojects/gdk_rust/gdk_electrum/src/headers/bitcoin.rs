@@ -4,18 +4,27 @@ use crate::spv::calc_difficulty_retarget;
 use bitcoin::blockdata::constants::{genesis_block, DIFFCHANGE_INTERVAL, TARGET_BLOCK_SPACING};
 use bitcoin::consensus::{deserialize, serialize};
 use bitcoin::hashes::hex::FromHex;
+use bitcoin::util::uint::Uint256;
 use bitcoin::{BlockHash, Txid};
 use bitcoin::{BlockHeader, Network};
 use electrum_client::GetMerkleRes;
+use gdk_common::network::SpvCheckpoint;
 use log::{info, warn};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
 use std::iter::FromIterator;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
+/// The checkpoints used when the network parameters don't set one, as (network, height, header
+/// hex, chainwork hex), with the values reported by a trusted node for a block at a difficulty
+/// retarget.
+///
+/// Networks without an entry start from the genesis block.
+const BUILTIN_CHECKPOINTS: &[(Network, u32, &str, &str)] = &[];
+
 pub static HEADERS_FILE_MUTEX: Lazy<HashMap<Network, Mutex<()>>> = Lazy::new(|| {
     HashMap::from_iter([
         (Network::Bitcoin, Mutex::new(())),
@@ -25,12 +34,67 @@ pub static HEADERS_FILE_MUTEX: Lazy<HashMap<Network, Mutex<()>>> = Lazy::new(||
     ])
 });
 
+/// A block trusted to be in the best chain, from which the chain of headers starts
+///
+/// The height must be at a difficulty retarget, so that the following retargets can be verified
+/// from the headers after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub height: u32,
+    pub header: BlockHeader,
+    /// the total work of the chain up to this block included
+    pub chainwork: Uint256,
+}
+
+impl Checkpoint {
+    pub fn genesis(network: Network) -> Self {
+        let header = genesis_block(network).header;
+        Checkpoint {
+            height: 0,
+            header,
+            chainwork: header.work(),
+        }
+    }
+
+    /// The checkpoint set in the network parameters, or the built-in one for `network`, or the
+    /// genesis block
+    pub fn from_params(
+        checkpoint: Option<&SpvCheckpoint>,
+        network: Network,
+    ) -> Result<Self, Error> {
+        match checkpoint {
+            Some(checkpoint) => {
+                Checkpoint::parse(checkpoint.height, &checkpoint.header, &checkpoint.chainwork)
+            }
+            None => match BUILTIN_CHECKPOINTS.iter().find(|(n, ..)| *n == network) {
+                Some((_, height, header, chainwork)) => {
+                    Checkpoint::parse(*height, header, chainwork)
+                }
+                None => Ok(Checkpoint::genesis(network)),
+            },
+        }
+    }
+
+    fn parse(height: u32, header: &str, chainwork: &str) -> Result<Self, Error> {
+        let header: BlockHeader = deserialize(&Vec::<u8>::from_hex(header)?)?;
+        let chainwork = Uint256::from_be_bytes(FromHex::from_hex(chainwork)?);
+        Ok(Checkpoint {
+            height,
+            header,
+            chainwork,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct HeadersChain {
     path: PathBuf,
     height: u32,
     last: BlockHeader,
     checkpoints: HashMap<u32, BlockHash>,
+    start: Checkpoint,
+    /// the total work of the chain up to the tip included
+    chainwork: Uint256,
     pub network: Network,
 }
 
@@ -40,25 +104,34 @@ impl HeadersChain {
     ///
     /// if the file doesn't exist, a chain with only the genesis block (relative to `network`) is returned
     pub fn new<P: AsRef<Path>>(path: P, network: Network) -> Result<HeadersChain, Error> {
+        HeadersChain::with_checkpoint(path, network, Checkpoint::genesis(network))
+    }
+
+    /// Create a chain of headers starting from `start` instead of the genesis block, persisted in
+    /// a file name dependent also on the height of `start`
+    ///
+    /// A chain already downloaded from the genesis block is preferred if it exists.
+    pub fn with_checkpoint<P: AsRef<Path>>(
+        path: P,
+        network: Network,
+        start: Checkpoint,
+    ) -> Result<HeadersChain, Error> {
+        if start.height % DIFFCHANGE_INTERVAL != 0
+            || start.header.validate_pow(&start.header.target()).is_err()
+        {
+            return Err(Error::Generic(format!("invalid checkpoint at height {}", start.height)));
+        }
         std::fs::create_dir_all(path.as_ref())?;
         let mut filepath: PathBuf = path.as_ref().into();
         filepath.push(format!("headers_chain_{}", network));
-        let checkpoints = get_checkpoints(network);
-        if !filepath.exists() {
-            info!("{:?} chain file doesn't exist, creating", filepath);
-            let last = genesis_block(network).header;
-            let mut file = File::create(&filepath)?;
-            file.write_all(&serialize(&last))?;
-            let height = 0;
-
-            Ok(HeadersChain {
-                path: filepath,
-                height,
-                last,
-                checkpoints,
-                network,
-            })
+        let start = if start.height == 0 || filepath.exists() {
+            Checkpoint::genesis(network)
         } else {
+            filepath.set_file_name(format!("headers_chain_{}_{}", network, start.height));
+            start
+        };
+        let checkpoints = get_checkpoints(network);
+        if filepath.exists() && HeadersChain::starts_with(&filepath, &start.header)? {
             info!("{:?} chain file exists, reading", filepath);
             let mut file = File::open(&filepath)?;
             let file_size = file.metadata()?.len();
@@ -73,23 +146,85 @@ impl HeadersChain {
             }
             let mut buf = [0u8; 80];
             file.read_exact(&mut buf)?;
-            let height = (file_size as u32 / 80) - 1;
+            let height = start.height + (file_size as u32 / 80) - 1;
             let last: BlockHeader = deserialize(&buf)?;
+            let chainwork = HeadersChain::file_chainwork(&filepath, &start)?;
+
+            Ok(HeadersChain {
+                path: filepath,
+                height,
+                last,
+                checkpoints,
+                chainwork,
+                start,
+                network,
+            })
+        } else {
+            info!("{:?} chain file doesn't exist or has another start, creating", filepath);
+            let last = start.header;
+            let mut file = File::create(&filepath)?;
+            file.write_all(&serialize(&last))?;
+            let height = start.height;
+            let chainwork = start.chainwork;
 
             Ok(HeadersChain {
                 path: filepath,
                 height,
                 last,
                 checkpoints,
+                chainwork,
+                start,
                 network,
             })
         }
     }
 
+    /// Whether the file at `filepath` starts with `header`, the first header of the chain
+    fn starts_with(filepath: &Path, header: &BlockHeader) -> Result<bool, Error> {
+        let mut buf = [0u8; 80];
+        match File::open(filepath)?.read_exact(&mut buf) {
+            Ok(()) => Ok(buf[..] == serialize(header)[..]),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// The total work of the headers in the file at `filepath`, starting from `start`
+    fn file_chainwork(filepath: &Path, start: &Checkpoint) -> Result<Uint256, Error> {
+        let mut file = BufReader::new(File::open(filepath)?);
+        let mut chainwork = start.chainwork;
+        let mut buf = [0u8; 80];
+        // the first header is the start one, already included in its chainwork
+        file.read_exact(&mut buf)?;
+        while file.read_exact(&mut buf).is_ok() {
+            let header: BlockHeader = deserialize(&buf)?;
+            chainwork = chainwork + header.work();
+        }
+        Ok(chainwork)
+    }
+
+    /// The block the chain starts from, the genesis block or a checkpoint
+    pub fn start(&self) -> &Checkpoint {
+        &self.start
+    }
+
     pub fn height(&self) -> u32 {
         self.height
     }
 
+    /// The total work of the chain up to the tip included
+    pub fn chainwork(&self) -> Uint256 {
+        self.chainwork
+    }
+
+    /// The total work of the chain up to the block at `height` included
+    pub fn chainwork_at(&self, height: u32) -> Result<Uint256, Error> {
+        let mut chainwork = self.chainwork;
+        for h in height.max(self.start.height) + 1..=self.height {
+            chainwork = chainwork - self.get(h)?.work();
+        }
+        Ok(chainwork)
+    }
+
     fn pow_allow_min_difficulty_blocks(&self) -> bool {
         // Special difficulty rule for testnet and regtest:
         // If the next block's timestamp is more than 2* 10 minutes
@@ -107,7 +242,7 @@ impl HeadersChain {
             // loop at most DIFFCHANGE_INTERVAL times
             let bits = loop {
                 let header = self.get(height)?;
-                if height == 0
+                if height == self.start.height
                     || height % DIFFCHANGE_INTERVAL == 0
                     || header.difficulty(self.network) != 1
                 {
@@ -122,8 +257,14 @@ impl HeadersChain {
     }
 
     pub fn get(&self, height: u32) -> Result<BlockHeader, Error> {
+        if height < self.start.height {
+            return Err(Error::Generic(format!(
+                "height {} is before the start of the chain at {}",
+                height, self.start.height
+            )));
+        }
         let mut file = File::open(&self.path)?;
-        let wanted_seek = height as u64 * 80;
+        let wanted_seek = (height - self.start.height) as u64 * 80;
         let effective_seek = file.seek(SeekFrom::Start(wanted_seek))?;
         if wanted_seek != effective_seek {
             warn!("Seek failed wanted:{} effective:{}", wanted_seek, effective_seek);
@@ -137,11 +278,13 @@ impl HeadersChain {
 
     /// to handle reorgs, it's necessary to remove some of the last headers
     pub fn remove(&mut self, headers_to_remove: u32) -> Result<(), Error> {
-        let headers_to_remove = headers_to_remove.min(self.height);
+        let headers_to_remove = headers_to_remove.min(self.height - self.start.height);
         let new_height = self.height - headers_to_remove;
-        let new_size = (new_height + 1) as u64 * 80;
+        let new_size = (new_height - self.start.height + 1) as u64 * 80;
+        let chainwork = self.chainwork_at(new_height)?;
         let file = OpenOptions::new().write(true).open(&self.path)?;
         self.last = self.get(new_height)?;
+        self.chainwork = chainwork;
         self.height = new_height;
         file.set_len(new_size)?;
         Ok(())
@@ -200,6 +343,7 @@ impl HeadersChain {
             serialized.extend(serialize(&new_header));
             self.last = new_header;
             self.height = new_height;
+            self.chainwork = self.chainwork + new_header.work();
         }
         self.flush(&mut serialized)?;
         info!(
@@ -268,10 +412,14 @@ fn get_checkpoints(network: Network) -> HashMap<u32, BlockHash> {
 
 #[cfg(test)]
 mod test {
-    use crate::headers::bitcoin::HeadersChain;
+    use crate::headers::bitcoin::{
+        get_checkpoints, Checkpoint, HeadersChain, BUILTIN_CHECKPOINTS, DIFFCHANGE_INTERVAL,
+    };
     use bitcoin::consensus::encode::Decodable;
     use bitcoin::hash_types::BlockHash;
     use bitcoin::hashes::hex::FromHex;
+    use bitcoin::hashes::Hash;
+    use bitcoin::TxMerkleNode;
     use bitcoin::{BlockHeader, Network};
     use electrum_client::GetMerkleRes;
     use std::io::Cursor;
@@ -378,4 +526,97 @@ mod test {
         );
         assert!(chain.get(200).is_err());
     }
+
+    /// A regtest header following `prev`, with a nonce satisfying its proof of work
+    fn mine(prev: BlockHash, time: u32) -> BlockHeader {
+        let mut header = BlockHeader {
+            version: 0x20000000,
+            prev_blockhash: prev,
+            merkle_root: TxMerkleNode::all_zeros(),
+            time,
+            bits: 0x207fffff,
+            nonce: 0,
+        };
+        while header.validate_pow(&header.target()).is_err() {
+            header.nonce += 1;
+        }
+        header
+    }
+
+    #[test]
+    fn test_headers_checkpoint() {
+        let start_height = DIFFCHANGE_INTERVAL;
+        let start_header = mine(BlockHash::all_zeros(), 1_600_000_000);
+        let start = Checkpoint {
+            height: start_height,
+            header: start_header,
+            chainwork: start_header.work(),
+        };
+
+        let temp = TempDir::new().unwrap();
+        let mut not_retarget = start.clone();
+        not_retarget.height += 1;
+        assert!(HeadersChain::with_checkpoint(&temp, Network::Regtest, not_retarget).is_err());
+
+        let mut chain =
+            HeadersChain::with_checkpoint(&temp, Network::Regtest, start.clone()).unwrap();
+        assert_eq!(chain.height(), start_height);
+        assert_eq!(chain.start(), &start);
+
+        let mut headers = vec![];
+        let mut prev = start_header.block_hash();
+        for i in 1..=10 {
+            let header = mine(prev, 1_600_000_000 + i * 600);
+            prev = header.block_hash();
+            headers.push(header);
+        }
+        chain.push(headers.clone()).unwrap();
+        assert_eq!(chain.height(), start_height + 10);
+        let work = start_header.work();
+        assert_eq!(chain.chainwork(), work.mul_u32(11));
+        assert_eq!(chain.chainwork_at(start_height + 4).unwrap(), work.mul_u32(5));
+        assert_eq!(chain.get(start_height).unwrap(), start_header);
+        assert_eq!(chain.get(start_height + 5).unwrap(), headers[4]);
+        assert!(chain.get(start_height - 1).is_err(), "before the checkpoint should error");
+        assert!(chain.get(start_height + 11).is_err());
+
+        chain.remove(100).unwrap();
+        assert_eq!(chain.height(), start_height, "cannot remove the checkpoint");
+        assert_eq!(chain.chainwork(), work);
+        chain.push(headers).unwrap();
+
+        let chain = HeadersChain::with_checkpoint(&temp, Network::Regtest, start.clone()).unwrap();
+        assert_eq!(chain.height(), start_height + 10);
+        assert_eq!(chain.chainwork(), work.mul_u32(11), "chainwork is recomputed from the file");
+        assert_eq!(chain.start(), &start);
+
+        let mut other = start.clone();
+        other.header = mine(BlockHash::all_zeros(), 1_600_000_001);
+        let chain = HeadersChain::with_checkpoint(&temp, Network::Regtest, other).unwrap();
+        assert_eq!(chain.height(), start_height, "a different checkpoint restarts the chain");
+    }
+
+    #[test]
+    fn test_builtin_checkpoints() {
+        for (network, height, header, chainwork) in BUILTIN_CHECKPOINTS {
+            let checkpoint = Checkpoint::parse(*height, header, chainwork).unwrap();
+            assert_eq!(checkpoint.height % DIFFCHANGE_INTERVAL, 0, "{} not at a retarget", height);
+            assert!(checkpoint.header.validate_pow(&checkpoint.header.target()).is_ok());
+            assert!(checkpoint.chainwork > checkpoint.header.work());
+            // the hash of the header is also in the checkpoints verified while syncing
+            let hash = checkpoint.header.block_hash();
+            assert_eq!(get_checkpoints(*network).get(height), Some(&hash), "{}", height);
+            assert_eq!(Checkpoint::from_params(None, *network).unwrap(), checkpoint);
+            let temp = TempDir::new().unwrap();
+            let chain = HeadersChain::with_checkpoint(&temp, *network, checkpoint).unwrap();
+            assert_eq!(chain.height(), *height);
+        }
+
+        for network in [Network::Bitcoin, Network::Testnet, Network::Regtest] {
+            if BUILTIN_CHECKPOINTS.iter().all(|(n, ..)| *n != network) {
+                let genesis = Checkpoint::genesis(network);
+                assert_eq!(Checkpoint::from_params(None, network).unwrap(), genesis);
+            }
+        }
+    }
 }
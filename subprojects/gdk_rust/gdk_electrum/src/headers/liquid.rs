@@ -3,19 +3,49 @@ use elements::{self, BlockExtData};
 use crate::error::*;
 use crate::headers::compute_merkle_root;
 use bitcoin::hashes::hex::FromHex;
-use bitcoin::hashes::Hash;
+use bitcoin::hashes::{sha256, Hash};
 use bitcoin::secp256k1::{ecdsa::Signature, Message};
 use bitcoin::PublicKey;
 use electrum_client::GetMerkleRes;
+use elements::dynafed::Params;
 use elements::opcodes::{self, Class, ClassifyContext};
 use elements::script::{self, Instruction};
 use elements::{BlockHash, BlockHeader, Script, TxMerkleNode, Txid};
+use gdk_common::network::SpvLiquidAnchor;
 use gdk_common::ElementsNetwork;
-use log::info;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-/// liquid v1 block header verifier, not suitable for dynafed
-/// checks the challenge is exactly equal to the one present in block 1
+pub static LIQUID_HEADERS_FILE_MUTEX: Lazy<HashMap<ElementsNetwork, Mutex<()>>> = Lazy::new(|| {
+    HashMap::from_iter([
+        (ElementsNetwork::Liquid, Mutex::new(())),
+        (ElementsNetwork::LiquidTestnet, Mutex::new(())),
+        (ElementsNetwork::ElementsRegtest, Mutex::new(())),
+    ])
+});
+
+/// The anchors used when the network parameters don't set one, as (network, height,
+/// signblockscript hex), with the values reported by a trusted node for a block at a dynafed
+/// epoch start.
+///
+/// Chains of networks without an anchor don't start, except on regtest where the first header is
+/// trusted.
+const BUILTIN_ANCHORS: &[(ElementsNetwork, u32, &str)] = &[];
+
+/// Size of a persisted header: block hash, merkle root and root of the proposed dynafed params
+const RECORD_SIZE: u64 = 96;
+
+/// liquid block header verifier
+/// for liquid v1 headers checks the challenge is exactly equal to the one present in block 1,
+/// for dynafed headers checks the witness against a given signblockscript
 /// checks the solution script against the challenge, verifying signatures
+#[derive(Debug)]
 pub struct Verifier {
     challenge: Script,
     genesis: BlockHash,
@@ -61,6 +91,10 @@ impl Verifier {
         }
     }
 
+    fn is_genesis(&self, header: &BlockHeader) -> bool {
+        header.block_hash() == self.genesis
+    }
+
     /// verify the given liquid header
     fn verify_header(&self, header: &BlockHeader) -> Result<(), Error> {
         let mut stack = vec![];
@@ -82,16 +116,40 @@ impl Verifier {
                 {
                     self.process_instr(&instr, &hash, &mut stack)?;
                 }
-                if stack.is_empty() {
-                    Ok(())
-                } else {
-                    Err(Error::InvalidHeaders)
-                }
+                check_stack(&stack)
             }
             _ => Err(Error::InvalidHeaders),
         }
     }
 
+    /// verify the signblock witness of the given dynafed header against `signblockscript`, a v0
+    /// witness script hash
+    fn verify_dynafed_header(
+        &self,
+        header: &BlockHeader,
+        signblockscript: &Script,
+    ) -> Result<(), Error> {
+        let witness = match &header.ext {
+            BlockExtData::Dynafed {
+                signblock_witness,
+                ..
+            } => signblock_witness,
+            _ => return Err(Error::InvalidHeaders),
+        };
+        let (witness_script, witness_stack) =
+            witness.split_last().ok_or_else(|| Error::InvalidHeaders)?;
+        let witness_script = Script::from(witness_script.clone());
+        if *signblockscript != Script::new_v0_wsh(&witness_script.wscript_hash()) {
+            return Err(Error::InvalidHeaders);
+        }
+        let hash = header.block_hash();
+        let mut stack = witness_stack.to_vec();
+        for instr in witness_script.instructions_minimal() {
+            self.process_instr(&instr, &hash, &mut stack)?;
+        }
+        check_stack(&stack)
+    }
+
     fn process_instr(
         &self,
         instr: &Result<Instruction, script::Error>,
@@ -144,6 +202,7 @@ impl Verifier {
             if verified == required_sig {
                 info!("proof for block {} found {} valid signatures", hash, verified);
                 stack.pop().ok_or_else(|| Error::InvalidHeaders)?;
+                stack.push(vec![1]);
                 return Ok(());
             }
         }
@@ -151,29 +210,427 @@ impl Verifier {
     }
 }
 
+/// the script succeeded if it left only true on the stack
+fn check_stack(stack: &[Vec<u8>]) -> Result<(), Error> {
+    if stack == [vec![1]] {
+        Ok(())
+    } else {
+        Err(Error::InvalidHeaders)
+    }
+}
+
+/// A block trusted to be in the best chain, from which the chain of headers starts
+///
+/// The height must be at a dynafed epoch start, where the headers have the full params.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    pub height: u32,
+    /// the signblockscript of the params in use from the block
+    pub signblockscript: Script,
+}
+
+impl Anchor {
+    /// The anchor set in the network parameters, or the built-in one for `network`, if any
+    pub fn from_params(
+        anchor: Option<&SpvLiquidAnchor>,
+        network: ElementsNetwork,
+    ) -> Result<Option<Self>, Error> {
+        match anchor {
+            Some(anchor) => Anchor::parse(anchor.height, &anchor.signblockscript).map(Some),
+            None => match BUILTIN_ANCHORS.iter().find(|(n, ..)| *n == network) {
+                Some((_, height, signblockscript)) => {
+                    Anchor::parse(*height, signblockscript).map(Some)
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
+    fn parse(height: u32, signblockscript: &str) -> Result<Self, Error> {
+        Ok(Anchor {
+            height,
+            signblockscript: Script::from(Vec::<u8>::from_hex(signblockscript)?),
+        })
+    }
+}
+
+/// The number of blocks between the dynafed params transitions
+fn epoch_length(network: ElementsNetwork) -> u32 {
+    match network {
+        ElementsNetwork::Liquid => 20160,
+        ElementsNetwork::LiquidTestnet => 1000,
+        ElementsNetwork::ElementsRegtest => 10,
+    }
+}
+
+/// A chain of liquid headers, persisted as block hashes and merkle roots, following the dynamic
+/// federation params through their transitions
+///
+/// Since the full chain is too big to be downloaded, it starts from a trusted anchor, or on
+/// regtest from the last params transition at the time of the first sync.
+#[derive(Debug)]
+pub struct LiquidHeadersChain {
+    path: PathBuf,
+    params_path: PathBuf,
+    /// height of the first header, `None` if the chain has no header yet
+    start: Option<u32>,
+    height: u32,
+    last: BlockHash,
+    /// the params in use (compact) and the height from which they are, null before the dynafed
+    /// activation
+    epochs: Vec<(u32, Params)>,
+    /// the votes for the proposed params in the current epoch, by params root
+    votes: HashMap<sha256::Midstate, u32>,
+    anchor: Option<Anchor>,
+    verifier: Verifier,
+    pub network: ElementsNetwork,
+}
+
+impl LiquidHeadersChain {
+    /// Create a chain of headers without an anchor, see [`LiquidHeadersChain::with_anchor`]
+    pub fn new<P: AsRef<Path>>(path: P, network: ElementsNetwork) -> Result<Self, Error> {
+        LiquidHeadersChain::with_anchor(path, network, None)
+    }
+
+    /// Create a chain of headers starting from `anchor`, which is persisted inside given `path`
+    /// parameter using a file name dependent on the given `network` and the height of `anchor`
+    ///
+    /// if the file doesn't exist, an empty chain is returned, the first pushed header must match
+    /// `anchor`, without one it is trusted on regtest and rejected on the other networks
+    pub fn with_anchor<P: AsRef<Path>>(
+        path: P,
+        network: ElementsNetwork,
+        anchor: Option<Anchor>,
+    ) -> Result<Self, Error> {
+        if let Some(anchor) = &anchor {
+            if anchor.height % epoch_length(network) != 0 {
+                return Err(Error::Generic(format!("invalid anchor at height {}", anchor.height)));
+            }
+        }
+        std::fs::create_dir_all(path.as_ref())?;
+        let suffix = match &anchor {
+            Some(anchor) => format!("{:?}_{}", network, anchor.height),
+            None => format!("{:?}", network),
+        };
+        let mut filepath: PathBuf = path.as_ref().into();
+        filepath.push(format!("liquid_headers_chain_{}", suffix));
+        let mut params_path = filepath.clone();
+        params_path.set_file_name(format!("liquid_headers_params_{}", suffix));
+        let mut chain = LiquidHeadersChain {
+            path: filepath,
+            params_path,
+            start: None,
+            height: 0,
+            last: BlockHash::all_zeros(),
+            epochs: vec![],
+            votes: HashMap::new(),
+            anchor,
+            verifier: Verifier::new(network),
+            network,
+        };
+        if let Err(e) = chain.read() {
+            warn!("{:?} cannot be read ({:?}), creating", chain.path, e);
+            File::create(&chain.path)?;
+            chain.start = None;
+            chain.epochs.clear();
+            chain.flush_params()?;
+        }
+        Ok(chain)
+    }
+
+    fn read(&mut self) -> Result<(), Error> {
+        let file_size = File::open(&self.path)?.metadata()?.len();
+        if file_size % RECORD_SIZE != 0 {
+            return Err(Error::InvalidHeaders);
+        }
+        self.epochs = serde_cbor::from_reader(File::open(&self.params_path)?)?;
+        if file_size == 0 {
+            return Ok(());
+        }
+        let (start, params) = self.epochs.first().ok_or_else(|| Error::InvalidHeaders)?;
+        if let Some(anchor) = &self.anchor {
+            if *start != anchor.height || params.signblockscript() != Some(&anchor.signblockscript)
+            {
+                return Err(Error::InvalidHeaders);
+            }
+        }
+        let start = *start;
+        self.start = Some(start);
+        self.height = start + (file_size / RECORD_SIZE) as u32 - 1;
+        self.last = self.get(self.height)?.0;
+        self.count_votes()
+    }
+
+    /// height of the first header of the chain, if any
+    pub fn start(&self) -> Option<u32> {
+        self.start
+    }
+
+    /// height of the last header of the chain, if any
+    pub fn height(&self) -> Option<u32> {
+        self.start.map(|_| self.height)
+    }
+
+    /// the height where to start a new chain, given the height of the best chain
+    pub fn start_for(&self, tip_height: u32) -> u32 {
+        match &self.anchor {
+            Some(anchor) => anchor.height,
+            None => tip_height - tip_height % epoch_length(self.network),
+        }
+    }
+
+    /// the block hash and the merkle root of the header at `height`
+    pub fn get(&self, height: u32) -> Result<(BlockHash, TxMerkleNode), Error> {
+        let (hash, merkle_root, _) = self.get_record(height)?;
+        Ok((hash, merkle_root))
+    }
+
+    fn get_record(&self, height: u32) -> Result<(BlockHash, TxMerkleNode, [u8; 32]), Error> {
+        let start = match self.start {
+            Some(start) if start <= height && height <= self.height => start,
+            _ => return Err(Error::Generic(format!("height {} not in the chain", height))),
+        };
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start((height - start) as u64 * RECORD_SIZE))?;
+        let mut buf = [0u8; RECORD_SIZE as usize];
+        file.read_exact(&mut buf)?;
+        let mut proposed = [0u8; 32];
+        proposed.copy_from_slice(&buf[64..]);
+        Ok((BlockHash::from_slice(&buf[..32])?, TxMerkleNode::from_slice(&buf[32..64])?, proposed))
+    }
+
+    /// the signblockscript of the params in use, `None` before the dynafed activation
+    pub fn signblockscript(&self) -> Option<&Script> {
+        self.epochs.last().and_then(|(_, params)| params.signblockscript())
+    }
+
+    fn is_dynafed(&self) -> bool {
+        self.signblockscript().is_some()
+    }
+
+    /// count the votes of the current epoch from the persisted headers
+    fn count_votes(&mut self) -> Result<(), Error> {
+        self.votes.clear();
+        if let Some(start) = self.start {
+            let epoch_start = self.start_for(self.height).max(start);
+            for height in epoch_start..=self.height {
+                let (_, _, proposed) = self.get_record(height)?;
+                if proposed != [0u8; 32] {
+                    *self.votes.entry(sha256::Midstate::from_inner(proposed)).or_default() += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the root of the params voted by more than 4/5 of the headers of the epoch before
+    /// `height`, if any
+    fn winning_proposal(&self) -> Option<sha256::Midstate> {
+        let threshold = epoch_length(self.network) * 4 / 5;
+        self.votes.iter().find(|(_, votes)| **votes > threshold).map(|(root, _)| *root)
+    }
+
+    /// check `header` follows the chain and is signed by the federation in charge, updating the
+    /// params and the votes
+    fn check(&mut self, header: &BlockHeader) -> Result<[u8; 32], Error> {
+        let height = match self.start {
+            Some(_) => self.height + 1,
+            None => header.height,
+        };
+        if header.height != height || (self.start.is_some() && header.prev_blockhash != self.last) {
+            return Err(Error::InvalidHeaders);
+        }
+        let (current, proposed) = match &header.ext {
+            BlockExtData::Proof {
+                ..
+            } => {
+                // liquid v1 headers are before the dynafed activation
+                if self.is_dynafed() || self.verifier.verify_header(header).is_err() {
+                    return Err(Error::InvalidHeaders);
+                }
+                if self.start.is_none() {
+                    if !self.verifier.is_genesis(header) {
+                        return Err(Error::InvalidHeaders);
+                    }
+                    self.epochs.push((height, Params::Null));
+                }
+                return Ok([0u8; 32]);
+            }
+            BlockExtData::Dynafed {
+                current,
+                proposed,
+                ..
+            } => (current, proposed),
+        };
+        let is_transition = height % epoch_length(self.network) == 0;
+        let root = current.calculate_root();
+        match self.epochs.last() {
+            // the first header of the chain, at the anchor
+            None => {
+                let anchored = match &self.anchor {
+                    Some(anchor) => {
+                        height == anchor.height
+                            && current.signblockscript() == Some(&anchor.signblockscript)
+                    }
+                    None => self.verifier.is_regtest,
+                };
+                if !current.is_full() || !is_transition || !anchored {
+                    return Err(Error::InvalidHeaders);
+                }
+            }
+            // the first header after the dynafed activation
+            Some((_, Params::Null)) => {
+                if !current.is_full() {
+                    return Err(Error::InvalidHeaders);
+                }
+            }
+            Some((_, params)) if is_transition => {
+                let expected = self.winning_proposal().unwrap_or_else(|| params.calculate_root());
+                if !current.is_full() || root != expected {
+                    return Err(Error::InvalidHeaders);
+                }
+            }
+            Some((_, params)) => {
+                if !current.is_compact() || root != params.calculate_root() {
+                    return Err(Error::InvalidHeaders);
+                }
+            }
+        }
+        let signblockscript = current.signblockscript().ok_or_else(|| Error::InvalidHeaders)?;
+        self.verifier.verify_dynafed_header(header, signblockscript)?;
+
+        if is_transition || !self.is_dynafed() {
+            self.votes.clear();
+            if self.epochs.last().map(|(_, params)| params.calculate_root()) != Some(root) {
+                info!("dynafed params transition at height {}", height);
+                self.epochs.push((height, current.clone().into_compact().expect("full params")));
+            }
+        }
+        if proposed.is_null() {
+            Ok([0u8; 32])
+        } else {
+            let proposed = proposed.calculate_root();
+            *self.votes.entry(proposed).or_default() += 1;
+            Ok(proposed.into_inner())
+        }
+    }
+
+    /// write new headers to the file if checks are passed
+    ///
+    /// Headers not following the tip of the chain return `Error::InvalidHeaders`, a possible reorg.
+    pub fn push(&mut self, new_headers: Vec<BlockHeader>) -> Result<(), Error> {
+        let mut serialized = Vec::with_capacity(new_headers.len() * RECORD_SIZE as usize);
+        let mut result = Ok(());
+        let epochs = self.epochs.len();
+        for new_header in new_headers {
+            let proposed = match self.check(&new_header) {
+                Ok(proposed) => proposed,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+            let hash = new_header.block_hash();
+            serialized.extend(hash.into_inner());
+            serialized.extend(new_header.merkle_root.into_inner());
+            serialized.extend(proposed);
+            if self.start.is_none() {
+                info!("liquid headers chain starting at height {}", new_header.height);
+                self.start = Some(new_header.height);
+            }
+            self.height = new_header.height;
+            self.last = hash;
+        }
+        if self.epochs.len() != epochs {
+            self.flush_params()?;
+        }
+        if !serialized.is_empty() {
+            let mut file = OpenOptions::new().append(true).open(&self.path)?;
+            file.write_all(&serialized)?;
+            file.flush()?;
+            info!("liquid chain tip height {} hash {}", self.height, self.last);
+        }
+        result
+    }
+
+    /// remove the last `headers_to_remove` headers, keeping at least the first one
+    pub fn remove(&mut self, headers_to_remove: u32) -> Result<(), Error> {
+        let start = match self.start {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+        let new_height = self.height - headers_to_remove.min(self.height - start);
+        let file = OpenOptions::new().write(true).open(&self.path)?;
+        file.set_len((new_height - start + 1) as u64 * RECORD_SIZE)?;
+        self.height = new_height;
+        self.last = self.get(new_height)?.0;
+        let epochs = self.epochs.len();
+        self.epochs.retain(|(height, _)| *height <= new_height);
+        if self.epochs.len() != epochs {
+            self.flush_params()?;
+        }
+        self.count_votes()
+    }
+
+    fn flush_params(&self) -> Result<(), Error> {
+        let mut file = File::create(&self.params_path)?;
+        file.write_all(&serde_cbor::to_vec(&self.epochs)?)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// verify the given txid and the proof against the header at `height` in the chain
+    pub fn verify_tx_proof(
+        &self,
+        txid: &Txid,
+        height: u32,
+        merkle: GetMerkleRes,
+    ) -> Result<(), Error> {
+        let root: TxMerkleNode = compute_merkle_root(txid, merkle)?;
+        if self.get(height)?.1 == root {
+            info!("proof for txid {}, block height {}, merkle root matches", txid, height);
+            Ok(())
+        } else {
+            Err(Error::InvalidHeaders)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::headers::liquid::Verifier;
-    use bitcoin::hashes::hex::FromHex;
+    use crate::headers::liquid::{Anchor, LiquidHeadersChain, Verifier};
+    use bitcoin::hashes::hex::{FromHex, ToHex};
+    use bitcoin::hashes::Hash;
+    use electrum_client::GetMerkleRes;
+    use elements::dynafed::Params;
     use elements::encode::deserialize;
     use elements::{BlockExtData, BlockHeader, Script};
+    use elements::{BlockHash, TxMerkleNode, Txid};
+    use gdk_common::network::SpvLiquidAnchor;
     use gdk_common::ElementsNetwork;
     use rand::seq::SliceRandom;
+    use tempfile::TempDir;
 
     #[test]
     fn test_regtest() {
         let regtest_header : BlockHeader = deserialize(&Vec::<u8>::from_hex("000000a07da0ac2b4932e9501c0e192dfa8b4e6ddd801562f846bd04584bbfa6bd779520a297a6b54050bd32f46e7b738931f2bfc0f9ebc2663e2057dbdf26c5472c73439ee3ec5e01000000022200204ae81572f06e1b88fd5ced7a1a000945432e83e1551e6f721ee9c00b8cc332604a00000017a91472c44f957fc011d97e3406667dca5b1c930c4026870151014202fcba7ecf41bc7e1be4ee122d9d22e3333671eb0a3a87b5cdf099d59874e1940f02fcba7ecf41bc7e1be4ee122d9d22e3333671eb0a3a87b5cdf099d59874e1940f00010151").unwrap()).unwrap();
 
-        match regtest_header.ext {
+        match &regtest_header.ext {
             BlockExtData::Proof {
                 challenge: _,
                 solution: _,
             } => assert!(false),
             BlockExtData::Dynafed {
-                current: _,
+                current,
                 proposed: _,
                 signblock_witness: _,
-            } => assert!(true),
+            } => {
+                let verifier = Verifier::new(ElementsNetwork::ElementsRegtest);
+                let signblockscript = current.signblockscript().unwrap();
+                assert!(verifier.verify_dynafed_header(&regtest_header, signblockscript).is_ok());
+                let other = Script::new_v0_wsh(&Script::from(vec![0x52]).wscript_hash());
+                assert!(verifier.verify_dynafed_header(&regtest_header, &other).is_err());
+            }
         }
     }
 
@@ -255,4 +712,205 @@ mod test {
             assert!(false);
         }
     }
+
+    /// regtest params signing with a witness script of OP_TRUE, varying by the witness limit
+    fn params(signblock_witness_limit: u32) -> Params {
+        Params::Full {
+            signblockscript: Script::new_v0_wsh(&Script::from(vec![0x51]).wscript_hash()),
+            signblock_witness_limit,
+            fedpeg_program: bitcoin::Script::new(),
+            fedpegscript: vec![0x51],
+            extension_space: vec![],
+        }
+    }
+
+    /// a dynafed regtest header at `height` following `prev`, with the current params compacted
+    /// if not at an epoch start
+    fn header(prev: BlockHash, height: u32, current: &Params, proposed: &Params) -> BlockHeader {
+        let current = if height % 10 == 0 {
+            current.clone()
+        } else {
+            current.clone().into_compact().unwrap()
+        };
+        BlockHeader {
+            version: 0x20000000,
+            prev_blockhash: prev,
+            merkle_root: TxMerkleNode::hash(&height.to_le_bytes()),
+            time: 1_600_000_000 + height,
+            height,
+            ext: BlockExtData::Dynafed {
+                current,
+                proposed: proposed.clone(),
+                signblock_witness: vec![vec![0x51]],
+            },
+        }
+    }
+
+    /// headers from `from` to `to` included, voting for `proposed`
+    fn headers(
+        prev: BlockHash,
+        from: u32,
+        to: u32,
+        current: &Params,
+        proposed: &Params,
+    ) -> Vec<BlockHeader> {
+        let mut prev = prev;
+        (from..=to)
+            .map(|height| {
+                let header = header(prev, height, current, proposed);
+                prev = header.block_hash();
+                header
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_liquid_headers_chain() {
+        let temp = TempDir::new().unwrap();
+        let network = ElementsNetwork::ElementsRegtest;
+        let (a, b) = (params(74), params(75));
+
+        let mut chain = LiquidHeadersChain::new(&temp, network).unwrap();
+        assert_eq!(chain.height(), None);
+        assert_eq!(chain.start_for(17), 10);
+        let first = header(BlockHash::all_zeros(), 11, &a, &Params::Null);
+        assert!(chain.push(vec![first]).is_err(), "the chain starts at an epoch start");
+
+        // exactly 80% of the votes is not enough
+        let temp_80 = TempDir::new().unwrap();
+        let mut chain_80 = LiquidHeadersChain::new(&temp_80, network).unwrap();
+        let mut epoch = headers(BlockHash::all_zeros(), 10, 11, &a, &Params::Null);
+        let prev = epoch[1].block_hash();
+        epoch.extend(headers(prev, 12, 19, &a, &b));
+        chain_80.push(epoch.clone()).unwrap();
+        chain_80.push(vec![header(epoch[9].block_hash(), 20, &a, &Params::Null)]).unwrap();
+        assert_eq!(chain_80.signblockscript(), a.signblockscript());
+
+        // the epoch from 10 votes for b at 90%
+        let mut epoch = headers(BlockHash::all_zeros(), 10, 10, &a, &Params::Null);
+        let prev = epoch[0].block_hash();
+        epoch.extend(headers(prev, 11, 19, &a, &b));
+        chain.push(epoch.clone()).unwrap();
+        assert_eq!(chain.start(), Some(10));
+        assert_eq!(chain.height(), Some(19));
+        assert_eq!(chain.get(15).unwrap(), (epoch[5].block_hash(), epoch[5].merkle_root));
+        assert!(chain.get(9).is_err());
+        assert!(chain.get(20).is_err());
+
+        let tip = epoch[9].block_hash();
+        let not_activated = header(tip, 20, &a, &Params::Null);
+        assert!(chain.push(vec![not_activated]).is_err(), "the voted params must activate");
+        let compact = header(tip, 20, &b, &Params::Null);
+        let compact = BlockHeader {
+            ext: BlockExtData::Dynafed {
+                current: b.clone().into_compact().unwrap(),
+                proposed: Params::Null,
+                signblock_witness: vec![vec![0x51]],
+            },
+            ..compact
+        };
+        assert!(chain.push(vec![compact]).is_err(), "full params are needed at transitions");
+        let mut unsigned = header(tip, 20, &b, &Params::Null);
+        if let BlockExtData::Dynafed {
+            signblock_witness,
+            ..
+        } = &mut unsigned.ext
+        {
+            *signblock_witness = vec![vec![0x52]];
+        }
+        assert!(chain.push(vec![unsigned]).is_err());
+        let reorg = header(epoch[8].block_hash(), 20, &b, &Params::Null);
+        assert!(chain.push(vec![reorg]).is_err(), "not following the tip");
+        assert_eq!(chain.height(), Some(19));
+
+        let next = headers(tip, 20, 22, &b, &Params::Null);
+        chain.push(next.clone()).unwrap();
+        assert_eq!(chain.height(), Some(22));
+        assert_eq!(chain.signblockscript(), b.signblockscript());
+        let old_params = header(next[2].block_hash(), 23, &a, &Params::Null);
+        assert!(chain.push(vec![old_params]).is_err());
+
+        let txid = Txid::from_inner(next[1].merkle_root.into_inner());
+        let proof = || GetMerkleRes {
+            block_height: 21,
+            pos: 0,
+            merkle: vec![],
+        };
+        chain.verify_tx_proof(&txid, 21, proof()).unwrap();
+        assert!(chain.verify_tx_proof(&txid, 20, proof()).is_err());
+        assert!(chain.verify_tx_proof(&txid, 23, proof()).is_err());
+
+        // reopening recounts the votes from the persisted headers
+        drop(chain);
+        let mut chain = LiquidHeadersChain::new(&temp, network).unwrap();
+        assert_eq!(chain.height(), Some(22));
+        assert_eq!(chain.signblockscript(), b.signblockscript());
+        chain.remove(4).unwrap();
+        assert_eq!(chain.height(), Some(18));
+        assert_eq!(chain.signblockscript(), a.signblockscript());
+        let mut chain = LiquidHeadersChain::new(&temp, network).unwrap();
+        assert_eq!(chain.height(), Some(18));
+        let short = vec![epoch[9].clone(), header(tip, 20, &a, &Params::Null)];
+        assert!(chain.push(short).is_err(), "the votes are still counted after a reorg");
+        assert_eq!(chain.height(), Some(19));
+        chain.push(next).unwrap();
+        assert_eq!(chain.height(), Some(22));
+
+        chain.remove(100).unwrap();
+        assert_eq!(chain.height(), Some(10), "cannot remove the first header");
+    }
+
+    #[test]
+    fn test_liquid_headers_chain_anchor() {
+        let temp = TempDir::new().unwrap();
+        let network = ElementsNetwork::ElementsRegtest;
+        let a = params(74);
+        let other = Script::new_v0_wsh(&Script::from(vec![0x52]).wscript_hash());
+        let anchor = |height, signblockscript: &Script| Anchor {
+            height,
+            signblockscript: signblockscript.clone(),
+        };
+        let signblockscript = a.signblockscript().unwrap();
+        let invalid =
+            LiquidHeadersChain::with_anchor(&temp, network, Some(anchor(15, signblockscript)));
+        assert!(invalid.is_err(), "the anchor is at an epoch start");
+
+        let mut chain =
+            LiquidHeadersChain::with_anchor(&temp, network, Some(anchor(10, &other))).unwrap();
+        let epoch = headers(BlockHash::all_zeros(), 10, 12, &a, &Params::Null);
+        assert!(chain.push(epoch).is_err(), "the first header has the anchored signblockscript");
+        assert_eq!(chain.height(), None);
+
+        let mut chain =
+            LiquidHeadersChain::with_anchor(&temp, network, Some(anchor(10, signblockscript)))
+                .unwrap();
+        assert_eq!(chain.start_for(37), 10);
+        let later = headers(BlockHash::all_zeros(), 20, 22, &a, &Params::Null);
+        assert!(chain.push(later).is_err(), "the first header is at the anchor");
+        assert_eq!(chain.height(), None);
+        let epoch = headers(BlockHash::all_zeros(), 10, 12, &a, &Params::Null);
+        chain.push(epoch).unwrap();
+        assert_eq!(chain.start(), Some(10));
+
+        // a chain persisted with another anchor is not reused
+        let chain =
+            LiquidHeadersChain::with_anchor(&temp, network, Some(anchor(10, &other))).unwrap();
+        assert_eq!(chain.height(), None);
+        let chain = LiquidHeadersChain::new(&temp, network).unwrap();
+        assert_eq!(chain.height(), None);
+
+        // without an anchor the first header is trusted only on regtest
+        let mut chain = LiquidHeadersChain::new(&temp, ElementsNetwork::Liquid).unwrap();
+        assert_eq!(chain.start_for(20160 * 3 + 1), 20160 * 3);
+        let first = header(BlockHash::all_zeros(), 20160 * 3, &a, &Params::Null);
+        assert!(chain.push(vec![first]).is_err());
+
+        let params = SpvLiquidAnchor {
+            height: 20,
+            signblockscript: signblockscript.as_bytes().to_hex(),
+        };
+        let parsed = Anchor::from_params(Some(&params), network).unwrap();
+        assert_eq!(parsed, Some(anchor(20, signblockscript)));
+        assert_eq!(Anchor::from_params(None, network).unwrap(), None);
+    }
 }
@@ -1,13 +1,14 @@
+use crate::backend::Backend;
 use crate::error::Error;
-use crate::headers::bitcoin::{HeadersChain, HEADERS_FILE_MUTEX};
-use crate::headers::liquid::Verifier;
-use crate::session::determine_electrum_url;
+use crate::headers::bitcoin::{Checkpoint, HeadersChain, HEADERS_FILE_MUTEX};
+use crate::headers::liquid::{LiquidHeadersChain, Verifier};
+use crate::session::determine_backend_url;
 use ::bitcoin::hashes::hex::ToHex;
 use ::bitcoin::hashes::{sha256, sha256d, Hash};
 use aes_gcm_siv::aead::NewAead;
 use aes_gcm_siv::{Aes256GcmSiv, Key};
-use electrum_client::{Client, ElectrumApi, GetMerkleRes};
-use gdk_common::be::{BETxid, BETxidConvert};
+use electrum_client::GetMerkleRes;
+use gdk_common::be::{BEBlockHash, BETxid, BETxidConvert};
 use gdk_common::model::{
     SPVCommonParams, SPVDownloadHeadersParams, SPVDownloadHeadersResult, SPVVerifyTxParams,
     SPVVerifyTxResult,
@@ -28,11 +29,44 @@ pub enum ChainOrVerifier {
     Chain(HeadersChain),
 
     /// used for elements networks
-    Verifier(Verifier),
+    LiquidChain(LiquidHeadersChain),
+}
+
+impl ChainOrVerifier {
+    /// height of the last header downloaded, if any
+    pub fn height(&self) -> Option<u32> {
+        match self {
+            ChainOrVerifier::Chain(chain) => Some(chain.height()),
+            ChainOrVerifier::LiquidChain(chain) => chain.height(),
+        }
+    }
+
+    /// height of the first header downloaded, if any
+    pub fn start(&self) -> Option<u32> {
+        match self {
+            ChainOrVerifier::Chain(chain) => Some(chain.start().height),
+            ChainOrVerifier::LiquidChain(chain) => chain.start(),
+        }
+    }
+
+    pub fn network(&self) -> NetworkId {
+        match self {
+            ChainOrVerifier::Chain(chain) => NetworkId::Bitcoin(chain.network),
+            ChainOrVerifier::LiquidChain(chain) => NetworkId::Elements(chain.network),
+        }
+    }
+
+    /// hash of the header at `height`
+    pub fn block_hash(&self, height: u32) -> Result<BEBlockHash, Error> {
+        Ok(match self {
+            ChainOrVerifier::Chain(chain) => BEBlockHash::Bitcoin(chain.get(height)?.block_hash()),
+            ChainOrVerifier::LiquidChain(chain) => BEBlockHash::Elements(chain.get(height)?.0),
+        })
+    }
 }
 
 /// compute the merkle root from the merkle path of a tx in electrum format (note the hash.reverse())
-fn compute_merkle_root<T, N>(txid: &T, merkle: GetMerkleRes) -> Result<N, Error>
+pub(crate) fn compute_merkle_root<T, N>(txid: &T, merkle: GetMerkleRes) -> Result<N, Error>
 where
     T: Hash<Inner = [u8; 32]>, // bitcoin::Txid or elements::Txid
     N: Hash<Inner = [u8; 32]>, // bitcoin::TxMerkleNode or elements::TxMerkleNode
@@ -58,20 +92,24 @@ where
 }
 
 trait ParamsMethods {
-    fn build_client(&self) -> Result<Client, Error>;
+    fn build_client(&self) -> Result<Box<dyn Backend>, Error>;
     fn headers_chain(&self) -> Result<HeadersChain, Error>;
     fn verified_cache(&self) -> Result<VerifiedCache, Error>;
     fn bitcoin_network(&self) -> Option<::bitcoin::Network>;
 }
 
 impl ParamsMethods for SPVCommonParams {
-    fn build_client(&self) -> Result<Client, Error> {
-        let url = determine_electrum_url(&self.network)?;
+    fn build_client(&self) -> Result<Box<dyn Backend>, Error> {
+        let url = determine_backend_url(&self.network)?;
         url.build_client(self.network.proxy.as_deref(), self.timeout)
     }
     fn headers_chain(&self) -> Result<HeadersChain, Error> {
         let network = self.bitcoin_network().expect("headers_chain available only on bitcoin");
-        Ok(HeadersChain::new(&self.network.state_dir, network)?)
+        Ok(HeadersChain::with_checkpoint(
+            &self.network.state_dir,
+            network,
+            Checkpoint::from_params(self.network.spv_checkpoint.as_ref(), network)?,
+        )?)
     }
     fn verified_cache(&self) -> Result<VerifiedCache, Error> {
         Ok(VerifiedCache::new(&self.network.state_dir, self.network.id(), &self.encryption_key))
@@ -97,7 +135,7 @@ pub fn download_headers(
     let client = input.params.build_client()?;
     let mut chain = input.params.headers_chain()?;
     let headers_to_download = input.headers_to_download.unwrap_or(2016);
-    let headers = client.block_headers(chain.height() as usize + 1, headers_to_download)?.headers;
+    let headers = client.block_headers(chain.height() as usize + 1, headers_to_download)?;
     info!("height:{} downloaded_headers:{}", chain.height(), headers.len());
     let mut reorg_happened = false;
     if let Err(Error::InvalidHeaders) = chain.push(headers) {
@@ -26,6 +26,10 @@ impl ElectrumUrl {
 
         config = config.timeout(Some(timeout))?;
 
+        // a silent reconnection would lose the subscriptions, errors must reach the caller which
+        // rebuilds the client
+        config = config.retry(0);
+
         let (url, config) = match self {
             ElectrumUrl::Tls(url, validate) => {
                 (format!("ssl://{}", url), config.validate_domain(*validate))
@@ -0,0 +1,281 @@
+use bitcoin::hashes::{sha256, Hash};
+use elements::confidential::{AssetBlindingFactor, Value, ValueBlindingFactor};
+use elements::secp256k1_zkp::{
+    Generator, PedersenCommitment, RangeProof, SecretKey, Tweak, ZERO_TWEAK,
+};
+use elements::{AssetId, ContractHash, OutPoint, TxIn, TxOut};
+
+use gdk_common::be::{BETransaction, BETransactions};
+use gdk_common::wally::{asset_blinding_key_to_ec_private_key, MasterBlindingKey};
+
+use crate::error::Error;
+
+/// The maximum amount of an issuance, as for any other value in elements
+pub const MAX_ISSUANCE_AMOUNT: u64 = 21_000_000 * 100_000_000;
+
+/// The hash of a registry contract, committed in the asset id of new issuances
+pub fn contract_hash(contract: &serde_json::Value) -> Result<ContractHash, Error> {
+    Ok(ContractHash::from_json_contract(&serde_json::to_string(contract)?)?)
+}
+
+/// The entropy of the asset issued or reissued by `input`
+pub fn issuance_entropy(input: &TxIn) -> sha256::Midstate {
+    if input.asset_issuance.asset_blinding_nonce == ZERO_TWEAK {
+        let contract_hash = ContractHash::from_inner(input.asset_issuance.asset_entropy);
+        AssetId::generate_asset_entropy(input.previous_output, contract_hash)
+    } else {
+        sha256::Midstate::from_inner(input.asset_issuance.asset_entropy)
+    }
+}
+
+/// Look for a new issuance of `asset_id` among `all_txs`, returning the asset entropy and whether
+/// the issued amount was confidential, which determines the reissuance token id
+pub fn find_issuance(
+    all_txs: &BETransactions,
+    asset_id: &AssetId,
+) -> Option<(sha256::Midstate, bool)> {
+    all_txs.values().find_map(|entry| match &entry.tx {
+        BETransaction::Elements(tx) => tx
+            .input
+            .iter()
+            .filter(|i| i.has_issuance() && i.asset_issuance.asset_blinding_nonce == ZERO_TWEAK)
+            .find(|i| &AssetId::from_entropy(issuance_entropy(i)) == asset_id)
+            .map(|i| (issuance_entropy(i), i.asset_issuance.amount.is_confidential())),
+        BETransaction::Bitcoin(_) => None,
+    })
+}
+
+/// The key used to blind the issuance amounts of an input spending `outpoint`
+///
+/// As in Elements Core it's derived from a script committing to the spent outpoint, so the wallet
+/// can later unblind the issuance without storing anything.
+pub fn issuance_blinding_key(
+    master_blinding: &MasterBlindingKey,
+    outpoint: &OutPoint,
+) -> SecretKey {
+    let script = elements::script::Builder::new()
+        .push_opcode(elements::opcodes::all::OP_RETURN)
+        .push_slice(&outpoint.txid[..])
+        .push_int(outpoint.vout as i64)
+        .into_script();
+    asset_blinding_key_to_ec_private_key(master_blinding, &script)
+}
+
+fn issuance_generator(asset: AssetId) -> Generator {
+    Generator::new_blinded(&crate::EC, asset.into_tag(), AssetBlindingFactor::zero().into_inner())
+}
+
+/// Blind the explicit issuance amounts of `input`, with rangeproofs rewindable with `blinding_key`
+///
+/// The value blinding factors can be recovered with [`issuance_value_blinders`] and must be
+/// accounted for when blinding the transaction outputs.
+pub fn blind_issuance(input: &mut TxIn, blinding_key: SecretKey) -> Result<(), Error> {
+    let entropy = issuance_entropy(input);
+    let asset_id = AssetId::from_entropy(entropy);
+    let token_id = AssetId::reissuance_token_from_entropy(entropy, true);
+    let mut rng = rand::thread_rng();
+    let issuance = &mut input.asset_issuance;
+    let witness = &mut input.witness;
+
+    if let Value::Explicit(value) = issuance.amount {
+        let (commitment, proof) = blind_amount(value, asset_id, blinding_key, &mut rng)?;
+        issuance.amount = commitment;
+        witness.amount_rangeproof = Some(Box::new(proof));
+    }
+    if let Value::Explicit(value) = issuance.inflation_keys {
+        let (commitment, proof) = blind_amount(value, token_id, blinding_key, &mut rng)?;
+        issuance.inflation_keys = commitment;
+        witness.inflation_keys_rangeproof = Some(Box::new(proof));
+    }
+    Ok(())
+}
+
+fn blind_amount(
+    value: u64,
+    asset: AssetId,
+    blinding_key: SecretKey,
+    rng: &mut impl rand::RngCore,
+) -> Result<(Value, RangeProof), Error> {
+    let generator = issuance_generator(asset);
+    let vbf = ValueBlindingFactor::new(rng).into_inner();
+    let commitment = PedersenCommitment::new(&crate::EC, value, vbf, generator);
+    // the rangeproof message is the asset and its blinding factor, which is zero for issuances
+    let mut message = [0u8; 64];
+    message[..32].copy_from_slice(asset.into_tag().as_ref());
+    let proof = RangeProof::new(
+        &crate::EC,
+        TxOut::RANGEPROOF_MIN_VALUE,
+        commitment,
+        value,
+        vbf,
+        &message,
+        &[],
+        blinding_key,
+        TxOut::RANGEPROOF_EXP_SHIFT,
+        TxOut::RANGEPROOF_MIN_PRIV_BITS,
+        generator,
+    )?;
+    Ok((Value::Confidential(commitment), proof))
+}
+
+/// The value blinding factors of the confidential issuance amounts of `input`
+pub fn issuance_value_blinders(input: &TxIn, blinding_key: SecretKey) -> Result<Vec<Tweak>, Error> {
+    let (asset_id, token_id) = input.issuance_ids();
+    let issuance = &input.asset_issuance;
+    let witness = &input.witness;
+    let amounts = [
+        (issuance.amount, &witness.amount_rangeproof, asset_id),
+        (issuance.inflation_keys, &witness.inflation_keys_rangeproof, token_id),
+    ];
+    let mut blinders = vec![];
+    for (value, proof, asset) in amounts.iter() {
+        if let Value::Confidential(commitment) = value {
+            let proof = proof
+                .as_ref()
+                .ok_or_else(|| Error::Generic("missing issuance rangeproof".into()))?;
+            let (opening, _) = proof.rewind(
+                &crate::EC,
+                *commitment,
+                blinding_key,
+                &[],
+                issuance_generator(*asset),
+            )?;
+            blinders.push(opening.blinding_factor);
+        }
+    }
+    Ok(blinders)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use elements::hashes::hex::FromHex;
+    use elements::{AssetIssuance, Script, TxOutSecrets};
+    use gdk_registry::AssetEntry;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    const PREVOUT: &str = "0000000000000000000000000000000000000000000000000000000000000001:0";
+
+    fn issuance_input(contract_hash: ContractHash, asset: u64, token: u64) -> TxIn {
+        TxIn {
+            previous_output: OutPoint::from_str(PREVOUT).unwrap(),
+            asset_issuance: AssetIssuance {
+                asset_blinding_nonce: ZERO_TWEAK,
+                asset_entropy: contract_hash.into_inner(),
+                amount: Value::Explicit(asset),
+                inflation_keys: Value::Explicit(token),
+            },
+            sequence: 0xffff_fffe,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn issuance_contract() {
+        let contract = json!({
+            "entity": {"domain": "example.com"},
+            "issuer_pubkey": "0337cceec0beea0232ebe14cba0197a9fbd45fcf2ec946749de920e71434c2b904",
+            "name": "Test Asset",
+            "precision": 8,
+            "ticker": "TEST",
+            "version": 0,
+        });
+        let contract_hash = contract_hash(&contract).unwrap();
+        let input = issuance_input(contract_hash, 1000, 1);
+        let asset_id = AssetId::from_entropy(issuance_entropy(&input));
+        assert_eq!(input.issuance_ids().0, asset_id);
+
+        let entry: AssetEntry = serde_json::from_value(json!({
+            "asset_id": asset_id.to_string(),
+            "contract": contract,
+            "entity": contract["entity"],
+            "issuance_prevout": {
+                "txid": input.previous_output.txid.to_string(),
+                "vout": input.previous_output.vout,
+            },
+            "issuer_pubkey": contract["issuer_pubkey"],
+            "name": contract["name"],
+            "precision": 8,
+            "ticker": "TEST",
+            "version": 0,
+        }))
+        .unwrap();
+        assert!(entry.verifies().unwrap());
+    }
+
+    #[test]
+    fn confidential_issuance() {
+        let policy_asset = AssetId::from_slice(&[1u8; 32]).unwrap();
+        let prevout_script =
+            Script::from_hex("0014d0c4a3ef09e997b6e99e397e518fe3e41a118ca1").unwrap();
+        let prevout = elements::TxOut {
+            asset: elements::confidential::Asset::Explicit(policy_asset),
+            value: Value::Explicit(100_000),
+            script_pubkey: prevout_script.clone(),
+            ..Default::default()
+        };
+
+        let mut input = issuance_input(ContractHash::all_zeros(), 1000, 1);
+        let blinding_key = SecretKey::from_slice(&[3u8; 32]).unwrap();
+        blind_issuance(&mut input, blinding_key).unwrap();
+        assert!(input.asset_issuance.amount.is_confidential());
+        assert!(input.asset_issuance.inflation_keys.is_confidential());
+        let (asset_id, token_id) = input.issuance_ids();
+        assert_eq!(
+            token_id,
+            AssetId::reissuance_token_from_entropy(issuance_entropy(&input), true)
+        );
+
+        let receiver = SecretKey::from_slice(&[4u8; 32]).unwrap();
+        let receiver = elements::bitcoin::PublicKey::new(
+            elements::secp256k1_zkp::PublicKey::from_secret_key(&crate::EC, &receiver),
+        );
+        let output = |asset, value| elements::TxOut {
+            asset: elements::confidential::Asset::Explicit(asset),
+            value: Value::Explicit(value),
+            nonce: elements::confidential::Nonce::Confidential(receiver.inner),
+            script_pubkey: prevout_script.clone(),
+            ..Default::default()
+        };
+        let tx = elements::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![input.clone()],
+            output: vec![
+                output(asset_id, 1000),
+                output(token_id, 1),
+                output(policy_asset, 99_000),
+                elements::TxOut::new_fee(1000, policy_asset),
+            ],
+        };
+
+        let mut pset = elements::pset::PartiallySignedTransaction::from_tx(tx);
+        // as in blind_tx, remove the issuance flag set by from_tx
+        pset.inputs_mut()[0].previous_output_index &= !(1 << 31);
+        pset.inputs_mut()[0].witness_utxo = Some(prevout.clone());
+        for output in pset.outputs_mut().iter_mut() {
+            output.blinder_index = Some(0);
+        }
+        let mut inp_txout_sec = HashMap::new();
+        inp_txout_sec.insert(
+            0,
+            TxOutSecrets::new(
+                policy_asset,
+                AssetBlindingFactor::zero(),
+                100_000,
+                ValueBlindingFactor::zero(),
+            ),
+        );
+        pset.global.scalars.extend(issuance_value_blinders(&input, blinding_key).unwrap());
+        pset.blind_last(&mut rand::thread_rng(), &crate::EC, &inp_txout_sec).unwrap();
+        let tx = pset.extract_tx().unwrap();
+        assert_eq!(tx.input[0].previous_output, input.previous_output);
+        tx.verify_tx_amt_proofs(&crate::EC, &[prevout]).unwrap();
+
+        // rewinding with another key fails
+        let other_key = SecretKey::from_slice(&[5u8; 32]).unwrap();
+        assert!(issuance_value_blinders(&input, other_key).is_err());
+    }
+}
@@ -11,23 +11,41 @@ use log::{debug, info, trace, warn};
 use serde_json::Value;
 
 pub mod account;
+pub mod backend;
+pub mod coin_selection;
+pub mod descriptor;
 pub mod error;
+pub mod esplora;
 pub mod headers;
 pub mod interface;
+pub mod issuance;
+pub mod message;
+pub mod peg;
 pub mod pin;
+pub mod pool;
+pub mod psbt;
 pub mod pset;
+pub mod rpc;
 pub mod session;
+pub mod signer;
 pub mod spv;
+pub mod swap;
+pub mod sweep;
+pub mod unblinded;
 
 use crate::account::{
     discover_account, get_account_derivation, get_account_script_purpose,
-    get_last_next_account_nums, Account,
+    get_last_next_account_nums, xpubs_equivalent, Account,
+};
+use crate::backend::{Backend, BackendUrl, ScriptStatus};
+use crate::descriptor::{
+    parse_ct_descriptor, parse_single_sig_descriptor, parse_slip132_extended_pubkey,
 };
 use crate::error::Error;
-use crate::interface::ElectrumUrl;
 use crate::store::*;
 
 use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::{sha256, Hash};
 use bitcoin::secp256k1::{self, SecretKey};
 use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
 
@@ -52,18 +70,20 @@ use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use std::{iter, thread};
 
-use crate::headers::bitcoin::HeadersChain;
-use crate::headers::liquid::Verifier;
+use crate::headers::bitcoin::{Checkpoint, HeadersChain};
+use crate::headers::liquid::{Anchor, LiquidHeadersChain, Verifier, LIQUID_HEADERS_FILE_MUTEX};
 use crate::headers::ChainOrVerifier;
 use crate::pin::PinManager;
+use crate::signer::Signer;
 use crate::spv::SpvCrossValidator;
 use aes::Aes256;
 use bitcoin::blockdata::constants::DIFFCHANGE_INTERVAL;
 use block_modes::block_padding::Pkcs7;
 use block_modes::BlockMode;
 use block_modes::Cbc;
-use electrum_client::{Client, ElectrumApi};
-pub use gdk_common::notification::{NativeNotif, Notification, TransactionNotification};
+pub use gdk_common::notification::{
+    NativeNotif, Notification, ReorgNotification, TransactionNotification,
+};
 use once_cell::sync::Lazy;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
@@ -71,7 +91,7 @@ use rand::Rng;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::JoinHandle;
 
 const CROSS_VALIDATION_RATE: u8 = 4; // Once every 4 thread loop runs, or roughly 28 seconds
@@ -91,6 +111,9 @@ struct Syncer {
     master_blinding: Option<MasterBlindingKey>,
     network: NetworkParameters,
     recent_spent_utxos: Arc<RwLock<HashSet<BEOutPoint>>>,
+
+    /// scripts subscribed with the current client, with their last status hash notified
+    subscribed: Mutex<HashMap<bitcoin::Script, Option<sha256::Hash>>>,
 }
 
 pub struct Tipper {
@@ -108,7 +131,7 @@ pub struct ElectrumSession {
     pub proxy: Option<String>,
     pub timeout: Option<u8>,
     pub network: NetworkParameters,
-    pub url: ElectrumUrl,
+    pub url: BackendUrl,
 
     /// Accounts of the wallet
     pub accounts: Arc<RwLock<HashMap<u32, Account>>>,
@@ -141,6 +164,9 @@ pub struct ElectrumSession {
     /// This set it emptied after every sync.
     pub recent_spent_utxos: Arc<RwLock<HashSet<BEOutPoint>>>,
 
+    /// External signer, if set it is used instead of the master xprv to sign transactions
+    pub signer: Option<Arc<dyn Signer>>,
+
     xr_cache: ExchangeRatesCache,
 }
 
@@ -179,13 +205,13 @@ fn socksify(proxy: Option<&str>) -> Option<String> {
     }
 }
 
-fn try_get_fee_estimates(client: &Client) -> Result<Vec<FeeEstimate>, Error> {
+fn try_get_fee_estimates(client: &dyn Backend) -> Result<Vec<FeeEstimate>, Error> {
     let relay_fee = (client.relay_fee()? * 100_000_000.0) as u64;
     let blocks: Vec<usize> = (1..25).collect();
     // max is covering a rounding errors in production electrs which sometimes cause a fee
     // estimates lower than relay fee
     let mut estimates: Vec<FeeEstimate> = client
-        .batch_estimate_fee(blocks)?
+        .batch_estimate_fee(&blocks)?
         .iter()
         .map(|e| FeeEstimate(relay_fee.max((*e * 100_000_000.0) as u64)))
         .collect();
@@ -237,17 +263,17 @@ impl ElectrumSession {
             // We can't call start_threads() here because not everything is loaded before login,
             // but we need to emit a network notification, to do so we test the electrum server
             // with a ping to emit a notification
-            let electrum_url = self.url.clone();
+            let backend_url = self.url.clone();
             let proxy = self.proxy.clone();
-            match electrum_url.build_client(proxy.as_deref(), None) {
+            match backend_url.build_client(proxy.as_deref(), None) {
                 Ok(client) => match client.ping() {
                     Ok(_) => {
-                        info!("succesfully pinged electrum server {:?}", electrum_url.url());
+                        info!("succesfully pinged server {:?}", backend_url.url());
                         self.last_network_call_succeeded.store(true, Ordering::Relaxed);
                         true
                     }
                     Err(e) => {
-                        warn!("failed to ping electrum server {:?}: {:?}", electrum_url.url(), e);
+                        warn!("failed to ping server {:?}: {:?}", backend_url.url(), e);
                         false
                     }
                 },
@@ -421,6 +447,108 @@ impl ElectrumSession {
         self.get_wallet_hash_id()
     }
 
+    /// Login without private keys, creating an account for each given xpub or descriptor
+    ///
+    /// Since there is no master xpub, the store is keyed by the xpub of the first account.
+    /// On Liquid the master blinding key, given directly or by the confidential descriptors, is
+    /// needed to unblind the outputs.
+    pub fn login_watch_only(
+        &mut self,
+        credentials: WatchOnlyCredentials,
+    ) -> Result<LoginData, Error> {
+        info!("login watch-only {:?}", self.network);
+
+        if self.master_xpub.is_some() {
+            return self.get_wallet_hash_id();
+        }
+        let mut keys = vec![];
+        let mut master_blinding_keys: Vec<_> =
+            credentials.master_blinding_key.iter().cloned().collect();
+        for slip132 in credentials.slip132_extended_pubkeys.iter() {
+            let (xpub, mainnet, script_type) = parse_slip132_extended_pubkey(slip132)?;
+            if mainnet != self.network.mainnet {
+                return Err(Error::InvalidExtendedPubKey(format!("wrong network {}", slip132)));
+            }
+            keys.push((xpub, None, script_type));
+        }
+        for desc in credentials.core_descriptors.iter() {
+            let (xpub, key_origin, script_type) = if desc.trim().starts_with("ct(") {
+                let (master_blinding_key, xpub, key_origin, script_type) =
+                    parse_ct_descriptor(desc)?;
+                master_blinding_keys.push(master_blinding_key);
+                (xpub, key_origin, script_type)
+            } else {
+                parse_single_sig_descriptor(desc)?
+            };
+            if (xpub.network == bitcoin::Network::Bitcoin) != self.network.mainnet {
+                return Err(Error::InvalidDescriptor(format!("wrong network {}", desc)));
+            }
+            keys.push((xpub, key_origin, script_type));
+        }
+        let first_xpub = keys
+            .first()
+            .map(|(xpub, _, _)| *xpub)
+            .ok_or_else(|| Error::Generic("watch-only login requires at least one key".into()))?;
+        let master_blinding_key = master_blinding_keys.pop();
+        if master_blinding_keys.iter().any(|k| Some(k) != master_blinding_key.as_ref()) {
+            return Err(Error::Generic("mismatching master blinding keys".into()));
+        }
+        if master_blinding_key.is_some() && !self.network.liquid {
+            return Err(Error::Generic("master blinding keys are supported only on Liquid".into()));
+        }
+
+        self.load_store(&LoadStoreOpt {
+            master_xpub: first_xpub,
+        })?;
+
+        // if missing on Liquid, the key must have been set in a previous login
+        if let Some(master_blinding_key) = master_blinding_key {
+            let stored = self.get_master_blinding_key()?.master_blinding_key;
+            if stored.map_or(false, |k| k != master_blinding_key) {
+                return Err(Error::Generic("master blinding key mismatch".into()));
+            }
+            self.set_master_blinding_key(&SetMasterBlindingKeyOpt {
+                master_blinding_key,
+            })?;
+        }
+        let master_blinding = self.store()?.read()?.cache.master_blinding.clone();
+        if self.network.liquid && master_blinding.is_none() {
+            // allow another login attempt with the key
+            self.master_xpub = None;
+            return Err(Error::Generic(
+                "liquid watch-only login requires a master blinding key or ct descriptors".into(),
+            ));
+        }
+
+        {
+            let store = self.store()?;
+            let mut accounts = self.accounts.write()?;
+            for (xpub, key_origin, script_type) in keys {
+                if accounts.values().any(|a| xpubs_equivalent(a.xpub(), &xpub).is_ok()) {
+                    // duplicated key
+                    continue;
+                }
+                // accounts are created without checking the gaps as they can't be discovered
+                let (_, account_num) =
+                    get_last_next_account_nums(accounts.keys().copied().collect(), script_type);
+                let account = Account::new(
+                    self.network.clone(),
+                    &None,
+                    &Some(xpub),
+                    key_origin,
+                    master_blinding.clone(),
+                    store.clone(),
+                    account_num,
+                    false,
+                )?;
+                accounts.insert(account_num, account);
+            }
+        }
+
+        self.start_threads()?;
+        self.get_wallet_hash_id()
+    }
+
     pub fn join_threads(&mut self) {
         while let Some(handle) = self.handles.pop() {
             handle.join().expect("Couldn't join on the associated thread");
@@ -473,7 +601,7 @@ impl ElectrumSession {
             info!("building built end");
             let fee_store = self.store()?;
             thread::spawn(move || {
-                match try_get_fee_estimates(&fee_client) {
+                match try_get_fee_estimates(fee_client.as_ref()) {
                     Ok(fee_estimates) => {
                         fee_store.write().unwrap().cache.fee_estimates = fee_estimates
                     }
@@ -489,11 +617,18 @@ impl ElectrumSession {
         if self.network.spv_enabled.unwrap_or(false) {
             let checker = match self.network.id() {
                 NetworkId::Bitcoin(network) => {
-                    ChainOrVerifier::Chain(HeadersChain::new(&self.network.state_dir, network)?)
+                    ChainOrVerifier::Chain(HeadersChain::with_checkpoint(
+                        &self.network.state_dir,
+                        network,
+                        Checkpoint::from_params(self.network.spv_checkpoint.as_ref(), network)?,
+                    )?)
                 }
                 NetworkId::Elements(network) => {
-                    let verifier = Verifier::new(network);
-                    ChainOrVerifier::Verifier(verifier)
+                    ChainOrVerifier::LiquidChain(LiquidHeadersChain::with_anchor(
+                        &self.network.state_dir,
+                        network,
+                        Anchor::from_params(self.network.spv_liquid_anchor.as_ref(), network)?,
+                    )?)
                 }
             };
 
@@ -522,14 +657,15 @@ impl ElectrumSession {
                         info!("closing headers thread");
                         break;
                     }
-                    let mut _lock;
-                    if let ChainOrVerifier::Chain(chain) = &headers.checker {
-                        _lock = HEADERS_FILE_MUTEX
-                            .get(&chain.network)
-                            .expect("unreachable because map populate with every enum variants")
-                            .lock()
-                            .unwrap();
+                    let _lock = match &headers.checker {
+                        ChainOrVerifier::Chain(chain) => HEADERS_FILE_MUTEX.get(&chain.network),
+                        ChainOrVerifier::LiquidChain(chain) => {
+                            LIQUID_HEADERS_FILE_MUTEX.get(&chain.network)
+                        }
                     }
+                    .expect("unreachable because map populate with every enum variants")
+                    .lock()
+                    .unwrap();
 
                     if let Ok(client) = headers_url.build_client(proxy.as_deref(), None) {
                         loop {
@@ -537,7 +673,7 @@ impl ElectrumSession {
                                 info!("closing headers thread");
                                 break 'outer;
                             }
-                            match headers.ask(chunk_size, &client) {
+                            match headers.ask(chunk_size, client.as_ref()) {
                                 Ok(headers_found) => {
                                     if headers_found < chunk_size {
                                         break;
@@ -546,14 +682,35 @@ impl ElectrumSession {
                                     }
                                 }
                                 Err(Error::InvalidHeaders) => {
-                                    warn!("invalid headers");
-                                    // this should handle reorgs and also broke IO writes update
-                                    headers.store.write().unwrap().cache.txs_verif.clear();
-                                    if let Err(e) = headers.remove(max_reorg_blocks) {
-                                        warn!("failed removing headers: {:?}", e);
-                                        break;
+                                    warn!("invalid headers, looking for a reorg");
+                                    let height = match headers.checker.height() {
+                                        Some(height) => height,
+                                        None => break,
+                                    };
+                                    let fork_height = match headers
+                                        .fork_height(client.as_ref(), max_reorg_blocks)
+                                    {
+                                        Ok(Some(fork_height)) if fork_height == height => {
+                                            warn!("not a reorg, the server sent invalid headers");
+                                            break;
+                                        }
+                                        Ok(Some(fork_height)) => fork_height,
+                                        Ok(None) => {
+                                            warn!("reorg deeper than {} blocks", max_reorg_blocks);
+                                            height.saturating_sub(max_reorg_blocks)
+                                        }
+                                        Err(e) => {
+                                            warn!("failed looking for the fork: {:?}", e);
+                                            break;
+                                        }
+                                    };
+                                    match headers.reorg(fork_height) {
+                                        Ok(ntf) => notify_blocks.reorg(&ntf),
+                                        Err(e) => {
+                                            warn!("failed removing headers: {:?}", e);
+                                            break;
+                                        }
                                     }
-                                    // XXX clear affected blocks/txs more surgically?
                                 }
                                 Err(e) => {
                                     warn!("error while asking headers {}", e);
@@ -562,7 +719,7 @@ impl ElectrumSession {
                             }
                         }
 
-                        match headers.get_proofs(&client) {
+                        match headers.get_proofs(client.as_ref()) {
                             Ok(found) => {
                                 if found > 0 {
                                     info!("found proof {}", found)
@@ -601,6 +758,7 @@ impl ElectrumSession {
             master_blinding: master_blinding.clone(),
             network: self.network.clone(),
             recent_spent_utxos: self.recent_spent_utxos.clone(),
+            subscribed: Default::default(),
         };
 
         let tipper = Tipper {
@@ -624,26 +782,26 @@ impl ElectrumSession {
         let syncer_tipper_handle = thread::spawn(move || {
             info!("starting syncer & tipper thread");
 
-            let update_tip = |client: &Client, do_update: bool| match tipper.tip(&client, do_update)
-            {
-                Ok(Some((height, header))) => {
-                    // This is a new block
-                    if do_update {
-                        notify.block_from_header(height, &header);
+            let update_tip =
+                |client: &dyn Backend, do_update: bool| match tipper.tip(client, do_update) {
+                    Ok(Some((height, header))) => {
+                        // This is a new block
+                        if do_update {
+                            notify.block_from_header(height, &header);
+                        }
+                        Ok(Some(height))
                     }
-                    Ok(Some(height))
-                }
-                Ok(None) => Ok(None), // nothing to update
-                Err(e) => {
-                    warn!("exception in tipper {:?}", e);
-                    Err(e)
-                }
-            };
+                    Ok(None) => Ok(None), // nothing to update
+                    Err(e) => {
+                        warn!("exception in tipper {:?}", e);
+                        Err(e)
+                    }
+                };
 
             let mut first_sync = true;
 
-            let mut sync = |client: &Client| {
-                match syncer.sync(&client) {
+            let mut sync = |client: &dyn Backend| {
+                match syncer.sync(client) {
                     Ok(tx_ntfs) => {
                         state_updater.update_if_needed(true);
                         // Skip sending transaction notifications if it's the
@@ -657,56 +815,87 @@ impl ElectrumSession {
                             }
                         }
                         first_sync = false;
+                        true
                     }
                     Err(e) => {
                         state_updater.update_if_needed(false);
-                        warn!("Error during sync, {:?}", e)
+                        warn!("Error during sync, {:?}", e);
+                        false
                     }
                 }
             };
 
-            loop {
-                match url.build_client(proxy.as_deref(), None) {
-                    Ok(client) => {
-                        let tip_before_sync = match update_tip(&client, false) {
-                            Ok(height) => height,
-                            Err(_) => {
-                                continue;
-                            }
-                        };
-
-                        sync(&client);
+            // the client is kept between syncs to receive the notifications of its subscriptions
+            let mut client: Option<Box<dyn Backend>> = None;
 
-                        let tip_after_sync = match update_tip(&client, true) {
-                            Ok(height) => height,
-                            Err(_) => {
-                                continue;
+            loop {
+                if client.is_none() {
+                    match url.build_client(proxy.as_deref(), None) {
+                        Ok(new_client) => {
+                            syncer.subscribed.lock().unwrap().clear();
+                            client = Some(new_client);
+                        }
+                        Err(err) => {
+                            state_updater.update_if_needed(false);
+                            warn!("Can't build client {:?}", err);
+                            if wait_or_close(&user_wants_to_sync, sync_interval) {
+                                info!("closing syncer & tipper thread");
+                                break;
                             }
-                        };
-
-                        let should_resync = match (tip_before_sync, tip_after_sync) {
-                            (None, Some(_)) => true,
-                            (Some(before), Some(after)) if before != after => true,
-                            _ => false,
-                        };
-
-                        if should_resync {
-                            // If a block arrives while we are syncing
-                            // transactions, transactions might be returned as
-                            // unconfirmed even if they belong to the newly
-                            // notified block. Sync again to ensure
-                            // consistency.
                             continue;
                         }
                     }
+                }
+                let backend = client.as_deref().expect("built above");
 
-                    Err(err) => {
-                        state_updater.update_if_needed(false);
-                        warn!("Can't build client {:?}", err);
+                let tip_before_sync = match update_tip(backend, false) {
+                    Ok(height) => height,
+                    Err(_) => {
+                        client = None;
+                        continue;
+                    }
+                };
+
+                let synced = sync(backend);
+
+                let tip_after_sync = match update_tip(backend, true) {
+                    Ok(height) => height,
+                    Err(_) => {
+                        client = None;
+                        continue;
                     }
                 };
 
-                if wait_or_close(&user_wants_to_sync, sync_interval) {
+                let should_resync = match (tip_before_sync, tip_after_sync) {
+                    (None, Some(_)) => true,
+                    (Some(before), Some(after)) if before != after => true,
+                    _ => false,
+                };
+
+                if should_resync {
+                    // If a block arrives while we are syncing
+                    // transactions, transactions might be returned as
+                    // unconfirmed even if they belong to the newly
+                    // notified block. Sync again to ensure
+                    // consistency.
+                    continue;
+                }
+
+                let close = if synced {
+                    match syncer.wait_notifications(backend, &user_wants_to_sync, sync_interval) {
+                        Ok(close) => close,
+                        Err(e) => {
+                            warn!("error waiting for notifications {:?}", e);
+                            client = None;
+                            false
+                        }
+                    }
+                } else {
+                    // subscriptions could be lost, build a new client
+                    client = None;
+                    wait_or_close(&user_wants_to_sync, sync_interval)
+                };
+                if close {
                     info!("closing syncer & tipper thread");
                     break;
                 }
@@ -726,9 +915,16 @@ impl ElectrumSession {
 
     pub fn get_receive_address(&self, opt: &GetAddressOpt) -> Result<AddressPointer, Error> {
         debug!("get_receive_address {:?}", opt);
-        let address =
-            self.get_account(opt.subaccount)?.get_next_address(opt.is_internal.unwrap_or(false))?;
+        let is_internal = opt.is_internal.unwrap_or(false);
+        let account = self.get_account(opt.subaccount)?;
+        let address = account.get_next_address(is_internal)?;
         debug!("get_address {:?}", address);
+        let gap_limit = account.gap_limit()?;
+        if account.unused_addresses(is_internal)? > gap_limit {
+            // funds received beyond the gap limit are not found by sync and restore
+            warn!("subaccount {} handed out more addresses than its gap limit", opt.subaccount);
+            self.notify.gap_limit_exceeded(opt.subaccount, gap_limit);
+        }
         Ok(address)
     }
 
@@ -785,6 +981,13 @@ impl ElectrumSession {
         self.get_account(account_num)?.info()
     }
 
+    pub fn get_subaccount_descriptors(
+        &self,
+        opt: &GetSubaccountDescriptorsOpt,
+    ) -> Result<SubaccountDescriptors, Error> {
+        self.get_account(opt.subaccount)?.descriptors()
+    }
+
     pub fn get_subaccount_root_path(
         &mut self,
         opt: GetAccountPathOpt,
@@ -797,6 +1000,9 @@ impl ElectrumSession {
 
     pub fn create_subaccount(&mut self, opt: CreateAccountOpt) -> Result<AccountInfo, Error> {
         let master_xprv = self.master_xprv.clone();
+        // known also with external signers
+        let (_, path) = get_account_derivation(opt.subaccount, self.network.id())?;
+        let key_origin = self.master_xpub.map(|xpub| (xpub.fingerprint(), path));
         let store = self.store()?.clone();
         let master_blinding = store.read()?.cache.master_blinding.clone();
         let network = self.network.clone();
@@ -827,6 +1033,7 @@ impl ElectrumSession {
                     network,
                     &master_xprv,
                     &opt.xpub, // account xpub
+                    key_origin,
                     master_blinding,
                     store,
                     opt.subaccount,
@@ -842,7 +1049,11 @@ impl ElectrumSession {
     }
 
     pub fn discover_subaccount(&self, opt: DiscoverAccountOpt) -> Result<bool, Error> {
-        discover_account(&self.url, self.proxy.as_deref(), &opt.xpub, opt.script_type)
+        let gap_limit = opt.gap_limit.unwrap_or(DEFAULT_GAP_LIMIT);
+        if !(1..=MAX_GAP_LIMIT).contains(&gap_limit) {
+            return Err(Error::InvalidGapLimit(MAX_GAP_LIMIT));
+        }
+        discover_account(&self.url, self.proxy.as_deref(), &opt.xpub, opt.script_type, gap_limit)
     }
 
     pub fn get_next_subaccount(&self, opt: GetNextAccountOpt) -> Result<u32, Error> {
@@ -861,7 +1072,7 @@ impl ElectrumSession {
         self.get_account(opt.subaccount)?.set_settings(UpdateAccountOpt {
             subaccount: opt.subaccount,
             name: Some(opt.new_name),
-            hidden: None,
+            ..Default::default()
         })
     }
 
@@ -869,7 +1080,7 @@ impl ElectrumSession {
         self.get_account(opt.subaccount)?.set_settings(UpdateAccountOpt {
             subaccount: opt.subaccount,
             hidden: Some(opt.hidden),
-            name: None,
+            ..Default::default()
         })
     }
 
@@ -932,6 +1143,24 @@ impl ElectrumSession {
         Ok(())
     }
 
+    pub fn export_labels(&self) -> Result<ExportLabelsResult, Error> {
+        let labels = self.store()?.read()?.export_labels();
+        Ok(ExportLabelsResult {
+            labels: Bip329Label::to_jsonl(&labels)?,
+        })
+    }
+
+    pub fn import_labels(&self, opt: &ImportLabelsOpt) -> Result<ImportLabelsResult, Error> {
+        let labels = Bip329Label::from_jsonl(&opt.labels)?;
+        if labels.iter().any(|l| l.label.as_ref().map_or(false, |l| l.len() > 1024)) {
+            return Err(Error::Generic("Too long label (max 1024)".into()));
+        }
+        let imported = self.store()?.write()?.import_labels(&labels)?;
+        Ok(ImportLabelsResult {
+            imported,
+        })
+    }
+
     fn remove_recent_spent_utxos(&self, tx_req: &mut CreateTransaction) -> Result<(), Error> {
         let id = self.network.id();
         let recent_spent_utxos = self.recent_spent_utxos.read()?;
@@ -949,10 +1178,257 @@ impl ElectrumSession {
     ) -> Result<TransactionMeta, Error> {
         info!("electrum create_transaction {:?}", tx_req);
 
+        if !tx_req.private_keys.is_empty() {
+            let utxos = self.sweep_utxos(tx_req)?;
+            return self.get_account(tx_req.subaccount)?.create_sweep_tx(tx_req, utxos);
+        }
         self.remove_recent_spent_utxos(tx_req)?;
         self.get_account(tx_req.subaccount)?.create_tx(tx_req)
     }
 
+    /// The utxos of the private keys to sweep, looking up every script type they may be used with
+    fn sweep_utxos(&self, tx_req: &CreateTransaction) -> Result<Vec<UnspentOutput>, Error> {
+        let network = self
+            .network
+            .id()
+            .get_bitcoin_network()
+            .ok_or_else(|| Error::InvalidSweepRequest("supported only on bitcoin".into()))?;
+        let mut scripts = vec![];
+        for key in tx_req.private_keys.iter() {
+            let key = sweep::decode_private_key(key, tx_req.passphrase.as_deref(), network)?;
+            for (script_type, script_pubkey) in sweep::sweep_scripts(&key) {
+                scripts.push((key, script_type, script_pubkey));
+            }
+        }
+        let client = self.url.build_client(self.proxy.as_deref(), None)?;
+        let script_pubkeys: Vec<_> = scripts.iter().map(|(_, _, s)| s.clone()).collect();
+        let unspents = client.batch_script_list_unspent(&script_pubkeys)?;
+        let mut utxos = vec![];
+        for ((key, script_type, script_pubkey), unspents) in scripts.iter().zip(unspents.iter()) {
+            for unspent in unspents {
+                utxos.push(sweep::sweep_utxo(
+                    key,
+                    *script_type,
+                    script_pubkey,
+                    unspent,
+                    tx_req.subaccount,
+                ));
+            }
+        }
+        info!("found {} utxos to sweep", utxos.len());
+        Ok(utxos)
+    }
+
+    /// Create a transaction issuing a new asset and, optionally, its reissuance tokens
+    pub fn create_issuance(
+        &mut self,
+        opt: &CreateIssuanceOpt,
+    ) -> Result<IssuanceTransaction, Error> {
+        let mut tx_req = self.liquid_tx_request(opt.subaccount, opt.fee_rate, &opt.utxos)?;
+        tx_req.issuance = Some(AssetIssuanceRequest {
+            asset_amount: opt.asset_amount,
+            token_amount: opt.token_amount,
+            asset_address: opt.asset_address.clone(),
+            token_address: opt.token_address.clone(),
+            contract: opt.contract.clone(),
+            reissue_asset_id: None,
+            confidential: opt.confidential,
+        });
+        issuance_transaction(self.create_transaction(&mut tx_req)?)
+    }
+
+    /// Create a transaction reissuing an asset, spending one of its reissuance tokens
+    pub fn create_reissuance(
+        &mut self,
+        opt: &CreateReissuanceOpt,
+    ) -> Result<IssuanceTransaction, Error> {
+        let mut tx_req = self.liquid_tx_request(opt.subaccount, opt.fee_rate, &opt.utxos)?;
+        tx_req.issuance = Some(AssetIssuanceRequest {
+            asset_amount: opt.asset_amount,
+            asset_address: opt.asset_address.clone(),
+            reissue_asset_id: Some(opt.asset_id.clone()),
+            confidential: opt.confidential,
+            ..Default::default()
+        });
+        issuance_transaction(self.create_transaction(&mut tx_req)?)
+    }
+
+    /// Create a transaction destroying an amount of an asset
+    pub fn create_burn(&mut self, opt: &CreateBurnOpt) -> Result<TransactionMeta, Error> {
+        let mut tx_req = self.liquid_tx_request(opt.subaccount, opt.fee_rate, &opt.utxos)?;
+        tx_req.burns = vec![BurnAmount {
+            asset_id: opt.asset_id.clone(),
+            satoshi: opt.satoshi,
+        }];
+        self.create_transaction(&mut tx_req)
+    }
+
+    pub fn get_pegin_address(&self, opt: &GetPeginAddressOpt) -> Result<PeginAddress, Error> {
+        self.get_account(opt.subaccount)?.get_pegin_address()
+    }
+
+    /// Create a signed transaction claiming bitcoins pegged in to an address of the subaccount
+    pub fn create_pegin_claim(&self, opt: &CreatePeginClaimOpt) -> Result<TransactionMeta, Error> {
+        self.get_account(opt.subaccount)?.create_pegin_claim(opt)
+    }
+
+    /// Create a PSET offering a utxo in exchange of an amount of another asset
+    pub fn create_swap_proposal(
+        &self,
+        opt: &CreateSwapProposalOpt,
+    ) -> Result<SwapTransaction, Error> {
+        self.get_account(opt.subaccount)?.create_swap_proposal(opt)
+    }
+
+    /// Complete a swap proposal made by a counterparty, paying with the subaccount utxos
+    pub fn complete_swap(&self, opt: &CompleteSwapOpt) -> Result<SwapTransaction, Error> {
+        if !self.network.liquid {
+            return Err(Error::InvalidSwap("supported only on liquid".into()));
+        }
+        let utxos = self.liquid_tx_request(opt.subaccount, opt.fee_rate, &opt.utxos)?.utxos;
+        self.get_account(opt.subaccount)?.complete_swap(opt, &utxos)
+    }
+
+    /// The unblinded values of the outputs of the transaction `opt.txid` of the wallet, plus these
+    /// of other outputs unblinded with the data in `opt.blinded`, which is verified
+    pub fn get_unblinded_data(&self, opt: &GetUnblindedDataOpt) -> Result<UnblindedData, Error> {
+        if !self.network.liquid {
+            return Err(Error::InvalidBlindingData("supported only on liquid".into()));
+        }
+        let txid = elements::Txid::from_hex(&opt.txid)?;
+        let mut tx = None;
+        let mut outputs = HashMap::new();
+        {
+            let store = self.store()?;
+            let store_read = store.read()?;
+            for account_num in self.get_subaccount_nums()? {
+                let acc_store = store_read.account_cache(account_num)?;
+                let account_tx = match acc_store.get_liquid_tx(&txid) {
+                    Ok(account_tx) => account_tx,
+                    Err(_) => continue,
+                };
+                for vout in 0..account_tx.output.len() as u32 {
+                    let outpoint = elements::OutPoint::new(txid, vout);
+                    if let Some(secrets) = acc_store.unblinded.get(&outpoint) {
+                        outputs.insert(vout, (*secrets, true));
+                    }
+                }
+                tx = Some(account_tx);
+            }
+        }
+        let tx = tx.ok_or_else(|| Error::TxNotFound(BETxid::Elements(txid)))?;
+
+        if let Some(blinded) = opt.blinded.as_ref() {
+            for secrets in unblinded::parse_blinded_url_fragment(blinded)? {
+                let vout = tx
+                    .output
+                    .iter()
+                    .position(|o| unblinded::secrets_match(o, &secrets))
+                    .ok_or_else(|| {
+                        Error::InvalidBlindingData(format!(
+                            "no output of {} with {} of {}",
+                            txid, secrets.value, secrets.asset
+                        ))
+                    })?;
+                outputs.entry(vout as u32).or_insert((secrets, false));
+            }
+        }
+
+        let mut outputs: Vec<_> = outputs.into_iter().collect();
+        outputs.sort_by_key(|(vout, _)| *vout);
+        let secrets: Vec<_> = outputs.iter().map(|(_, (secrets, _))| *secrets).collect();
+        Ok(UnblindedData {
+            txid: txid.to_string(),
+            outputs: outputs
+                .into_iter()
+                .map(|(vout, (secrets, is_relevant))| UnblindedOutput {
+                    vout,
+                    asset_id: secrets.asset.to_string(),
+                    satoshi: secrets.value,
+                    asset_blinder: secrets.asset_bf.to_hex(),
+                    amount_blinder: secrets.value_bf.to_hex(),
+                    is_relevant,
+                })
+                .collect(),
+            url_fragment: unblinded::blinded_url_fragment(&secrets),
+        })
+    }
+
+    /// A transaction request spending `utxos`, or all the subaccount utxos if empty
+    fn liquid_tx_request(
+        &self,
+        subaccount: u32,
+        fee_rate: Option<u64>,
+        utxos: &CreateTxUtxos,
+    ) -> Result<CreateTransaction, Error> {
+        if !self.network.liquid {
+            return Err(Error::InvalidIssuanceRequest("supported only on liquid".into()));
+        }
+        let utxos = if utxos.is_empty() {
+            let unspent = self.get_unspent_outputs(&GetUnspentOpt {
+                subaccount,
+                ..Default::default()
+            })?;
+            unspent
+                .0
+                .into_iter()
+                .map(|(asset_id, asset_utxos)| {
+                    let asset_utxos = asset_utxos
+                        .into_iter()
+                        .map(|u| CreateTxUtxo {
+                            txid: u.txhash,
+                            vout: u.pt_idx,
+                        })
+                        .collect();
+                    (asset_id, asset_utxos)
+                })
+                .collect()
+        } else {
+            utxos.clone()
+        };
+        Ok(CreateTransaction {
+            subaccount,
+            fee_rate,
+            utxos,
+            ..Default::default()
+        })
+    }
+
+    /// Sign a message with the key of an address of any subaccount
+    pub fn sign_message(&self, opt: &SignMessageOpt) -> Result<SignMessageResult, Error> {
+        let address = opt.address.parse::<bitcoin::Address>().map_err(|_| Error::InvalidAddress)?;
+        let script_pubkey = BEScript::Bitcoin(address.script_pubkey());
+        for account_num in self.get_subaccount_nums()? {
+            let account = self.get_account(account_num)?;
+            if account.get_wallet_chain_type(&script_pubkey).is_some() {
+                return account.sign_message(opt);
+            }
+        }
+        Err(Error::ScriptPubkeyNotFound)
+    }
+
+    /// Create a Bitcoin PSBT from the output of `create_transaction`
+    pub fn create_psbt(&self, tx: &TransactionMeta) -> Result<CreatePsbtResult, Error> {
+        let account_num = tx
+            .create_transaction
+            .as_ref()
+            .ok_or_else(|| Error::Generic("Cannot create a psbt without tx data".into()))?
+            .subaccount;
+        let psbt = self.get_account(account_num)?.create_psbt(tx)?;
+        Ok(CreatePsbtResult {
+            psbt: psbt::psbt_to_base64(&psbt),
+        })
+    }
+
+    pub fn sign_psbt(&self, params: &SignPsbtParams) -> Result<SignPsbtResult, Error> {
+        let mut psbt = psbt::psbt_from_base64(&params.psbt)?;
+        let signed_inputs = self.get_account(params.subaccount)?.sign_psbt(&mut psbt)?;
+        Ok(SignPsbtResult {
+            psbt: psbt::psbt_to_base64(&psbt),
+            signed_inputs,
+        })
+    }
+
     pub fn psbt_get_details(
         &mut self,
         params: PsbtGetDetailsParams,
@@ -1041,7 +1517,18 @@ impl ElectrumSession {
             .as_ref()
             .ok_or_else(|| Error::Generic("Cannot sign without tx data".into()))?
             .subaccount;
-        self.get_account(account_num)?.sign(create_tx)
+        let account = self.get_account(account_num)?;
+        if create_tx.is_sweep {
+            return account.sign_sweep(create_tx);
+        }
+        match self.signer {
+            Some(ref signer) => account.sign_with_signer(create_tx, signer.as_ref()),
+            None => account.sign(create_tx),
+        }
+    }
+
+    pub fn set_signer(&mut self, signer: Arc<dyn Signer>) {
+        self.signer = Some(signer);
     }
 
     fn set_recent_spent_utxos(&self, tx: &BETransaction) -> Result<(), Error> {
@@ -1088,7 +1575,7 @@ impl ElectrumSession {
             NetworkId::Elements(_) => 100,
         };
         let fee_estimates =
-            try_get_fee_estimates(&self.url.build_client(self.proxy.as_deref(), None)?)
+            try_get_fee_estimates(self.url.build_client(self.proxy.as_deref(), None)?.as_ref())
                 .unwrap_or_else(|_| vec![FeeEstimate(min_fee); 25]);
         self.store()?.write()?.cache.fee_estimates = fee_estimates.clone();
         Ok(fee_estimates)
@@ -1119,6 +1606,7 @@ impl ElectrumSession {
         let height = self.store()?.read()?.cache.tip_height();
         let num_confs = opt.num_confs.unwrap_or(0);
         let confidential_utxos_only = opt.confidential_utxos_only.unwrap_or(false);
+        let all_coins = opt.all_coins.unwrap_or(false);
         for outpoint in account.unspents()? {
             let utxo = account.txo(&outpoint)?;
             let confirmations = match utxo.height {
@@ -1128,15 +1616,42 @@ impl ElectrumSession {
             if num_confs > confirmations || (confidential_utxos_only && !utxo.is_confidential()) {
                 continue;
             }
+            let store = self.store()?;
+            let store_read = store.read()?;
+            let frozen = store_read.is_utxo_frozen(&outpoint);
+            if frozen && !all_coins {
+                continue;
+            }
+            let label = store_read.get_output_label(&outpoint).cloned().unwrap_or_default();
+            drop(store_read);
             let asset_id = match &utxo.txoutsecrets {
                 None => "btc".to_string(),
                 Some(s) => s.asset.to_hex(),
             };
-            (*unspent_outputs.entry(asset_id).or_insert(vec![])).push(utxo.try_into()?);
+            let mut unspent_output: UnspentOutput = utxo.try_into()?;
+            if frozen {
+                unspent_output.user_status = UtxoStatus::Frozen;
+            }
+            unspent_output.label = label;
+            (*unspent_outputs.entry(asset_id).or_insert(vec![])).push(unspent_output);
         }
         Ok(GetUnspentOutputs(unspent_outputs))
     }
 
+    pub fn set_unspent_outputs_status(
+        &self,
+        opt: &SetUnspentOutputsStatusOpt,
+    ) -> Result<(), Error> {
+        let id = self.network.id();
+        let store = self.store()?;
+        let mut store_write = store.write()?;
+        for utxo in opt.list.iter() {
+            let outpoint = BEOutPoint::new(BETxid::from_hex(&utxo.txhash, id)?, utxo.pt_idx);
+            store_write.set_utxo_frozen(&outpoint, utxo.user_status == UtxoStatus::Frozen)?;
+        }
+        Ok(())
+    }
+
     pub fn export_cache(&mut self) -> Result<RawCache, Error> {
         self.store()?.write()?.export_cache()
     }
@@ -1181,10 +1696,10 @@ pub fn keys_from_credentials(
 impl Tipper {
     pub fn tip(
         &self,
-        client: &Client,
+        client: &dyn Backend,
         update_cache: bool,
     ) -> Result<Option<(u32, BEBlockHeader)>, Error> {
-        let header = client.block_headers_subscribe_raw()?;
+        let header = client.tip()?;
         let new_height = header.height as u32;
         let new_header = BEBlockHeader::deserialize(&header.header, self.network.id())?;
         if !update_cache {
@@ -1207,20 +1722,44 @@ impl Tipper {
 }
 
 impl Headers {
-    pub fn ask(&mut self, chunk_size: usize, client: &Client) -> Result<usize, Error> {
-        if let ChainOrVerifier::Chain(chain) = &mut self.checker {
-            info!("asking headers, current height:{} chunk_size:{} ", chain.height(), chunk_size);
-            let headers = client.block_headers(chain.height() as usize + 1, chunk_size)?.headers;
-            let len = headers.len();
-            chain.push(headers)?;
-            Ok(len)
-        } else {
-            // Liquid doesn't need to download the header's chain
-            Ok(0)
+    pub fn ask(&mut self, chunk_size: usize, client: &dyn Backend) -> Result<usize, Error> {
+        match &mut self.checker {
+            ChainOrVerifier::Chain(chain) => {
+                info!(
+                    "asking headers, current height:{} chunk_size:{} ",
+                    chain.height(),
+                    chunk_size
+                );
+                let headers = client.block_headers(chain.height() as usize + 1, chunk_size)?;
+                let len = headers.len();
+                chain.push(headers)?;
+                Ok(len)
+            }
+            ChainOrVerifier::LiquidChain(chain) => {
+                // liquid headers have a variable size, they are asked by height
+                let tip_height = client.tip()?.height as u32;
+                let first = match chain.height() {
+                    Some(height) => height + 1,
+                    None => chain.start_for(tip_height),
+                };
+                info!(
+                    "asking liquid headers, from height:{} tip:{} chunk_size:{}",
+                    first, tip_height, chunk_size
+                );
+                let heights: Vec<u32> = (first..=tip_height).take(chunk_size).collect();
+                let headers = client
+                    .batch_block_header_raw(&heights)?
+                    .iter()
+                    .map(|raw| elements::encode::deserialize(raw))
+                    .collect::<Result<Vec<elements::BlockHeader>, _>>()?;
+                let len = headers.len();
+                chain.push(headers)?;
+                Ok(len)
+            }
         }
     }
 
-    pub fn get_proofs(&mut self, client: &Client) -> Result<usize, Error> {
+    pub fn get_proofs(&mut self, client: &dyn Backend) -> Result<usize, Error> {
         let mut proofs_done = 0;
         let account_nums = self.store.read()?.account_nums();
 
@@ -1237,11 +1776,19 @@ impl Headers {
                 .map(|(t, _)| t.clone())
                 .collect();
 
-            // find confirmed transactions with no SPV validation cache
+            // find confirmed transactions with no SPV validation cache, in a block already
+            // downloaded by the headers chain, the bitcoin chain has no headers before its
+            // checkpoint to verify them
+            let tip_height = self.checker.height();
+            let first_height = match &self.checker {
+                ChainOrVerifier::Chain(chain) => chain.start().height,
+                ChainOrVerifier::LiquidChain(_) => 0,
+            };
             let needs_proof: Vec<(BETxid, u32)> = acc_store
                 .heights
                 .iter()
                 .filter_map(|(t, h_opt)| Some((t, (*h_opt)?)))
+                .filter(|(_, h)| matches!(tip_height, Some(tip) if first_height <= *h && *h <= tip))
                 .filter(|(t, _)| store_read.cache.txs_verif.get(*t).is_none())
                 .map(|(t, h)| (t.clone(), h))
                 .collect();
@@ -1257,11 +1804,17 @@ impl Headers {
                         ChainOrVerifier::Chain(chain) => chain
                             .verify_tx_proof(txid.ref_bitcoin().unwrap(), height, proof)
                             .is_ok(),
-                        ChainOrVerifier::Verifier(verifier) => {
+                        ChainOrVerifier::LiquidChain(chain) if matches!(chain.start(), Some(start) if height >= start) => {
+                            chain
+                                .verify_tx_proof(txid.ref_elements().unwrap(), height, proof)
+                                .is_ok()
+                        }
+                        ChainOrVerifier::LiquidChain(chain) => {
+                            // before the start of the chain, only liquid v1 headers are verified
                             if let Some(BEBlockHeader::Elements(header)) =
                                 self.store.read()?.cache.headers.get(&height)
                             {
-                                verifier
+                                Verifier::new(chain.network)
                                     .verify_tx_proof(txid.ref_elements().unwrap(), proof, &header)
                                     .is_ok()
                             } else {
@@ -1296,9 +1849,68 @@ impl Headers {
         Ok(proofs_done)
     }
 
+    /// The height of the last header of the chain also in the best chain of the server, looking
+    /// back at most `max_depth` headers, `None` if the fork is deeper
+    pub fn fork_height(&self, client: &dyn Backend, max_depth: u32) -> Result<Option<u32>, Error> {
+        let (start, height) = match (self.checker.start(), self.checker.height()) {
+            (Some(start), Some(height)) => (start, height),
+            _ => return Ok(None),
+        };
+        let tip_height = client.tip()?.height as u32;
+        let lowest = height.saturating_sub(max_depth).max(start);
+        let heights: Vec<u32> = (lowest..=height.min(tip_height)).rev().collect();
+        let headers = client.batch_block_header_raw(&heights)?;
+        for (height, header) in heights.into_iter().zip(headers) {
+            let header = BEBlockHeader::deserialize(&header, self.checker.network())?;
+            if header.block_hash() == self.checker.block_hash(height)? {
+                return Ok(Some(height));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Remove the headers after `fork_height` and mark the transactions confirmed after it as
+    /// unconfirmed, until the next sync, and to be verified again
+    pub fn reorg(&mut self, fork_height: u32) -> Result<ReorgNotification, Error> {
+        if let Some(height) = self.checker.height() {
+            info!("reorg from height {} to {}", height, fork_height);
+            self.remove(height.saturating_sub(fork_height))?;
+        }
+        let mut store_write = self.store.write()?;
+        store_write.cache.headers.retain(|height, _| *height <= fork_height);
+        let mut txids = HashSet::new();
+        let mut subaccounts = vec![];
+        for account_num in store_write.account_nums() {
+            let acc_store = store_write.account_cache_mut(account_num)?;
+            let reorged: Vec<BETxid> = acc_store
+                .heights
+                .iter()
+                .filter(|(_, height)| matches!(height, Some(height) if *height > fork_height))
+                .map(|(txid, _)| *txid)
+                .collect();
+            if !reorged.is_empty() {
+                subaccounts.push(account_num);
+            }
+            for txid in reorged {
+                acc_store.heights.insert(txid, None);
+                txids.insert(txid);
+            }
+        }
+        for txid in txids.iter() {
+            store_write.cache.txs_verif.remove(txid);
+        }
+        subaccounts.sort_unstable();
+        Ok(ReorgNotification {
+            fork_height,
+            txids: txids.into_iter().map(|txid| txid.into_bitcoin()).collect(),
+            subaccounts,
+        })
+    }
+
     pub fn remove(&mut self, headers: u32) -> Result<(), Error> {
-        if let ChainOrVerifier::Chain(chain) = &mut self.checker {
-            chain.remove(headers)?;
+        match &mut self.checker {
+            ChainOrVerifier::Chain(chain) => chain.remove(headers)?,
+            ChainOrVerifier::LiquidChain(chain) => chain.remove(headers)?,
         }
         Ok(())
     }
@@ -1335,83 +1947,166 @@ struct DownloadTxResult {
 
 impl Syncer {
     /// Sync the wallet, return the set of updated accounts
-    pub fn sync(&self, client: &Client) -> Result<Vec<TransactionNotification>, Error> {
+    pub fn sync(&self, client: &dyn Backend) -> Result<Vec<TransactionNotification>, Error> {
         trace!("start sync");
         let start = Instant::now();
 
         let accounts = self.accounts.read().unwrap();
         let mut updated_txs: HashMap<BETxid, TransactionNotification> = HashMap::new();
+        let mut any_changed = false;
+
+        client.begin_sync()?;
+        if !self.network.liquid {
+            // watch the scripts up to the gap limit after the last used or given address
+            let mut descriptors = vec![];
+            for account in accounts.values() {
+                let gap_limit = account.gap_limit()?;
+                let (external, internal) = {
+                    let store_read = self.store.read()?;
+                    let acc_store = store_read.account_cache(account.num())?;
+                    let (indexes, last_used) = (&acc_store.indexes, &acc_store.last_used);
+                    (
+                        indexes.external.max(last_used.external),
+                        indexes.internal.max(last_used.internal),
+                    )
+                };
+                descriptors.push((account.descriptor(false)?, external + gap_limit));
+                descriptors.push((account.descriptor(true)?, internal + gap_limit));
+            }
+            client.import_descriptors(&descriptors)?;
+        }
 
         for account in accounts.values() {
             let mut history_txs_id = HashSet::<BETxid>::new();
             let mut heights_set = HashSet::new();
             let mut txid_height = HashMap::<BETxid, _>::new();
             let mut scripts = HashMap::new();
+            // new histories of the scripts whose status changed, None if without history now
+            let mut updated_histories = HashMap::<BEScript, Option<ScriptHistory>>::new();
 
             let mut last_used = Indexes::default();
+            let gap_limit = account.gap_limit()?;
+            let net = self.network.id();
             let mut wallet_chains = vec![0, 1];
             wallet_chains.shuffle(&mut thread_rng());
             for i in wallet_chains {
                 let is_internal = i == 1;
                 let mut batch_count = 0;
                 loop {
-                    let batch = account.get_script_batch(is_internal, batch_count)?;
+                    let batch = account.get_script_batch(is_internal, batch_count, gap_limit)?;
+                    let be_scripts = batch.value.iter().map(|e| e.0.clone()).collect::<Vec<_>>();
                     // convert the BEScript into bitcoin::Script for electrum-client
                     let b_scripts =
-                        batch.value.iter().map(|e| e.0.clone().into_bitcoin()).collect::<Vec<_>>();
-                    let result: Vec<Vec<GetHistoryRes>> =
-                        client.batch_script_get_history(b_scripts.iter())?;
+                        be_scripts.iter().map(|s| s.clone().into_bitcoin()).collect::<Vec<_>>();
+                    let statuses = self.script_statuses(&b_scripts, client)?;
                     if !batch.cached {
                         scripts.extend(batch.value);
                     }
-                    let max = result
+                    let max = statuses
                         .iter()
                         .enumerate()
-                        .filter(|(_, v)| !v.is_empty())
+                        .filter(|(_, s)| s.status.is_some())
                         .map(|(i, _)| i as u32)
                         .max();
                     if let Some(max) = max {
                         if is_internal {
-                            last_used.internal = max + batch_count * BATCH_SIZE;
+                            last_used.internal = max + batch_count * gap_limit;
                         } else {
-                            last_used.external = max + batch_count * BATCH_SIZE;
+                            last_used.external = max + batch_count * gap_limit;
                         }
                     };
+                    trace!("{}/batch({}) {:?}", i, batch_count, max);
 
-                    let flattened: Vec<GetHistoryRes> = result.into_iter().flatten().collect();
-                    trace!("{}/batch({}) {:?}", i, batch_count, flattened.len());
-
-                    if flattened.is_empty() {
+                    if max.is_none() {
                         break;
                     }
 
-                    let net = self.network.id();
-
-                    for el in flattened {
-                        // el.height = -1 means unconfirmed with unconfirmed parents
-                        // el.height =  0 means unconfirmed with confirmed parents
-                        // but we threat those tx the same
-                        let height = el.height.max(0);
-                        heights_set.insert(height as u32);
-                        if height == 0 {
-                            txid_height.insert(el.tx_hash.into_net(net), None);
-                        } else {
-                            txid_height.insert(el.tx_hash.into_net(net), Some(height as u32));
-                        }
-
-                        history_txs_id.insert(el.tx_hash.into_net(net));
+                    // refetch only the histories of the scripts whose status changed, unless
+                    // already downloaded to compute the status
+                    let changed: Vec<_> = {
+                        let store_read = self.store.read()?;
+                        let acc_store = store_read.account_cache(account.num())?;
+                        be_scripts
+                            .into_iter()
+                            .zip(b_scripts)
+                            .zip(statuses)
+                            .filter(|((be_script, _), s)| {
+                                acc_store.histories.get(be_script).map(|h| h.status) != s.status
+                            })
+                            .collect()
+                    };
+                    let to_fetch: Vec<_> = changed
+                        .iter()
+                        .filter(|(_, s)| s.status.is_some() && s.history.is_none())
+                        .map(|((_, b_script), _)| b_script.clone())
+                        .collect();
+                    let result: Vec<Vec<GetHistoryRes>> = if to_fetch.is_empty() {
+                        vec![]
+                    } else {
+                        client.batch_script_get_history(&to_fetch)?
+                    };
+                    let mut result = result.into_iter();
+                    for ((be_script, _), s) in changed {
+                        let history = match s.status {
+                            Some(status) => {
+                                let history = match s.history {
+                                    Some(history) => history,
+                                    None => result.next().expect("one history per script"),
+                                };
+                                // el.height = -1 means unconfirmed with unconfirmed parents
+                                // el.height =  0 means unconfirmed with confirmed parents
+                                // but we threat those tx the same
+                                let txs = history
+                                    .iter()
+                                    .map(|el| {
+                                        let height =
+                                            Some(el.height).filter(|h| *h > 0).map(|h| h as u32);
+                                        (el.tx_hash.into_net(net), height)
+                                    })
+                                    .collect();
+                                Some(ScriptHistory {
+                                    status,
+                                    txs,
+                                })
+                            }
+                            None => None,
+                        };
+                        updated_histories.insert(be_script, history);
                     }
 
                     batch_count += 1;
                 }
             }
 
-            let new_txs = self.download_txs(account.num(), &history_txs_id, &scripts, &client)?;
-            let headers = self.download_headers(account.num(), &heights_set, &client)?;
+            {
+                let store_read = self.store.read()?;
+                let acc_store = store_read.account_cache(account.num())?;
+                let histories = acc_store
+                    .histories
+                    .iter()
+                    .filter(|(script, _)| !updated_histories.contains_key(script))
+                    .map(|(_, history)| history)
+                    .chain(updated_histories.values().flatten());
+                for history in histories {
+                    for (txid, height) in history.txs.iter() {
+                        heights_set.insert(height.unwrap_or(0));
+                        txid_height.insert(*txid, *height);
+                        history_txs_id.insert(*txid);
+                    }
+                }
+            }
+
+            let new_txs = self.download_txs(account.num(), &history_txs_id, &scripts, client)?;
+            let headers = self.download_headers(&heights_set, client)?;
 
             let store_read = self.store.read()?;
             let acc_store = store_read.account_cache(account.num())?;
-            let store_indexes = acc_store.indexes.clone();
+            // the addresses handed out after the last used one are not handed out again
+            let indexes = Indexes {
+                external: acc_store.indexes.external.max(last_used.external),
+                internal: acc_store.indexes.internal.max(last_used.internal),
+            };
+            let indexes_changed = acc_store.indexes != indexes || acc_store.last_used != last_used;
             let txs_heights_changed = txid_height
                 .iter()
                 .any(|(txid, height)| acc_store.heights.get(txid) != Some(height))
@@ -1421,9 +2116,10 @@ impl Syncer {
 
             let changed = if !new_txs.txs.is_empty()
                 || !headers.is_empty()
-                || store_indexes != last_used
+                || indexes_changed
                 || !scripts.is_empty()
                 || txs_heights_changed
+                || !updated_histories.is_empty()
             {
                 info!(
                     "There are changes in the store new_txs:{:?} headers:{:?} txid_height:{:?}",
@@ -1435,7 +2131,8 @@ impl Syncer {
                 store_write.cache.headers.extend(headers);
 
                 let mut acc_store = store_write.account_cache_mut(account.num())?;
-                acc_store.indexes = last_used;
+                acc_store.indexes = indexes;
+                acc_store.last_used = last_used;
                 acc_store
                     .all_txs
                     .extend(new_txs.txs.iter().cloned().map(|(txid, tx)| (txid, tx.into())));
@@ -1447,6 +2144,12 @@ impl Syncer {
                 acc_store.heights.extend(txid_height.into_iter());
                 acc_store.scripts.extend(scripts.clone().into_iter().map(|(a, b)| (b, a)));
                 acc_store.paths.extend(scripts.into_iter());
+                for (script, history) in updated_histories {
+                    match history {
+                        Some(history) => acc_store.histories.insert(script, history),
+                        None => acc_store.histories.remove(&script),
+                    };
+                }
 
                 for tx in new_txs.txs.iter() {
                     if let Some(ntf) = updated_txs.get_mut(&tx.0) {
@@ -1494,12 +2197,87 @@ impl Syncer {
                 changed,
                 start.elapsed().as_millis()
             );
+            any_changed |= changed;
         }
 
+        if any_changed {
+            // spent outputs can't be spent again, they don't need to stay frozen
+            self.store.write()?.prune_frozen_utxos()?;
+        }
         self.empty_recent_spent_utxos()?;
         Ok(updated_txs.into_values().collect())
     }
 
+    /// The current status hashes of `scripts`, subscribing to the ones not subscribed yet
+    fn script_statuses(
+        &self,
+        scripts: &[bitcoin::Script],
+        client: &dyn Backend,
+    ) -> Result<Vec<ScriptStatus>, Error> {
+        if !client.notifies() {
+            return client.batch_script_subscribe(scripts);
+        }
+        let mut subscribed = self.subscribed.lock()?;
+        let to_subscribe: Vec<_> =
+            scripts.iter().filter(|s| !subscribed.contains_key(*s)).cloned().collect();
+        if !to_subscribe.is_empty() {
+            let statuses = client.batch_script_subscribe(&to_subscribe)?;
+            subscribed.extend(to_subscribe.into_iter().zip(statuses.into_iter().map(|s| s.status)));
+        }
+        scripts
+            .iter()
+            .map(|script| {
+                let status = subscribed.get_mut(script).expect("subscribed above");
+                if let Some(new_status) = client.script_pop(script)? {
+                    *status = Some(new_status);
+                }
+                Ok(ScriptStatus {
+                    status: *status,
+                    history: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Update the statuses of the subscribed scripts with the notified ones, return whether
+    /// any status or the tip changed
+    fn pop_notifications(&self, client: &dyn Backend) -> Result<bool, Error> {
+        let mut changed = client.tip_pop()?.is_some();
+        for (script, status) in self.subscribed.lock()?.iter_mut() {
+            if let Some(new_status) = client.script_pop(script)? {
+                changed |= *status != Some(new_status);
+                *status = Some(new_status);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Wait `interval` seconds, or less if the server notifies a change, return true if the
+    /// thread should close
+    ///
+    /// The electrum client reads the notifications only while waiting for a response, thus the
+    /// server is pinged every second.
+    fn wait_notifications(
+        &self,
+        client: &dyn Backend,
+        user_wants_to_sync: &Arc<AtomicBool>,
+        interval: u32,
+    ) -> Result<bool, Error> {
+        if !client.notifies() {
+            return Ok(wait_or_close(user_wants_to_sync, interval));
+        }
+        for _ in 0..interval {
+            if wait_or_close(user_wants_to_sync, 1) {
+                return Ok(true);
+            }
+            client.ping()?;
+            if self.pop_notifications(client)? {
+                break;
+            }
+        }
+        Ok(false)
+    }
+
     fn empty_recent_spent_utxos(&self) -> Result<(), Error> {
         let mut recent_spent_utxos = self.recent_spent_utxos.write()?;
         *recent_spent_utxos = HashSet::new();
@@ -1527,22 +2305,20 @@ impl Syncer {
 
     fn download_headers(
         &self,
-        account_num: u32,
         heights_set: &HashSet<u32>,
-        client: &Client,
+        client: &dyn Backend,
     ) -> Result<Vec<(u32, BEBlockHeader)>, Error> {
+        // headers after a reorg are removed from the cache, to be downloaded again
         let heights_in_db: HashSet<u32> = {
             let store_read = self.store.read()?;
-            let acc_store = store_read.account_cache(account_num)?;
-            iter::once(0).chain(acc_store.heights.iter().filter_map(|(_, h)| *h)).collect()
+            iter::once(0).chain(store_read.cache.headers.keys().cloned()).collect()
         };
 
         let mut result = vec![];
         let heights_to_download: Vec<u32> =
             heights_set.difference(&heights_in_db).cloned().collect();
         if !heights_to_download.is_empty() {
-            let headers_bytes_downloaded =
-                client.batch_block_header_raw(heights_to_download.clone())?;
+            let headers_bytes_downloaded = client.batch_block_header_raw(&heights_to_download)?;
             let mut headers_downloaded: Vec<BEBlockHeader> = vec![];
             for vec in headers_bytes_downloaded {
                 headers_downloaded.push(BEBlockHeader::deserialize(&vec, self.network.id())?);
@@ -1563,7 +2339,7 @@ impl Syncer {
         account_num: u32,
         history_txs_id: &HashSet<BETxid>,
         scripts: &HashMap<BEScript, DerivationPath>,
-        client: &Client,
+        client: &dyn Backend,
     ) -> Result<DownloadTxResult, Error> {
         let mut txs = vec![];
         let mut unblinds = vec![];
@@ -1574,7 +2350,7 @@ impl Syncer {
         let txs_to_download: Vec<bitcoin::Txid> =
             history_txs_id.difference(&txs_in_db).map(BETxidConvert::into_bitcoin).collect();
         if !txs_to_download.is_empty() {
-            let txs_bytes_downloaded = client.batch_transaction_get_raw(txs_to_download.iter())?;
+            let txs_bytes_downloaded = client.batch_transaction_get_raw(&txs_to_download)?;
             let mut txs_downloaded: Vec<BETransaction> = vec![];
             for vec in txs_bytes_downloaded {
                 let tx = BETransaction::deserialize(&vec, self.network.id())?;
@@ -1628,8 +2404,7 @@ impl Syncer {
                 .collect();
 
             if !txs_to_download.is_empty() {
-                let txs_bytes_downloaded =
-                    client.batch_transaction_get_raw(txs_to_download.iter())?;
+                let txs_bytes_downloaded = client.batch_transaction_get_raw(&txs_to_download)?;
                 for vec in txs_bytes_downloaded {
                     let tx = BETransaction::deserialize(&vec, self.network.id())?;
                     txs.push((tx.txid(), tx));
@@ -1645,6 +2420,36 @@ impl Syncer {
     }
 }
 
+/// Add the ids of the issued asset to a transaction created with an issuance request
+fn issuance_transaction(transaction: TransactionMeta) -> Result<IssuanceTransaction, Error> {
+    let tx: elements::Transaction = encode::deserialize(&Vec::<u8>::from_hex(&transaction.hex)?)?;
+    let input = tx
+        .input
+        .iter()
+        .find(|i| i.has_issuance())
+        .ok_or_else(|| Error::Generic("missing issuance input".into()))?;
+    let (asset_id, token_id) = input.issuance_ids();
+    let is_reissuance =
+        input.asset_issuance.asset_blinding_nonce != elements::secp256k1_zkp::ZERO_TWEAK;
+    let has_contract = transaction
+        .create_transaction
+        .as_ref()
+        .and_then(|r| r.issuance.as_ref())
+        .map_or(false, |i| i.contract.is_some());
+    let contract_hash = if has_contract && !is_reissuance {
+        Some(elements::ContractHash::from_inner(input.asset_issuance.asset_entropy).to_string())
+    } else {
+        None
+    };
+    Ok(IssuanceTransaction {
+        asset_id: asset_id.to_string(),
+        token_id: (!input.asset_issuance.inflation_keys.is_null()).then(|| token_id.to_string()),
+        asset_entropy: issuance::issuance_entropy(input).to_string(),
+        contract_hash,
+        transaction,
+    })
+}
+
 fn unblind_output(
     output: elements::TxOut,
     master_blinding: &MasterBlindingKey,
@@ -1697,6 +2502,282 @@ impl ElectrumSession {
 #[cfg(test)]
 mod test {
     use super::*;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::{OutPoint, PackedLockTime, Transaction, TxIn, TxOut};
+    use electrum_client::{GetMerkleRes, ListUnspentRes, RawHeaderNotification};
+    use gdk_common::session::Session;
+    use std::str::FromStr;
+
+    /// A server without notifications answering with the given histories and transactions
+    #[derive(Default)]
+    struct MockBackend {
+        histories: HashMap<bitcoin::Script, Vec<(i32, bitcoin::Txid)>>,
+        txs: HashMap<bitcoin::Txid, Vec<u8>>,
+        /// The number of scripts of every history request
+        history_requests: Mutex<Vec<usize>>,
+    }
+
+    impl MockBackend {
+        /// Add `tx`, to the history of its first output script if confirmed at `height`
+        fn add_tx(&mut self, tx: Transaction, height: Option<i32>) -> bitcoin::Txid {
+            let txid = tx.txid();
+            if let Some(height) = height {
+                let script = tx.output[0].script_pubkey.clone();
+                self.histories.entry(script).or_default().push((height, txid));
+            }
+            self.txs.insert(txid, bitcoin::consensus::serialize(&tx));
+            txid
+        }
+    }
+
+    impl Backend for MockBackend {
+        fn ping(&self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn relay_fee(&self) -> Result<f64, Error> {
+            unimplemented!()
+        }
+
+        fn batch_estimate_fee(&self, _blocks: &[usize]) -> Result<Vec<f64>, Error> {
+            unimplemented!()
+        }
+
+        fn tip(&self) -> Result<RawHeaderNotification, Error> {
+            unimplemented!()
+        }
+
+        fn block_header_raw(&self, _height: usize) -> Result<Vec<u8>, Error> {
+            unimplemented!()
+        }
+
+        fn batch_block_header_raw(&self, heights: &[u32]) -> Result<Vec<Vec<u8>>, Error> {
+            let header = genesis_block(bitcoin::Network::Regtest).header;
+            Ok(heights.iter().map(|_| bitcoin::consensus::serialize(&header)).collect())
+        }
+
+        fn block_headers(
+            &self,
+            _start_height: usize,
+            _count: usize,
+        ) -> Result<Vec<bitcoin::BlockHeader>, Error> {
+            unimplemented!()
+        }
+
+        fn batch_script_get_history(
+            &self,
+            scripts: &[bitcoin::Script],
+        ) -> Result<Vec<Vec<GetHistoryRes>>, Error> {
+            self.history_requests.lock().unwrap().push(scripts.len());
+            let history = |script| {
+                let history = self.histories.get(script).map(Vec::as_slice).unwrap_or_default();
+                history
+                    .iter()
+                    .map(|(height, tx_hash)| GetHistoryRes {
+                        height: *height,
+                        tx_hash: *tx_hash,
+                        fee: None,
+                    })
+                    .collect()
+            };
+            Ok(scripts.iter().map(history).collect())
+        }
+
+        fn batch_script_list_unspent(
+            &self,
+            _scripts: &[bitcoin::Script],
+        ) -> Result<Vec<Vec<ListUnspentRes>>, Error> {
+            unimplemented!()
+        }
+
+        fn batch_transaction_get_raw(
+            &self,
+            txids: &[bitcoin::Txid],
+        ) -> Result<Vec<Vec<u8>>, Error> {
+            Ok(txids.iter().map(|txid| self.txs[txid].clone()).collect())
+        }
+
+        fn transaction_get_merkle(
+            &self,
+            _txid: &bitcoin::Txid,
+            _height: usize,
+        ) -> Result<GetMerkleRes, Error> {
+            Err(Error::Generic("no proof".into()))
+        }
+
+        fn transaction_broadcast_raw(&self, _raw_tx: &[u8]) -> Result<bitcoin::Txid, Error> {
+            unimplemented!()
+        }
+    }
+
+    fn tx(previous_output: OutPoint, script_pubkey: &bitcoin::Script) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: 100_000,
+                script_pubkey: script_pubkey.clone(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_sync_histories() {
+        let xpub = ExtendedPubKey::from_str("tpubD6NzVbkrYhZ4XYa9MoLt4BiMZ4gkt2faZ4BcmKu2a9te4LDpQmvEz2L2yDERivHxFPnxXXhqDRkUNnQCpZggCyEZLBktV7VaSmwayqMJy1s").unwrap();
+        let network = NetworkParameters::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = StoreMeta::new(dir.path(), &xpub, network.id()).unwrap();
+        let store = Arc::new(RwLock::new(store));
+        let account =
+            Account::new(network.clone(), &None, &Some(xpub), None, None, store.clone(), 0, false)
+                .unwrap();
+        account
+            .set_settings(UpdateAccountOpt {
+                gap_limit: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+        let script = account.get_script_batch(false, 0, 2).unwrap().value[0].0.clone();
+        for _ in 0..3 {
+            account.get_next_address(false).unwrap();
+        }
+        let syncer = Syncer {
+            accounts: Arc::new(RwLock::new(HashMap::from([(0, account)]))),
+            store: store.clone(),
+            master_blinding: None,
+            network,
+            recent_spent_utxos: Default::default(),
+            subscribed: Default::default(),
+        };
+
+        let mut client = MockBackend::default();
+        let b_script = script.clone().into_bitcoin();
+        let prev = client.add_tx(tx(OutPoint::null(), &bitcoin::Script::new()), None);
+        let txid = client.add_tx(tx(OutPoint::new(prev, 0), &b_script), Some(1));
+        let history = |store: &Store| {
+            let store = store.read().unwrap();
+            store.account_cache(0).unwrap().histories.get(&script).map(|h| h.txs.clone())
+        };
+
+        // the histories downloaded to compute the statuses are not downloaded again: one request
+        // for the used batch and one for the following one on the external chain, one on the
+        // internal chain
+        let ntfs = syncer.sync(&client).unwrap();
+        assert!(ntfs.iter().any(|ntf| ntf.txid == txid));
+        assert_eq!(*client.history_requests.lock().unwrap(), vec![2, 2, 2]);
+        assert_eq!(history(&store), Some(vec![(txid.into(), Some(1))]));
+        // the addresses handed out are not rewound to the last used one
+        assert_eq!(store.read().unwrap().account_cache(0).unwrap().indexes.external, 3);
+
+        // nothing changed
+        assert!(syncer.sync(&client).unwrap().is_empty());
+        assert_eq!(client.history_requests.lock().unwrap().len(), 6);
+
+        // a new transaction changes the status
+        let txid2 = client.add_tx(tx(OutPoint::new(txid, 0), &b_script), Some(2));
+        let ntfs = syncer.sync(&client).unwrap();
+        assert_eq!(ntfs.len(), 1);
+        assert_eq!(ntfs[0].txid, txid2);
+        assert_eq!(client.history_requests.lock().unwrap().len(), 9);
+        assert_eq!(history(&store), Some(vec![(txid.into(), Some(1)), (txid2.into(), Some(2))]));
+    }
+
+    #[test]
+    fn test_get_proofs_checkpoint() {
+        let xpub = ExtendedPubKey::from_str("tpubD6NzVbkrYhZ4XYa9MoLt4BiMZ4gkt2faZ4BcmKu2a9te4LDpQmvEz2L2yDERivHxFPnxXXhqDRkUNnQCpZggCyEZLBktV7VaSmwayqMJy1s").unwrap();
+        let network = NetworkParameters::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = StoreMeta::new(dir.path(), &xpub, network.id()).unwrap();
+        let store = Arc::new(RwLock::new(store));
+        Account::new(network, &None, &Some(xpub), None, None, store.clone(), 0, false).unwrap();
+        let header = genesis_block(bitcoin::Network::Regtest).header;
+        let checkpoint = Checkpoint {
+            height: DIFFCHANGE_INTERVAL,
+            header,
+            chainwork: header.work(),
+        };
+        let chain =
+            HeadersChain::with_checkpoint(dir.path(), bitcoin::Network::Regtest, checkpoint)
+                .unwrap();
+        let mut headers = Headers {
+            store: store.clone(),
+            checker: ChainOrVerifier::Chain(chain),
+            cross_validator: None,
+        };
+
+        let before = BETxid::Bitcoin(bitcoin::Txid::hash(b"before"));
+        let at = BETxid::Bitcoin(bitcoin::Txid::hash(b"at"));
+        {
+            let mut store = store.write().unwrap();
+            let acc_store = store.account_cache_mut(0).unwrap();
+            acc_store.heights.insert(before.clone(), Some(DIFFCHANGE_INTERVAL - 1));
+            acc_store.heights.insert(at.clone(), Some(DIFFCHANGE_INTERVAL));
+        }
+
+        // the transaction before the checkpoint is not checked, instead of failing to verify
+        assert_eq!(headers.get_proofs(&MockBackend::default()).unwrap(), 1);
+        let store = store.read().unwrap();
+        assert!(matches!(store.cache.txs_verif.get(&at), Some(SPVVerifyTxResult::NotVerified)));
+        assert!(store.cache.txs_verif.get(&before).is_none());
+    }
+
+    #[test]
+    fn test_unspent_outputs_frozen() {
+        let xpub = ExtendedPubKey::from_str("tpubD6NzVbkrYhZ4XYa9MoLt4BiMZ4gkt2faZ4BcmKu2a9te4LDpQmvEz2L2yDERivHxFPnxXXhqDRkUNnQCpZggCyEZLBktV7VaSmwayqMJy1s").unwrap();
+        let mut network = NetworkParameters::default();
+        network.electrum_url = Some("localhost:50001".into());
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = StoreMeta::new(dir.path(), &xpub, network.id()).unwrap();
+        let store = Arc::new(RwLock::new(store));
+        let account =
+            Account::new(network.clone(), &None, &Some(xpub), None, None, store.clone(), 0, false)
+                .unwrap();
+        let (script, path) = account.get_script_batch(false, 0, 1).unwrap().value.remove(0);
+        let funding = tx(OutPoint::null(), &script.clone().into_bitcoin());
+        let txid = BETxid::Bitcoin(funding.txid());
+        {
+            let mut store = store.write().unwrap();
+            let acc_store = store.account_cache_mut(0).unwrap();
+            acc_store.paths.insert(script, path);
+            acc_store.heights.insert(txid, Some(1));
+            acc_store.all_txs.insert(txid, BETransaction::Bitcoin(funding).into());
+        }
+        let mut session = ElectrumSession::new(network).unwrap();
+        session.store = Some(store);
+        session.accounts.write().unwrap().insert(0, account);
+
+        let set_status = |user_status| {
+            let list = vec![UnspentOutputStatus {
+                txhash: txid.to_hex(),
+                pt_idx: 0,
+                user_status,
+            }];
+            session.set_unspent_outputs_status(&SetUnspentOutputsStatusOpt {
+                list,
+            })
+        };
+        let unspents = |all_coins| {
+            let opt = GetUnspentOpt {
+                all_coins: Some(all_coins),
+                ..Default::default()
+            };
+            let utxos = session.get_unspent_outputs(&opt).unwrap().0;
+            utxos.values().flatten().map(|u| u.user_status).collect::<Vec<_>>()
+        };
+        assert_eq!(unspents(false), vec![UtxoStatus::Default]);
+
+        // frozen utxos are returned only with all_coins
+        set_status(UtxoStatus::Frozen).unwrap();
+        assert!(unspents(false).is_empty());
+        assert_eq!(unspents(true), vec![UtxoStatus::Frozen]);
+
+        set_status(UtxoStatus::Default).unwrap();
+        assert_eq!(unspents(false), vec![UtxoStatus::Default]);
+    }
 
     #[test]
     fn test_passphrase() {
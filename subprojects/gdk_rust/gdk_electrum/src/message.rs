@@ -0,0 +1,293 @@
+use std::str::FromStr;
+
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::ecdsa::{RecoverableSignature, RecoveryId, Signature};
+use bitcoin::secp256k1::{schnorr, Message, XOnlyPublicKey};
+use bitcoin::util::address::{Payload, WitnessVersion};
+use bitcoin::util::misc::signed_msg_hash;
+use bitcoin::util::sighash::{Prevouts, SighashCache};
+use bitcoin::{
+    Address, EcdsaSighashType, OutPoint, PackedLockTime, PrivateKey, PublicKey, SchnorrSighashType,
+    Script, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+use serde::{Deserialize, Serialize};
+
+use gdk_common::scripts::{p2pkh_script, ScriptType};
+
+use crate::error::Error;
+
+/// Sign `message` with `private_key` in the legacy format of BIP137, whose header byte encodes
+/// the type of the address
+pub fn sign_bip137(
+    private_key: &PrivateKey,
+    script_type: ScriptType,
+    message: &str,
+) -> Result<String, Error> {
+    let header = match script_type {
+        ScriptType::P2pkh if private_key.compressed => 31,
+        ScriptType::P2pkh => 27,
+        ScriptType::P2shP2wpkh => 35,
+        ScriptType::P2wpkh => 39,
+        ScriptType::P2tr => {
+            return Err(Error::InvalidMessageRequest(
+                "taproot addresses support only BIP322 signatures".into(),
+            ))
+        }
+    };
+    let msg = Message::from_slice(&signed_msg_hash(message)[..])?;
+    let signature = crate::EC.sign_ecdsa_recoverable(&msg, &private_key.inner);
+    let (recovery_id, signature) = signature.serialize_compact();
+    let mut serialized = vec![header + recovery_id.to_i32() as u8];
+    serialized.extend_from_slice(&signature);
+    Ok(base64::encode(serialized))
+}
+
+/// The tagged hash of `message` committed in the BIP322 virtual transactions
+fn message_hash(message: &str) -> sha256::Hash {
+    let tag = sha256::Hash::hash(b"BIP0322-signed-message");
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag[..]);
+    engine.input(&tag[..]);
+    engine.input(message.as_bytes());
+    sha256::Hash::from_engine(engine)
+}
+
+/// The BIP322 virtual transaction with an output locked by `script_pubkey` committing to `message`
+pub fn to_spend(script_pubkey: &Script, message: &str) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::all_zeros(), 0xffff_ffff),
+            script_sig: Builder::new().push_int(0).push_slice(&message_hash(message)).into_script(),
+            sequence: Sequence(0),
+            witness: Witness::default(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    }
+}
+
+/// The BIP322 virtual transaction spending `to_spend`, whose witness is the signature
+pub fn to_sign(to_spend: &Transaction, witness: Witness) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend.txid(), 0),
+            script_sig: Script::new(),
+            sequence: Sequence(0),
+            witness,
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: Builder::new().push_opcode(opcodes::all::OP_RETURN).into_script(),
+        }],
+    }
+}
+
+/// Encode the witness of a BIP322 simple signature
+pub fn encode_bip322_simple(witness: Vec<Vec<u8>>) -> String {
+    base64::encode(serialize(&Witness::from_vec(witness)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyMessageParam {
+    address: String,
+    message: String,
+    signature: String,
+}
+#[derive(Debug, Serialize)]
+pub struct VerifyMessageResult {
+    is_valid: bool,
+}
+/// Verify that `signature` is a BIP137 or BIP322 simple signature of `message` made with the key
+/// of `address`
+///
+/// As other wallets do, BIP137 signatures are accepted regardless of the address type in the
+/// header byte.
+pub fn verify(param: &VerifyMessageParam) -> Result<VerifyMessageResult, Error> {
+    let address = Address::from_str(&param.address).map_err(|_| Error::InvalidAddress)?;
+    let signature = base64::decode(&param.signature)?;
+    let is_valid = if signature.len() == 65 && (27..=42).contains(&signature[0]) {
+        verify_bip137(&address, &param.message, &signature)
+    } else {
+        verify_bip322_simple(&address, &param.message, &signature)
+    };
+    Ok(VerifyMessageResult {
+        is_valid,
+    })
+}
+
+fn verify_bip137(address: &Address, message: &str, signature: &[u8]) -> bool {
+    let header = signature[0] - 27;
+    let public_key = RecoveryId::from_i32((header % 4) as i32)
+        .and_then(|id| RecoverableSignature::from_compact(&signature[1..], id))
+        .and_then(|sig| {
+            let msg = Message::from_slice(&signed_msg_hash(message)[..])?;
+            crate::EC.recover_ecdsa(&msg, &sig)
+        });
+    let public_key = match public_key {
+        Ok(inner) => PublicKey {
+            compressed: header >= 4,
+            inner,
+        },
+        Err(_) => return false,
+    };
+    let network = address.network;
+    let mut addresses = vec![Address::p2pkh(&public_key, network)];
+    // segwit addresses are only for compressed keys
+    addresses.extend(Address::p2wpkh(&public_key, network));
+    addresses.extend(Address::p2shwpkh(&public_key, network));
+    addresses.iter().any(|a| a.script_pubkey() == address.script_pubkey())
+}
+
+fn verify_bip322_simple(address: &Address, message: &str, signature: &[u8]) -> bool {
+    let witness: Witness = match deserialize(signature) {
+        Ok(witness) => witness,
+        Err(_) => return false,
+    };
+    let to_spend = to_spend(&address.script_pubkey(), message);
+    let to_sign = to_sign(&to_spend, witness.clone());
+    let witness = witness.to_vec();
+    let mut cache = SighashCache::new(&to_sign);
+    match &address.payload {
+        Payload::WitnessProgram {
+            version: WitnessVersion::V0,
+            program,
+        } if program.len() == 20 && witness.len() == 2 => {
+            let public_key = match PublicKey::from_slice(&witness[1]) {
+                Ok(public_key) if public_key.compressed => public_key,
+                _ => return false,
+            };
+            let (signature, sighash) = match witness[0].split_last() {
+                Some((sighash, signature)) => (signature, *sighash),
+                None => return false,
+            };
+            match public_key.wpubkey_hash() {
+                Some(hash) if hash[..] == program[..] => (),
+                _ => return false,
+            }
+            let sighash = match EcdsaSighashType::from_standard(sighash as u32) {
+                Ok(sighash) => sighash,
+                Err(_) => return false,
+            };
+            let script_code = p2pkh_script(&public_key);
+            let hash = match cache.segwit_signature_hash(0, &script_code, 0, sighash) {
+                Ok(hash) => hash,
+                Err(_) => return false,
+            };
+            let msg = Message::from_slice(&hash[..]).expect("32 bytes");
+            Signature::from_der(signature)
+                .and_then(|sig| crate::EC.verify_ecdsa(&msg, &sig, &public_key.inner))
+                .is_ok()
+        }
+        Payload::WitnessProgram {
+            version: WitnessVersion::V1,
+            program,
+        } if program.len() == 32 && witness.len() == 1 => {
+            let output_key = match XOnlyPublicKey::from_slice(program) {
+                Ok(output_key) => output_key,
+                Err(_) => return false,
+            };
+            let (signature, sighash) = match witness[0].len() {
+                64 => (&witness[0][..], SchnorrSighashType::Default),
+                65 => match SchnorrSighashType::from_consensus_u8(witness[0][64]) {
+                    Ok(sighash) if sighash != SchnorrSighashType::Default => {
+                        (&witness[0][..64], sighash)
+                    }
+                    _ => return false,
+                },
+                _ => return false,
+            };
+            let prevouts = Prevouts::All(&to_spend.output);
+            let hash = match cache.taproot_key_spend_signature_hash(0, &prevouts, sighash) {
+                Ok(hash) => hash,
+                Err(_) => return false,
+            };
+            let msg = Message::from_slice(&hash[..]).expect("32 bytes");
+            schnorr::Signature::from_slice(signature)
+                .and_then(|sig| crate::EC.verify_schnorr(&sig, &msg, &output_key))
+                .is_ok()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn verify_str(address: &str, message: &str, signature: &str) -> bool {
+        let param = VerifyMessageParam {
+            address: address.into(),
+            message: message.into(),
+            signature: signature.into(),
+        };
+        verify(&param).unwrap().is_valid
+    }
+
+    #[test]
+    fn bip322_message_hash() {
+        // test vectors from the BIP
+        assert_eq!(
+            message_hash("").to_string(),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            message_hash("Hello World").to_string(),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+    }
+
+    #[test]
+    fn bip322_vectors() {
+        let address = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+        let script_pubkey = Address::from_str(address).unwrap().script_pubkey();
+        assert_eq!(
+            to_spend(&script_pubkey, "").txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        let to_spend_hello = to_spend(&script_pubkey, "Hello World");
+        assert_eq!(
+            to_spend_hello.txid().to_string(),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+        assert_eq!(
+            to_sign(&to_spend_hello, Witness::default()).txid().to_string(),
+            "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"
+        );
+
+        let signature = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        assert!(verify_str(address, "Hello World", signature));
+        assert!(!verify_str(address, "", signature));
+        let other_key = PrivateKey::from_slice(&[1u8; 32], bitcoin::Network::Bitcoin).unwrap();
+        let other_address =
+            Address::p2wpkh(&other_key.public_key(&crate::EC), bitcoin::Network::Bitcoin).unwrap();
+        assert!(!verify_str(&other_address.to_string(), "Hello World", signature));
+    }
+
+    #[test]
+    fn bip137_roundtrip() {
+        let private_key =
+            PrivateKey::from_wif("L44B5gGEpqEDRS9vVPz7QT35jcBG2r3CZwSwQ4fCewXAhAhqGVpP").unwrap();
+        let public_key = private_key.public_key(&crate::EC);
+        let net = bitcoin::Network::Bitcoin;
+        let addresses = [
+            (ScriptType::P2pkh, Address::p2pkh(&public_key, net)),
+            (ScriptType::P2shP2wpkh, Address::p2shwpkh(&public_key, net).unwrap()),
+            (ScriptType::P2wpkh, Address::p2wpkh(&public_key, net).unwrap()),
+        ];
+        for (script_type, address) in addresses.iter() {
+            let signature = sign_bip137(&private_key, *script_type, "message").unwrap();
+            assert!(verify_str(&address.to_string(), "message", &signature));
+            assert!(!verify_str(&address.to_string(), "other message", &signature));
+        }
+        assert!(sign_bip137(&private_key, ScriptType::P2tr, "message").is_err());
+    }
+}
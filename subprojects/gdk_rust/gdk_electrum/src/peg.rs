@@ -0,0 +1,219 @@
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{Builder, Instruction};
+use bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{PublicKey, Scalar};
+use bitcoin::util::merkleblock::MerkleBlock;
+use bitcoin::{BlockHash, Script};
+use elements::{AssetId, OutPoint, PeginData, TxIn, Txid};
+
+use crate::error::Error;
+
+/// The federation script tweaked with `claim_script`, the pegin outputs on the mainchain pay to it
+///
+/// As in Elements Core every 33 bytes push is considered a public key and tweaked with the HMAC of
+/// the claim script, in scripts with an emergency branch only the keys before `OP_ELSE` are.
+pub fn fedpeg_contract(fedpeg_script: &Script, claim_script: &[u8]) -> Result<Script, Error> {
+    let mut builder = Builder::new();
+    let mut op_else_found = false;
+    for instruction in fedpeg_script.instructions() {
+        match instruction.map_err(|e| Error::InvalidPegRequest(e.to_string()))? {
+            Instruction::PushBytes(bytes) if bytes.len() == 33 && !op_else_found => {
+                let pubkey = PublicKey::from_slice(bytes)?;
+                let mut engine = hmac::HmacEngine::<sha256::Hash>::new(bytes);
+                engine.input(claim_script);
+                let tweak = hmac::Hmac::<sha256::Hash>::from_engine(engine).into_inner();
+                let tweak = Scalar::from_be_bytes(tweak)
+                    .map_err(|_| Error::InvalidPegRequest("invalid contract tweak".into()))?;
+                let tweaked = pubkey.add_exp_tweak(&crate::EC, &tweak)?;
+                builder = builder.push_slice(&tweaked.serialize());
+            }
+            Instruction::PushBytes(bytes) => builder = builder.push_slice(bytes),
+            Instruction::Op(op) => {
+                if op == opcodes::all::OP_ELSE {
+                    op_else_found = true;
+                }
+                builder = builder.push_opcode(op);
+            }
+        }
+    }
+    Ok(builder.into_script())
+}
+
+/// The mainchain address to send bitcoins to for pegging them in with `claim_script`
+pub fn mainchain_address(
+    fedpeg_script: &Script,
+    claim_script: &[u8],
+    network: bitcoin::Network,
+) -> Result<bitcoin::Address, Error> {
+    Ok(bitcoin::Address::p2shwsh(&fedpeg_contract(fedpeg_script, claim_script)?, network))
+}
+
+/// Find the output of `tx` pegging in with `claim_script` and check `proof` commits to `tx`,
+/// returning the output index and value
+pub fn find_pegin_output(
+    tx: &bitcoin::Transaction,
+    proof: &MerkleBlock,
+    fedpeg_script: &Script,
+    claim_script: &[u8],
+) -> Result<(u32, u64), Error> {
+    let mut matches = vec![];
+    let mut indexes = vec![];
+    proof
+        .extract_matches(&mut matches, &mut indexes)
+        .map_err(|e| Error::InvalidPegRequest(format!("invalid txout proof: {:?}", e)))?;
+    if matches != [tx.txid()] {
+        return Err(Error::InvalidPegRequest("txout proof does not match the transaction".into()));
+    }
+
+    // both the P2SH-P2WSH and the P2WSH of the contract are accepted by the consensus rules
+    let contract = fedpeg_contract(fedpeg_script, claim_script)?;
+    let p2wsh = Script::new_v0_p2wsh(&contract.wscript_hash());
+    let p2shwsh = Script::new_p2sh(&p2wsh.script_hash());
+    tx.output
+        .iter()
+        .position(|o| o.script_pubkey == p2wsh || o.script_pubkey == p2shwsh)
+        .map(|vout| (vout as u32, tx.output[vout].value))
+        .ok_or_else(|| {
+            Error::InvalidPegRequest("no output pegging in with the claim script".into())
+        })
+}
+
+/// An input claiming the output `vout` of the mainchain transaction `tx`
+pub fn pegin_input(
+    tx: &bitcoin::Transaction,
+    proof: &MerkleBlock,
+    vout: u32,
+    claim_script: &[u8],
+    asset: AssetId,
+    genesis_hash: BlockHash,
+) -> TxIn {
+    let tx_bytes = bitcoin::consensus::serialize(tx);
+    let proof_bytes = bitcoin::consensus::serialize(proof);
+    let pegin_data = PeginData {
+        outpoint: bitcoin::OutPoint::new(tx.txid(), vout),
+        value: tx.output[vout as usize].value,
+        asset,
+        genesis_hash,
+        claim_script,
+        tx: &tx_bytes,
+        merkle_proof: &proof_bytes,
+        referenced_block: proof.header.block_hash(),
+    };
+    let mut input = TxIn {
+        previous_output: OutPoint::new(Txid::from_inner(tx.txid().into_inner()), vout),
+        is_pegin: true,
+        sequence: 0xffff_fffe,
+        ..Default::default()
+    };
+    input.witness.pegin_witness = pegin_data.to_pegin_witness();
+    input
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::secp256k1::SecretKey;
+    use gdk_common::be::BETransaction;
+    use gdk_common::network::ElementsNetwork;
+    use gdk_common::NetworkId;
+
+    fn pubkey(byte: u8) -> PublicKey {
+        PublicKey::from_secret_key(&crate::EC, &SecretKey::from_slice(&[byte; 32]).unwrap())
+    }
+
+    fn pushed_keys(script: &Script) -> Vec<Vec<u8>> {
+        script
+            .instructions()
+            .filter_map(|i| match i.unwrap() {
+                Instruction::PushBytes(b) if b.len() == 33 => Some(b.to_vec()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn contract_op_true() {
+        let fedpeg_script = Script::from(vec![0x51]);
+        let claim_script =
+            Vec::<u8>::from_hex("0014d0c4a3ef09e997b6e99e397e518fe3e41a118ca1").unwrap();
+        let contract = fedpeg_contract(&fedpeg_script, &claim_script).unwrap();
+        assert_eq!(contract, fedpeg_script);
+        let address = mainchain_address(&fedpeg_script, &claim_script, bitcoin::Network::Regtest);
+        assert_eq!(
+            address.unwrap().script_pubkey(),
+            Script::new_p2sh(&Script::new_v0_p2wsh(&contract.wscript_hash()).script_hash())
+        );
+    }
+
+    #[test]
+    fn contract_multisig() {
+        let claim_script = [0x51];
+        let fedpeg_script = Builder::new()
+            .push_opcode(opcodes::all::OP_PUSHNUM_1)
+            .push_key(&bitcoin::PublicKey::new(pubkey(1)))
+            .push_key(&bitcoin::PublicKey::new(pubkey(2)))
+            .push_opcode(opcodes::all::OP_PUSHNUM_2)
+            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
+            .into_script();
+        let contract = fedpeg_contract(&fedpeg_script, &claim_script).unwrap();
+        assert_eq!(contract.len(), fedpeg_script.len());
+
+        let keys = pushed_keys(&contract);
+        for (i, key) in pushed_keys(&fedpeg_script).iter().enumerate() {
+            let mut engine = hmac::HmacEngine::<sha256::Hash>::new(key);
+            engine.input(&claim_script);
+            let tweak = hmac::Hmac::<sha256::Hash>::from_engine(engine);
+            let tweak = SecretKey::from_slice(&tweak[..]).unwrap();
+            let expected = PublicKey::from_slice(key)
+                .unwrap()
+                .combine(&PublicKey::from_secret_key(&crate::EC, &tweak))
+                .unwrap();
+            assert_eq!(keys[i], expected.serialize().to_vec());
+        }
+
+        // another claim script gives another contract
+        assert_ne!(contract, fedpeg_contract(&fedpeg_script, &[0x52]).unwrap());
+    }
+
+    #[test]
+    fn contract_emergency_keys() {
+        // the keys of the emergency branch are not tweaked
+        let fedpeg_script = Builder::new()
+            .push_opcode(opcodes::all::OP_DEPTH)
+            .push_opcode(opcodes::all::OP_PUSHNUM_1)
+            .push_opcode(opcodes::all::OP_EQUAL)
+            .push_opcode(opcodes::all::OP_IF)
+            .push_key(&bitcoin::PublicKey::new(pubkey(1)))
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .push_opcode(opcodes::all::OP_ELSE)
+            .push_key(&bitcoin::PublicKey::new(pubkey(2)))
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .push_opcode(opcodes::all::OP_ENDIF)
+            .into_script();
+        let contract = fedpeg_contract(&fedpeg_script, &[0x51]).unwrap();
+        let keys = pushed_keys(&contract);
+        assert_ne!(keys[0], pubkey(1).serialize().to_vec());
+        assert_eq!(keys[1], pubkey(2).serialize().to_vec());
+    }
+
+    #[test]
+    fn pegout() {
+        let genesis_hash = genesis_block(bitcoin::Network::Regtest).block_hash();
+        let script_pubkey =
+            Script::from_hex("0014d0c4a3ef09e997b6e99e397e518fe3e41a118ca1").unwrap();
+        let asset = AssetId::from_slice(&[1u8; 32]).unwrap();
+        let mut tx = BETransaction::new(NetworkId::Elements(ElementsNetwork::ElementsRegtest));
+        tx.add_pegout_output(1000, asset, genesis_hash, &script_pubkey).unwrap();
+        let pegout = match tx {
+            BETransaction::Elements(tx) => tx.output[0].clone(),
+            BETransaction::Bitcoin(_) => unreachable!(),
+        };
+        assert!(pegout.script_pubkey.is_provably_unspendable());
+        let pegout_data = pegout.pegout_data().unwrap();
+        assert_eq!(pegout_data.value, 1000);
+        assert_eq!(pegout_data.genesis_hash, genesis_hash);
+        assert_eq!(pegout_data.script_pubkey, script_pubkey);
+    }
+}
@@ -0,0 +1,369 @@
+//! Failover among multiple electrum servers
+//!
+//! Every call to a server is tracked to score its health: servers with recent errors, with a tip
+//! lagging behind the ones of their peers, or just slow, are used only if the better ones fail.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bitcoin::hashes::sha256;
+use bitcoin::{BlockHeader, Script, Txid};
+use electrum_client::{Client, GetHistoryRes, GetMerkleRes, ListUnspentRes, RawHeaderNotification};
+use log::{info, warn};
+
+use crate::backend::{Backend, ScriptStatus};
+use crate::error::Error;
+use crate::interface::ElectrumUrl;
+
+/// Each recent error of a server weighs as this much latency
+const ERROR_PENALTY: Duration = Duration::from_secs(10);
+
+/// Each position after the first in the list of servers weighs as this much latency
+const POSITION_PENALTY: Duration = Duration::from_secs(1);
+
+/// A server lagging behind its peers weighs as this much latency
+const LAGGING_PENALTY: Duration = Duration::from_secs(60);
+
+/// Errors older than this are forgotten, so that failed servers are tried again
+const ERROR_MEMORY: Duration = Duration::from_secs(600);
+
+/// Tips older than this are not used to compare servers, and are refreshed from a peer
+const TIP_MEMORY: Duration = Duration::from_secs(600);
+
+/// A server whose tip is behind the best tip of its peers by more blocks than this is lagging
+const MAX_LAG_BLOCKS: u32 = 2;
+
+#[derive(Debug, Default)]
+struct ServerHealth {
+    /// moving average of the response times
+    latency: Option<Duration>,
+
+    /// consecutive errors, and the time of the last one
+    errors: u32,
+    last_error: Option<Instant>,
+
+    /// the last tip height, and the time it was received
+    tip: Option<(u32, Instant)>,
+}
+
+impl ServerHealth {
+    fn fresh_tip(&self) -> Option<u32> {
+        self.tip.filter(|(_, time)| time.elapsed() < TIP_MEMORY).map(|(height, _)| height)
+    }
+
+    fn is_lagging(&self, best_height: u32) -> bool {
+        matches!(self.fresh_tip(), Some(height) if height + MAX_LAG_BLOCKS < best_height)
+    }
+
+    /// Lower is better
+    fn score(&self, position: usize, best_height: u32) -> Duration {
+        let mut score = POSITION_PENALTY * position as u32 + self.latency.unwrap_or_default();
+        if matches!(self.last_error, Some(time) if time.elapsed() < ERROR_MEMORY) {
+            score += ERROR_PENALTY * self.errors;
+        }
+        if self.is_lagging(best_height) {
+            score += LAGGING_PENALTY;
+        }
+        score
+    }
+}
+
+/// Electrum servers in order of preference, sharing their health among clones
+#[derive(Clone, Debug)]
+pub struct ElectrumPool {
+    urls: Arc<Vec<ElectrumUrl>>,
+    health: Arc<Mutex<Vec<ServerHealth>>>,
+}
+
+impl ElectrumPool {
+    pub fn new(urls: Vec<ElectrumUrl>) -> Self {
+        let health = urls.iter().map(|_| ServerHealth::default()).collect();
+        ElectrumPool {
+            urls: Arc::new(urls),
+            health: Arc::new(Mutex::new(health)),
+        }
+    }
+
+    /// The url of the preferred server
+    pub fn url(&self) -> &str {
+        self.urls.first().map(ElectrumUrl::url).unwrap_or_default()
+    }
+
+    /// Connect to the healthiest server whose tip is not lagging, failing over to the others in
+    /// order of score
+    pub fn build_client(
+        &self,
+        proxy: Option<&str>,
+        timeout: Option<u8>,
+    ) -> Result<PooledClient, Error> {
+        let mut last_error = Error::Generic("no electrum server".into());
+        for index in self.ranking()? {
+            let client = match self.connect(index, proxy, timeout) {
+                Ok(client) => client,
+                Err(e) => {
+                    warn!("can't connect to {}: {:?}", self.urls[index].url(), e);
+                    last_error = e;
+                    continue;
+                }
+            };
+            match client.tip() {
+                Ok(_) => {
+                    info!("connected to {}", self.urls[index].url());
+                    return Ok(client);
+                }
+                Err(e) => {
+                    warn!("failing over from {}: {:?}", self.urls[index].url(), e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    fn connect(
+        &self,
+        index: usize,
+        proxy: Option<&str>,
+        timeout: Option<u8>,
+    ) -> Result<PooledClient, Error> {
+        let start = Instant::now();
+        let client = self.urls[index].build_client(proxy, timeout);
+        self.record(index, start.elapsed(), client.is_ok())?;
+        Ok(PooledClient {
+            client: client?,
+            pool: self.clone(),
+            index,
+            proxy: proxy.map(Into::into),
+            timeout,
+        })
+    }
+
+    /// The indexes of the servers from the healthiest
+    fn ranking(&self) -> Result<Vec<usize>, Error> {
+        let health = self.health.lock()?;
+        let best_height = best_height(&health, None);
+        let mut ranking: Vec<_> = (0..health.len()).collect();
+        ranking.sort_by_key(|i| health[*i].score(*i, best_height));
+        Ok(ranking)
+    }
+
+    fn record(&self, index: usize, elapsed: Duration, success: bool) -> Result<(), Error> {
+        let mut health = self.health.lock()?;
+        let server = &mut health[index];
+        if success {
+            server.latency = Some(match server.latency {
+                Some(latency) => (latency * 3 + elapsed) / 4,
+                None => elapsed,
+            });
+            server.errors = 0;
+        } else {
+            server.errors += 1;
+            server.last_error = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Record the tip height of a server, returning an error if it's lagging behind its peers
+    fn record_tip(&self, index: usize, height: u32) -> Result<(), Error> {
+        let mut health = self.health.lock()?;
+        health[index].tip = Some((height, Instant::now()));
+        let best_height = best_height(&health, Some(index));
+        if health[index].is_lagging(best_height) {
+            return Err(Error::Generic(format!(
+                "electrum server {} at height {} is lagging behind its peers at height {}",
+                self.urls[index].url(),
+                height,
+                best_height
+            )));
+        }
+        Ok(())
+    }
+
+    /// The healthiest server other than `index` if no other server has a fresh tip
+    fn peer_to_check(&self, index: usize) -> Result<Option<usize>, Error> {
+        let health = self.health.lock()?;
+        if best_height(&health, Some(index)) > 0 {
+            return Ok(None);
+        }
+        drop(health);
+        Ok(self.ranking()?.into_iter().find(|i| *i != index))
+    }
+}
+
+/// The best fresh tip height among the servers, excluding `exclude`
+fn best_height(health: &[ServerHealth], exclude: Option<usize>) -> u32 {
+    health
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| Some(*i) != exclude)
+        .filter_map(|(_, server)| server.fresh_tip())
+        .max()
+        .unwrap_or(0)
+}
+
+/// A client of one of the servers of a pool, recording the health of the server
+pub struct PooledClient {
+    client: Client,
+    pool: ElectrumPool,
+    index: usize,
+    proxy: Option<String>,
+    timeout: Option<u8>,
+}
+
+impl PooledClient {
+    fn track<T>(&self, call: impl FnOnce(&Client) -> Result<T, Error>) -> Result<T, Error> {
+        let start = Instant::now();
+        let result = call(&self.client);
+        self.pool.record(self.index, start.elapsed(), result.is_ok())?;
+        result
+    }
+
+    /// Refresh the tip of a peer to check the tip of this server against
+    fn check_peer(&self) -> Result<(), Error> {
+        if let Some(peer) = self.pool.peer_to_check(self.index)? {
+            let client = self.pool.connect(peer, self.proxy.as_deref(), self.timeout)?;
+            let tip = client.track(|c| c.tip())?;
+            // the peer could be lagging too, it's not a reason to fail over from this server
+            let _ = self.pool.record_tip(peer, tip.height as u32);
+        }
+        Ok(())
+    }
+}
+
+impl Backend for PooledClient {
+    fn ping(&self) -> Result<(), Error> {
+        self.track(|c| c.ping())
+    }
+
+    fn relay_fee(&self) -> Result<f64, Error> {
+        self.track(|c| c.relay_fee())
+    }
+
+    fn batch_estimate_fee(&self, blocks: &[usize]) -> Result<Vec<f64>, Error> {
+        self.track(|c| c.batch_estimate_fee(blocks))
+    }
+
+    fn tip(&self) -> Result<RawHeaderNotification, Error> {
+        let tip = self.track(|c| c.tip())?;
+        if let Err(e) = self.check_peer() {
+            warn!("can't check the tip of a peer: {:?}", e);
+        }
+        self.pool.record_tip(self.index, tip.height as u32)?;
+        Ok(tip)
+    }
+
+    fn block_header_raw(&self, height: usize) -> Result<Vec<u8>, Error> {
+        self.track(|c| c.block_header_raw(height))
+    }
+
+    fn batch_block_header_raw(&self, heights: &[u32]) -> Result<Vec<Vec<u8>>, Error> {
+        self.track(|c| c.batch_block_header_raw(heights))
+    }
+
+    fn block_headers(&self, start_height: usize, count: usize) -> Result<Vec<BlockHeader>, Error> {
+        self.track(|c| c.block_headers(start_height, count))
+    }
+
+    fn batch_script_get_history(
+        &self,
+        scripts: &[Script],
+    ) -> Result<Vec<Vec<GetHistoryRes>>, Error> {
+        self.track(|c| c.batch_script_get_history(scripts))
+    }
+
+    fn batch_script_list_unspent(
+        &self,
+        scripts: &[Script],
+    ) -> Result<Vec<Vec<ListUnspentRes>>, Error> {
+        self.track(|c| c.batch_script_list_unspent(scripts))
+    }
+
+    fn batch_transaction_get_raw(&self, txids: &[Txid]) -> Result<Vec<Vec<u8>>, Error> {
+        self.track(|c| c.batch_transaction_get_raw(txids))
+    }
+
+    fn transaction_get_merkle(&self, txid: &Txid, height: usize) -> Result<GetMerkleRes, Error> {
+        self.track(|c| c.transaction_get_merkle(txid, height))
+    }
+
+    fn transaction_broadcast_raw(&self, raw_tx: &[u8]) -> Result<Txid, Error> {
+        self.track(|c| c.transaction_broadcast_raw(raw_tx))
+    }
+
+    fn notifies(&self) -> bool {
+        true
+    }
+
+    fn batch_script_subscribe(&self, scripts: &[Script]) -> Result<Vec<ScriptStatus>, Error> {
+        self.track(|c| c.batch_script_subscribe(scripts))
+    }
+
+    fn script_pop(&self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        self.client.script_pop(script)
+    }
+
+    fn tip_pop(&self) -> Result<Option<RawHeaderNotification>, Error> {
+        self.client.tip_pop()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pool(n: usize) -> ElectrumPool {
+        let urls = (0..n).map(|i| ElectrumUrl::Plaintext(format!("127.0.0.1:{}", i + 1))).collect();
+        ElectrumPool::new(urls)
+    }
+
+    #[test]
+    fn test_ranking() {
+        let pool = pool(3);
+        assert_eq!(pool.url(), "127.0.0.1:1");
+        assert_eq!(pool.ranking().unwrap(), vec![0, 1, 2]);
+
+        // a slow server is used after a faster one, unless the latter is much lower in the list
+        pool.record(0, Duration::from_millis(1500), true).unwrap();
+        pool.record(1, Duration::from_millis(100), true).unwrap();
+        pool.record(2, Duration::from_millis(100), true).unwrap();
+        assert_eq!(pool.ranking().unwrap(), vec![1, 0, 2]);
+
+        // errors are recovered by a success
+        pool.record(1, Duration::from_millis(100), false).unwrap();
+        assert_eq!(pool.ranking().unwrap(), vec![0, 2, 1]);
+        pool.record(1, Duration::from_millis(100), true).unwrap();
+        assert_eq!(pool.ranking().unwrap(), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_lagging() {
+        let pool = pool(3);
+        pool.record_tip(0, 100).unwrap();
+        pool.record_tip(1, 100 + MAX_LAG_BLOCKS).unwrap();
+        assert_eq!(pool.ranking().unwrap(), vec![0, 1, 2]);
+        assert!(pool.record_tip(2, 100 + MAX_LAG_BLOCKS * 2 + 1).is_ok());
+        // the other servers are lagging behind the new best tip
+        assert_eq!(pool.ranking().unwrap(), vec![2, 0, 1]);
+        assert!(pool.record_tip(0, 100).is_err());
+        assert!(pool.record_tip(0, 100 + MAX_LAG_BLOCKS * 2 + 1).is_ok());
+        assert_eq!(pool.ranking().unwrap(), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn test_peer_to_check() {
+        let pool = pool(2);
+        assert_eq!(pool.peer_to_check(0).unwrap(), Some(1));
+        pool.record_tip(1, 100).unwrap();
+        assert_eq!(pool.peer_to_check(0).unwrap(), None);
+        assert_eq!(pool.peer_to_check(1).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_failover_unreachable() {
+        // nothing listens on these ports
+        let pool = pool(2);
+        assert!(pool.build_client(None, Some(1)).is_err());
+        let health = pool.health.lock().unwrap();
+        assert!(health.iter().all(|server| server.errors == 1));
+    }
+}
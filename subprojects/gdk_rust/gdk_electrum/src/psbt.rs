@@ -0,0 +1,226 @@
+use crate::error::Error;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::Witness;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize)]
+pub struct CombineParam {
+    psbts: Vec<String>,
+}
+#[derive(Debug, Serialize)]
+pub struct PsbtResult {
+    psbt: String,
+}
+/// Return the base64 PSBT combining the given PSBTs of the same transaction
+pub fn combine(param: &CombineParam) -> Result<PsbtResult, Error> {
+    let mut psbts = param.psbts.iter().map(String::as_str).map(psbt_from_base64);
+    let mut combined =
+        psbts.next().ok_or_else(|| Error::Generic("no psbt to combine".into()))??;
+    for psbt in psbts {
+        combined.combine(psbt?)?;
+    }
+    Ok(PsbtResult {
+        psbt: psbt_to_base64(&combined),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FinalizeParam {
+    psbt: String,
+}
+/// Return the base64 PSBT with the single sig inputs finalized
+///
+/// Inputs already finalized are left untouched, the other inputs must be fully signed.
+pub fn finalize(param: &FinalizeParam) -> Result<PsbtResult, Error> {
+    let mut psbt = psbt_from_base64(&param.psbt)?;
+    finalize_inner(&mut psbt)?;
+    Ok(PsbtResult {
+        psbt: psbt_to_base64(&psbt),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExtractTxParam {
+    psbt: String,
+}
+#[derive(Debug, Serialize)]
+pub struct ExtractTxResult {
+    transaction: String,
+}
+/// Return the raw tx hex extracted from the given finalized PSBT
+pub fn extract_tx(param: &ExtractTxParam) -> Result<ExtractTxResult, Error> {
+    let psbt = psbt_from_base64(&param.psbt)?;
+    if let Some(i) = psbt
+        .inputs
+        .iter()
+        .position(|i| i.final_script_sig.is_none() && i.final_script_witness.is_none())
+    {
+        return Err(Error::Generic(format!("input {} is not finalized", i)));
+    }
+    Ok(ExtractTxResult {
+        transaction: serialize(&psbt.extract_tx()).to_hex(),
+    })
+}
+
+pub fn psbt_from_base64(psbt: &str) -> Result<PartiallySignedTransaction, Error> {
+    Ok(deserialize(&base64::decode(psbt)?)?)
+}
+
+pub fn psbt_to_base64(psbt: &PartiallySignedTransaction) -> String {
+    base64::encode(serialize(psbt))
+}
+
+fn finalize_inner(psbt: &mut PartiallySignedTransaction) -> Result<(), Error> {
+    for (i, input) in psbt.inputs.iter_mut().enumerate() {
+        if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+            continue;
+        }
+        let script_pubkey = match (&input.witness_utxo, &input.non_witness_utxo) {
+            (Some(txout), _) => txout.script_pubkey.clone(),
+            (None, Some(tx)) => {
+                let vout = psbt.unsigned_tx.input[i].previous_output.vout as usize;
+                tx.output.get(vout).ok_or(Error::InvalidAmount)?.script_pubkey.clone()
+            }
+            (None, None) => return Err(Error::Generic(format!("input {} has no utxo", i))),
+        };
+        let cannot_finalize = || Error::Generic(format!("cannot finalize input {}", i));
+        if script_pubkey.is_v1_p2tr() {
+            let sig = input.tap_key_sig.ok_or_else(cannot_finalize)?;
+            input.final_script_witness = Some(Witness::from_vec(vec![sig.to_vec()]));
+        } else {
+            if input.partial_sigs.len() != 1 {
+                return Err(cannot_finalize());
+            }
+            let (public_key, sig) = input.partial_sigs.iter().next().expect("checked len");
+            let witness = vec![sig.to_vec(), public_key.to_bytes()];
+            if script_pubkey.is_v0_p2wpkh() {
+                input.final_script_witness = Some(Witness::from_vec(witness));
+            } else if script_pubkey.is_p2sh()
+                && input.redeem_script.as_ref().map_or(false, |s| s.is_v0_p2wpkh())
+            {
+                let redeem_script = input.redeem_script.as_ref().expect("checked some");
+                input.final_script_sig =
+                    Some(Builder::new().push_slice(redeem_script.as_bytes()).into_script());
+                input.final_script_witness = Some(Witness::from_vec(witness));
+            } else if script_pubkey.is_p2pkh() {
+                input.final_script_sig = Some(
+                    Builder::new().push_slice(&witness[0]).push_slice(&witness[1]).into_script(),
+                );
+            } else {
+                return Err(cannot_finalize());
+            }
+        }
+        // the fields not needed anymore are removed as BIP174 recommends
+        input.partial_sigs = BTreeMap::new();
+        input.sighash_type = None;
+        input.redeem_script = None;
+        input.witness_script = None;
+        input.bip32_derivation = BTreeMap::new();
+        input.tap_key_sig = None;
+        input.tap_key_origins = BTreeMap::new();
+        input.tap_internal_key = None;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::psbt::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+    use bitcoin::util::psbt::Input;
+    use bitcoin::{EcdsaSig, EcdsaSighashType, OutPoint, PublicKey, Transaction, TxIn, TxOut};
+
+    fn psbt_with_p2wpkh_input() -> (PartiallySignedTransaction, PublicKey, EcdsaSig) {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let public_key = PublicKey::new(secret_key.public_key(&secp));
+        let tx = Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime(0),
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                ..Default::default()
+            }],
+            output: vec![TxOut::default()],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 1_000,
+            script_pubkey: bitcoin::Script::new_v0_p2wpkh(&public_key.wpubkey_hash().unwrap()),
+        });
+        // the signature validity is not checked when finalizing
+        let sig = EcdsaSig {
+            sig: secp.sign_ecdsa(&Message::from_slice(&[2u8; 32]).unwrap(), &secret_key),
+            hash_ty: EcdsaSighashType::All,
+        };
+        (psbt, public_key, sig)
+    }
+
+    #[test]
+    fn test_combine_finalize_extract() {
+        let (psbt, public_key, sig) = psbt_with_p2wpkh_input();
+        let mut signed = psbt.clone();
+        signed.inputs[0].partial_sigs.insert(public_key, sig);
+
+        // not signed
+        let unsigned = psbt_to_base64(&psbt);
+        assert!(finalize(&FinalizeParam {
+            psbt: unsigned.clone(),
+        })
+        .is_err());
+
+        let combined = combine(&CombineParam {
+            psbts: vec![unsigned.clone(), psbt_to_base64(&signed)],
+        })
+        .unwrap()
+        .psbt;
+        assert!(extract_tx(&ExtractTxParam {
+            psbt: combined.clone(),
+        })
+        .is_err());
+
+        let finalized = finalize(&FinalizeParam {
+            psbt: combined,
+        })
+        .unwrap()
+        .psbt;
+        let finalized_psbt = psbt_from_base64(&finalized).unwrap();
+        assert_eq!(finalized_psbt.inputs[0].partial_sigs.len(), 0);
+
+        let tx_hex = extract_tx(&ExtractTxParam {
+            psbt: finalized,
+        })
+        .unwrap()
+        .transaction;
+        let tx: Transaction = deserialize(&Vec::<u8>::from_hex(&tx_hex).unwrap()).unwrap();
+        assert_eq!(tx.input[0].witness.to_vec(), vec![sig.to_vec(), public_key.to_bytes()]);
+        assert!(tx.input[0].script_sig.is_empty());
+
+        // psbts of different transactions cannot be combined
+        let mut other = psbt;
+        other.unsigned_tx.lock_time = bitcoin::PackedLockTime(1);
+        assert!(combine(&CombineParam {
+            psbts: vec![unsigned, psbt_to_base64(&other)],
+        })
+        .is_err());
+        assert!(combine(&CombineParam {
+            psbts: vec![],
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_finalize_unknown_script() {
+        let (mut psbt, public_key, sig) = psbt_with_p2wpkh_input();
+        psbt.inputs[0].partial_sigs.insert(public_key, sig);
+        psbt.inputs[0].witness_utxo.as_mut().unwrap().script_pubkey = bitcoin::Script::new();
+        assert!(finalize_inner(&mut psbt).is_err());
+        psbt.inputs[0] = Input::default();
+        assert!(finalize_inner(&mut psbt).is_err());
+    }
+}
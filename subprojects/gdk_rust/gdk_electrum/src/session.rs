@@ -13,7 +13,10 @@ use gdk_common::{
 };
 use serde_json::Value;
 
-use crate::{account::Account, error::Error, interface::ElectrumUrl, socksify, ElectrumSession};
+use crate::{
+    account::Account, backend::BackendUrl, error::Error, interface::ElectrumUrl,
+    pool::ElectrumPool, socksify, spv, ElectrumSession,
+};
 
 impl ExchangeRatesCacher for ElectrumSession {
     fn xr_cache(&self) -> ExchangeRatesCache {
@@ -23,7 +26,7 @@ impl ExchangeRatesCacher for ElectrumSession {
 
 impl Session for ElectrumSession {
     fn new(network_parameters: NetworkParameters) -> Result<Self, JsonError> {
-        let url = determine_electrum_url(&network_parameters)?;
+        let url = determine_backend_url(&network_parameters)?;
 
         Ok(Self {
             proxy: socksify(network_parameters.proxy.as_deref()),
@@ -39,6 +42,7 @@ impl Session for ElectrumSession {
             master_xpub: None,
             master_xprv: None,
             recent_spent_utxos: Arc::new(RwLock::new(HashSet::<BEOutPoint>::new())),
+            signer: None,
             xr_cache: ExchangeRatesCache::default(),
         })
     }
@@ -59,7 +63,15 @@ impl Session for ElectrumSession {
 
             "disconnect" => self.disconnect().to_json(),
 
-            "login" => self.login(serde_json::from_value(input)?).to_json(),
+            "login" => {
+                if input.get("slip132_extended_pubkeys").is_some()
+                    || input.get("core_descriptors").is_some()
+                {
+                    self.login_watch_only(serde_json::from_value(input)?).to_json()
+                } else {
+                    self.login(serde_json::from_value(input)?).to_json()
+                }
+            }
             "credentials_from_pin_data" => {
                 self.credentials_from_pin_data(serde_json::from_value(input)?).to_json()
             }
@@ -77,6 +89,9 @@ impl Session for ElectrumSession {
             "discover_subaccount" => {
                 self.discover_subaccount(serde_json::from_value(input)?).to_json()
             }
+            "get_subaccount_descriptors" => {
+                self.get_subaccount_descriptors(&serde_json::from_value(input)?).to_json()
+            }
             "get_subaccount_root_path" => {
                 self.get_subaccount_root_path(serde_json::from_value(input)?).to_json()
             }
@@ -114,8 +129,31 @@ impl Session for ElectrumSession {
                 .to_json(),
             "get_balance" => self.get_balance(&serde_json::from_value(input)?).to_json(),
             "set_transaction_memo" => set_transaction_memo(self, &input),
+            "export_labels" => self.export_labels().to_json(),
+            "import_labels" => self.import_labels(&serde_json::from_value(input)?).to_json(),
             "create_transaction" => create_transaction(self, input).map_err(Into::into),
+            "create_issuance" => self.create_issuance(&serde_json::from_value(input)?).to_json(),
+            "create_reissuance" => {
+                self.create_reissuance(&serde_json::from_value(input)?).to_json()
+            }
+            "create_burn" => self.create_burn(&serde_json::from_value(input)?).to_json(),
+            "get_pegin_address" => {
+                self.get_pegin_address(&serde_json::from_value(input)?).to_json()
+            }
+            "create_pegin_claim" => {
+                self.create_pegin_claim(&serde_json::from_value(input)?).to_json()
+            }
+            "create_swap_proposal" => {
+                self.create_swap_proposal(&serde_json::from_value(input)?).to_json()
+            }
+            "complete_swap" => self.complete_swap(&serde_json::from_value(input)?).to_json(),
+            "get_unblinded_data" => {
+                self.get_unblinded_data(&serde_json::from_value(input)?).to_json()
+            }
+            "sign_message" => self.sign_message(&serde_json::from_value(input)?).to_json(),
             "psbt_get_details" => self.psbt_get_details(serde_json::from_value(input)?).to_json(),
+            "create_psbt" => self.create_psbt(&serde_json::from_value(input)?).to_json(),
+            "sign_psbt" => self.sign_psbt(&serde_json::from_value(input)?).to_json(),
             "sign_transaction" => self.sign_transaction(&serde_json::from_value(input)?).to_json(),
             "send_transaction" => self.send_transaction(&serde_json::from_value(input)?).to_json(),
             "broadcast_transaction" => self
@@ -144,6 +182,9 @@ impl Session for ElectrumSession {
             "get_unspent_outputs" => {
                 self.get_unspent_outputs(&serde_json::from_value(input)?).to_json()
             }
+            "set_unspent_outputs_status" => {
+                self.set_unspent_outputs_status(&serde_json::from_value(input)?).to_json()
+            }
             "load_store" => self.load_store(&serde_json::from_value(input)?).to_json(),
             "get_master_blinding_key" => self.get_master_blinding_key().to_json(),
             "set_master_blinding_key" => {
@@ -164,6 +205,46 @@ impl Session for ElectrumSession {
     }
 }
 
+/// The bitcoind or the Esplora server if set, otherwise the electrum one
+pub fn determine_backend_url(network: &NetworkParameters) -> Result<BackendUrl, Error> {
+    if let Some(rpc_url) = network.rpc_url.as_ref().filter(|u| !u.is_empty()) {
+        // the network parameters are logged, the credentials are set in rpc_credentials
+        if rpc_url.contains('@') {
+            return Err(Error::Generic("network rpc_url must not contain credentials".into()));
+        }
+        let wallet = network.rpc_wallet.as_deref().unwrap_or("gdk");
+        let credentials = network.rpc_credentials.clone();
+        return Ok(BackendUrl::Rpc(rpc_url.into(), wallet.into(), credentials));
+    }
+    match network.esplora_url.as_ref() {
+        Some(esplora_url) if !esplora_url.is_empty() => Ok(BackendUrl::Esplora(esplora_url.into())),
+        _ => {
+            let servers = determine_electrum_servers(network)?;
+            if servers.len() > 1 {
+                Ok(BackendUrl::ElectrumPool(ElectrumPool::new(servers)))
+            } else {
+                Ok(BackendUrl::Electrum(determine_electrum_url(network)?))
+            }
+        }
+    }
+}
+
+/// The primary electrum server followed by the ones to fail over to, without duplicates
+pub fn determine_electrum_servers(network: &NetworkParameters) -> Result<Vec<ElectrumUrl>, Error> {
+    let mut servers = vec![determine_electrum_url(network)?];
+    for server in network.electrum_servers.iter().flatten() {
+        servers.push(server.parse()?);
+    }
+    if network.electrum_fallback.unwrap_or(false) {
+        if let Some(net) = network.id().get_bitcoin_network() {
+            servers.extend(spv::default_servers(net, network.use_tor()));
+        }
+    }
+    let mut urls = HashSet::new();
+    servers.retain(|server| urls.insert(server.url().to_string()));
+    Ok(servers)
+}
+
 pub fn determine_electrum_url(network: &NetworkParameters) -> Result<ElectrumUrl, Error> {
     if let Some(true) = network.use_tor {
         if let Some(electrum_onion_url) = network.electrum_onion_url.as_ref() {
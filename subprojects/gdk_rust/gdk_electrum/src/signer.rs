@@ -0,0 +1,44 @@
+use gdk_common::model::{SignTransactionRequest, SignTransactionResponse};
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// A signer holding the keys outside of the session, e.g. a hardware wallet or a remote signer
+///
+/// The signer returns a signature for each input to sign, the session then builds the script
+/// sigs and witnesses.
+pub trait Signer: Send + Sync {
+    fn sign_transaction(
+        &self,
+        request: &SignTransactionRequest,
+    ) -> Result<SignTransactionResponse, Error>;
+}
+
+type JsonCallback = dyn Fn(Value) -> Result<Value, Error> + Send + Sync;
+
+/// A signer forwarding the requests as JSON to a callback, used to bridge signers implemented
+/// by the host application
+pub struct JsonSigner {
+    callback: Box<JsonCallback>,
+}
+
+impl JsonSigner {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(Value) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        JsonSigner {
+            callback: Box::new(callback),
+        }
+    }
+}
+
+impl Signer for JsonSigner {
+    fn sign_transaction(
+        &self,
+        request: &SignTransactionRequest,
+    ) -> Result<SignTransactionResponse, Error> {
+        let response = (self.callback)(serde_json::to_value(request)?)?;
+        Ok(serde_json::from_value(response)?)
+    }
+}
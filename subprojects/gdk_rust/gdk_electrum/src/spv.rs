@@ -14,7 +14,7 @@ use gdk_common::network::NetworkParameters;
 use crate::error::Error;
 use crate::headers::bitcoin::HeadersChain;
 use crate::interface::ElectrumUrl;
-use crate::session::determine_electrum_url;
+use crate::session::determine_backend_url;
 
 const INIT_CHUNK_SIZE: u32 = 5;
 const MAX_CHUNK_SIZE: u32 = 200;
@@ -51,6 +51,8 @@ pub struct CrossValidationInvalid {
     pub longest_work: Uint256,
     /// The extra work in the longest chain compared to our local one
     pub work_diff: Uint256,
+    /// The total work of the longest chain
+    pub longest_chainwork: Uint256,
     /// The server that we learned about the longest chain from
     pub origin_server: ElectrumUrl,
 }
@@ -186,6 +188,7 @@ pub fn spv_cross_validate(
                 longest_work: fork.total_fork_work,
                 common_ancestor: fork.common_ancestor,
                 work_diff: fork.total_fork_work,
+                longest_chainwork: chain.chainwork() + fork.total_fork_work,
                 origin_server: server_url.clone(),
             }));
         }
@@ -193,11 +196,11 @@ pub fn spv_cross_validate(
 
     let fork = get_fork_branch(chain, &client, remote_tip_height, None)?;
 
-    let our_work: Uint256 = (fork.common_ancestor + 1..=chain.height())
-        .fold(Uint256::zero(), |total, height| total + chain.get(height).unwrap().work());
+    let our_chainwork = chain.chainwork();
+    let fork_chainwork = chain.chainwork_at(fork.common_ancestor)? + fork.total_fork_work;
 
     // The remote is on a minority fork chain
-    if fork.total_fork_work <= our_work {
+    if fork_chainwork <= our_chainwork {
         Ok(CrossValidationResult::Valid)
     }
     // We are on the minority fork
@@ -207,7 +210,8 @@ pub fn spv_cross_validate(
             longest_height: fork.tip_height,
             longest_work: fork.total_fork_work,
             common_ancestor: fork.common_ancestor,
-            work_diff: fork.total_fork_work - our_work,
+            work_diff: fork_chainwork - our_chainwork,
+            longest_chainwork: fork_chainwork,
             origin_server: server_url.clone(),
         }))
     }
@@ -231,8 +235,8 @@ fn get_fork_branch(
     // Expects that all blocks involved in a reorg have a difficulty of at least 1/4 of our local tip.
     let sensible_target_threshold = chain.tip().target().mul_u32(4);
 
-    // Will not reorg past that
-    let height_limit = known_ancestor.unwrap_or(0);
+    // Will not reorg past that, nor past the start of our chain
+    let height_limit = known_ancestor.unwrap_or_else(|| chain.start().height);
 
     let mut total_fork_work = Uint256::zero();
     let mut curr_retarget: Option<(u32, BlockHeader, Option<BlockHeader>)> = None;
@@ -282,7 +286,7 @@ fn get_fork_branch(
                 CrossValidationError::ForkDepthExceeded
             );
 
-            // Reached the expected common ancestor height (or the genesis block) and we still don't have a match
+            // Reached the expected common ancestor height (or the start of our chain) and we still don't have a match
             ensure!(height > height_limit, CrossValidationError::KnownAncestorMismatch);
 
             // Verify the proof of work against the target specified by the header bits, and that its above
@@ -359,12 +363,9 @@ pub fn calc_difficulty_retarget(first: &BlockHeader, last: &BlockHeader) -> Uint
 
 impl CrossValidationInvalid {
     // Check whether the validation failure is still in effect, based
-    // on the proof-of-work added to our local chain since the forking point
+    // on the total proof-of-work of our local chain
     fn is_resolved(&self, chain: &HeadersChain) -> bool {
-        let local_work_since = (self.our_height + 1..=chain.height())
-            .fold(Uint256::zero(), |total, height| total + chain.get(height).unwrap().work());
-
-        local_work_since >= self.work_diff
+        chain.chainwork() >= self.longest_chainwork
     }
 }
 
@@ -402,6 +403,20 @@ fn parse_server_file(sl: &str) -> Vec<ElectrumUrl> {
     sl.lines().map(FromStr::from_str).collect::<Result<_, _>>().unwrap()
 }
 
+/// The builtin list of public electrum servers of `net`, only onion ones if `use_tor`
+///
+/// Note that if the user is using tor it might still want to use non-onion urls, but we are
+/// filtering them out.
+pub fn default_servers(net: bitcoin::Network, use_tor: bool) -> Vec<ElectrumUrl> {
+    let mut servers = match net {
+        bitcoin::Network::Bitcoin => SERVER_LIST_MAINNET.clone(),
+        bitcoin::Network::Testnet => SERVER_LIST_TESTNET.clone(),
+        bitcoin::Network::Regtest | bitcoin::Network::Signet => vec![],
+    };
+    servers.retain(|u| u.is_onion() == use_tor);
+    servers
+}
+
 pub fn get_cross_servers(network: &NetworkParameters) -> Result<Vec<ElectrumUrl>, Error> {
     let net = network.id().get_bitcoin_network().expect("spv cross-validation is bitcoin-only");
 
@@ -410,23 +425,11 @@ pub fn get_cross_servers(network: &NetworkParameters) -> Result<Vec<ElectrumUrl>
             // If the user sets the list, we assume all of them should be used
             servers.iter().map(String::as_ref).map(FromStr::from_str).collect()
         }
-        _ => {
-            let mut servers = match net {
-                bitcoin::Network::Bitcoin => SERVER_LIST_MAINNET.clone(),
-                bitcoin::Network::Testnet => SERVER_LIST_TESTNET.clone(),
-                bitcoin::Network::Regtest | bitcoin::Network::Signet => vec![],
-            };
-            // Filter the default cross validation servers list.
-            // Note that if the user is using tor it might still want to use non-onion urls,
-            // but we are filtering them out.
-            let use_tor = network.use_tor();
-            servers.retain(|u| u.is_onion() == use_tor);
-            Ok(servers)
-        }
+        _ => Ok(default_servers(net, network.use_tor())),
     }?;
 
     // Don't cross validation against the primary server
-    let primary_server = determine_electrum_url(network)?;
+    let primary_server = determine_backend_url(network)?;
     let primary_url = primary_server.url();
     Ok(servers.into_iter().filter(|s| s.url() != primary_url).collect())
 }
@@ -8,9 +8,12 @@ use bitcoin::Transaction;
 use elements::TxOutSecrets;
 use gdk_common::be::BETxidConvert;
 use gdk_common::be::{
-    BEBlockHash, BEBlockHeader, BEScript, BETransaction, BETransactionEntry, BETransactions, BETxid,
+    BEBlockHash, BEBlockHeader, BEOutPoint, BEScript, BETransaction, BETransactionEntry,
+    BETransactions, BETxid,
+};
+use gdk_common::model::{
+    AccountSettings, Bip329Label, FeeEstimate, LabelType, SPVVerifyTxResult, Settings,
 };
-use gdk_common::model::{AccountSettings, FeeEstimate, SPVVerifyTxResult, Settings};
 use gdk_common::store::{Decryptable, Encryptable, ToCipher};
 use gdk_common::wally::MasterBlindingKey;
 use gdk_common::NetworkId;
@@ -22,10 +25,15 @@ use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 use std::time::Instant;
 
-pub const BATCH_SIZE: u32 = 20;
+/// Default number of consecutive unused addresses scanned by sync and discovery
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// Maximum gap limit that can be set for an account
+pub const MAX_GAP_LIMIT: u32 = 1000;
 
 pub type Store = Arc<RwLock<StoreMeta>>;
 
@@ -77,7 +85,7 @@ pub struct RawAccountCache {
     /// contains all my tx and all prevouts
     pub all_txs: BETransactions,
 
-    /// contains all my script up to an empty batch of BATCHSIZE
+    /// contains all my script up to an empty batch of the account gap limit
     pub paths: HashMap<BEScript, DerivationPath>,
 
     /// inverse of `paths`
@@ -92,6 +100,10 @@ pub struct RawAccountCache {
     /// max used indexes for external derivation /0/* and internal derivation /1/* (change)
     pub indexes: Indexes,
 
+    /// max used indexes found by the last sync, `indexes` also include the handed out addresses
+    #[serde(default)]
+    pub last_used: Indexes,
+
     /// the xpub of the account
     pub xpub: ExtendedPubKey,
 
@@ -101,6 +113,20 @@ pub struct RawAccountCache {
     /// used to establish if an account has some transactions without waiting for the syncer to
     /// download transactions.
     pub bip44_discovered: bool,
+
+    /// electrum status hash and history of my scripts with history, the history of a script is
+    /// refetched only when its status hash changes
+    #[serde(default)]
+    pub histories: HashMap<BEScript, ScriptHistory>,
+}
+
+/// The history of a script as returned by the server, with its status hash
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ScriptHistory {
+    pub status: sha256::Hash,
+
+    /// txids with their heights (None if unconfirmed)
+    pub txs: Vec<(BETxid, Option<u32>)>,
 }
 
 /// RawStore contains data that are not extractable from xpub+blockchain
@@ -116,6 +142,25 @@ pub struct RawStore {
     // additional fields should always be appended at the end as an `Option` to retain db backwards compatibility
     /// account settings
     accounts_settings: Option<HashMap<u32, AccountSettings>>,
+
+    /// outpoints frozen by the user, not spent by the default coin selection
+    frozen_utxos: Option<HashSet<bitcoin::OutPoint>>,
+
+    /// address labels (address -> label)
+    address_labels: Option<HashMap<String, String>>,
+
+    /// output labels (outpoint -> label)
+    output_labels: Option<HashMap<bitcoin::OutPoint, String>>,
+}
+
+/// A BIP329 record validated and ready to be applied to the store
+enum LabelUpdate {
+    Tx(bitcoin::Txid, String),
+    Addr(String, String),
+    /// With the spendable flag, if any
+    Output(bitcoin::OutPoint, String, Option<bool>),
+    /// The name of the account with the given number
+    Xpub(u32, String),
 }
 
 pub struct StoreMeta {
@@ -262,6 +307,9 @@ impl StoreMeta {
         std::fs::create_dir_all(&path)?; // does nothing if path exists
 
         store.accounts_settings.get_or_insert_with(|| Default::default());
+        store.frozen_utxos.get_or_insert_with(Default::default);
+        store.address_labels.get_or_insert_with(Default::default);
+        store.output_labels.get_or_insert_with(Default::default);
 
         let store = StoreMeta {
             cache,
@@ -429,6 +477,186 @@ impl StoreMeta {
         self.store.memos.get(&txid.into_bitcoin())
     }
 
+    pub fn set_utxo_frozen(&mut self, outpoint: &BEOutPoint, frozen: bool) -> Result<(), Error> {
+        // Coerced into a bitcoin::OutPoint like memos txids
+        let outpoint = bitcoin::OutPoint::new(outpoint.txid().into_bitcoin(), outpoint.vout());
+        let frozen_utxos = self.store.frozen_utxos.as_mut().expect("set during initialization");
+        if frozen {
+            frozen_utxos.insert(outpoint);
+        } else {
+            frozen_utxos.remove(&outpoint);
+        }
+        self.flush_store()?;
+        Ok(())
+    }
+
+    /// Remove the frozen outpoints spent by the transactions of the wallet
+    ///
+    /// Outpoints of transactions not synced yet, such as the ones imported from labels, are kept
+    pub fn prune_frozen_utxos(&mut self) -> Result<(), Error> {
+        let spent: HashSet<_> = self
+            .cache
+            .accounts
+            .values()
+            .flat_map(|acc_store| {
+                acc_store
+                    .all_txs
+                    .iter()
+                    .filter(move |(txid, _)| acc_store.heights.contains_key(txid))
+                    .flat_map(|(_, txe)| txe.tx.previous_outputs())
+            })
+            .map(|o| bitcoin::OutPoint::new(o.txid().into_bitcoin(), o.vout()))
+            .collect();
+        let frozen_utxos = self.store.frozen_utxos.as_mut().expect("set during initialization");
+        let len = frozen_utxos.len();
+        frozen_utxos.retain(|outpoint| !spent.contains(outpoint));
+        if frozen_utxos.len() != len {
+            self.flush_store()?;
+        }
+        Ok(())
+    }
+
+    pub fn is_utxo_frozen(&self, outpoint: &BEOutPoint) -> bool {
+        let outpoint = bitcoin::OutPoint::new(outpoint.txid().into_bitcoin(), outpoint.vout());
+        self.store.frozen_utxos.as_ref().map_or(false, |f| f.contains(&outpoint))
+    }
+
+    pub fn get_address_label(&self, address: &str) -> Option<&String> {
+        self.store.address_labels.as_ref().and_then(|l| l.get(address))
+    }
+
+    pub fn get_output_label(&self, outpoint: &BEOutPoint) -> Option<&String> {
+        let outpoint = bitcoin::OutPoint::new(outpoint.txid().into_bitcoin(), outpoint.vout());
+        self.store.output_labels.as_ref().and_then(|l| l.get(&outpoint))
+    }
+
+    /// Export memos, address and output labels and account names as BIP329 records
+    pub fn export_labels(&self) -> Vec<Bip329Label> {
+        let record = |label_type, reference: String, label: Option<&String>| Bip329Label {
+            label_type,
+            reference,
+            label: label.cloned(),
+            origin: None,
+            spendable: None,
+        };
+        let mut txs: Vec<_> = self
+            .store
+            .memos
+            .iter()
+            .filter(|(_, memo)| !memo.is_empty())
+            .map(|(txid, memo)| record(LabelType::Tx, txid.to_string(), Some(memo)))
+            .collect();
+        let address_labels = self.store.address_labels.as_ref().expect("set during initialization");
+        let mut addresses: Vec<_> = address_labels
+            .iter()
+            .map(|(address, label)| record(LabelType::Addr, address.clone(), Some(label)))
+            .collect();
+        let output_labels = self.store.output_labels.as_ref().expect("set during initialization");
+        let frozen_utxos = self.store.frozen_utxos.as_ref().expect("set during initialization");
+        let mut outputs: Vec<_> = output_labels
+            .keys()
+            .chain(frozen_utxos.iter().filter(|o| !output_labels.contains_key(o)))
+            .map(|outpoint| {
+                let mut output =
+                    record(LabelType::Output, outpoint.to_string(), output_labels.get(outpoint));
+                if frozen_utxos.contains(outpoint) {
+                    output.spendable = Some(false);
+                }
+                output
+            })
+            .collect();
+        let mut xpubs: Vec<_> = self
+            .get_accounts_settings()
+            .iter()
+            .filter(|(_, settings)| !settings.name.is_empty())
+            .filter_map(|(account_num, settings)| {
+                let xpub = self.cache.accounts.get(account_num)?.xpub;
+                Some(record(LabelType::Xpub, xpub.to_string(), Some(&settings.name)))
+            })
+            .collect();
+
+        let mut labels = vec![];
+        for records in [&mut txs, &mut addresses, &mut outputs, &mut xpubs] {
+            records.sort_by(|a, b| a.reference.cmp(&b.reference));
+            labels.append(records);
+        }
+        labels
+    }
+
+    /// Apply BIP329 records to the store, returning the number of records applied
+    ///
+    /// Records of unsupported types or referring to unknown accounts are skipped. Nothing is
+    /// applied if any record is invalid.
+    pub fn import_labels(&mut self, labels: &[Bip329Label]) -> Result<usize, Error> {
+        let mut updates = vec![];
+        for record in labels {
+            let label = record.label.clone().unwrap_or_default();
+            let update = match record.label_type {
+                LabelType::Tx => {
+                    LabelUpdate::Tx(bitcoin::Txid::from_str(&record.reference)?, label)
+                }
+                LabelType::Addr => LabelUpdate::Addr(record.reference.clone(), label),
+                LabelType::Output => {
+                    let outpoint = bitcoin::OutPoint::from_str(&record.reference)
+                        .map_err(|e| Error::Generic(format!("invalid output ref: {}", e)))?;
+                    LabelUpdate::Output(outpoint, label, record.spendable)
+                }
+                LabelType::Xpub => {
+                    let xpub = ExtendedPubKey::from_str(&record.reference)?;
+                    let account_num = self
+                        .cache
+                        .accounts
+                        .iter()
+                        .find(|(_, account)| xpubs_equivalent(&account.xpub, &xpub).is_ok())
+                        .map(|(account_num, _)| *account_num);
+                    match account_num {
+                        Some(n) => LabelUpdate::Xpub(n, label),
+                        None => continue,
+                    }
+                }
+                LabelType::Pubkey | LabelType::Input | LabelType::Unknown => continue,
+            };
+            updates.push(update);
+        }
+
+        let imported = updates.len();
+        for update in updates {
+            match update {
+                LabelUpdate::Tx(txid, label) => {
+                    self.store.memos.insert(txid, label);
+                }
+                LabelUpdate::Addr(address, label) => {
+                    let address_labels = self.store.address_labels.as_mut().unwrap();
+                    if label.is_empty() {
+                        address_labels.remove(&address);
+                    } else {
+                        address_labels.insert(address, label);
+                    }
+                }
+                LabelUpdate::Output(outpoint, label, spendable) => {
+                    let output_labels = self.store.output_labels.as_mut().unwrap();
+                    if label.is_empty() {
+                        output_labels.remove(&outpoint);
+                    } else {
+                        output_labels.insert(outpoint, label);
+                    }
+                    let frozen_utxos = self.store.frozen_utxos.as_mut().unwrap();
+                    match spendable {
+                        Some(false) => frozen_utxos.insert(outpoint),
+                        Some(true) => frozen_utxos.remove(&outpoint),
+                        None => false,
+                    };
+                }
+                LabelUpdate::Xpub(account_num, label) => {
+                    let accounts_settings = self.store.accounts_settings.as_mut().unwrap();
+                    accounts_settings.entry(account_num).or_default().name = label;
+                }
+            }
+        }
+        self.flush_store()?;
+        Ok(imported)
+    }
+
     pub fn insert_settings(&mut self, settings: Option<Settings>) -> Result<(), Error> {
         self.store.settings = settings;
         self.flush_store()?;
@@ -508,8 +736,10 @@ impl RawAccountCache {
             heights: Default::default(),
             unblinded: Default::default(),
             indexes: Default::default(),
+            last_used: Default::default(),
             xpub,
             bip44_discovered,
+            histories: Default::default(),
         }
     }
     pub fn get_bitcoin_tx(&self, txid: &bitcoin::Txid) -> Result<Transaction, Error> {
@@ -537,7 +767,6 @@ mod tests {
     use bitcoin::util::bip32::ExtendedPubKey;
     use bitcoin::Network;
     use gdk_common::{be::BETxid, NetworkId};
-    use std::str::FromStr;
     use tempfile::TempDir;
 
     #[test]
@@ -559,12 +788,111 @@ mod tests {
             store.make_account(0, xpub, true).unwrap(); // The xpub here is incorrect, but that's irrelevant for the sake of the test
             store.account_cache_mut(0).unwrap().heights.insert(txid, Some(1));
             store.store.memos.insert(*txid_btc, "memo".to_string());
+            store.set_utxo_frozen(&BEOutPoint::new(txid, 1), true).unwrap();
         }
 
         let store = StoreMeta::new(&dir, &xpub, id).unwrap();
 
         assert_eq!(store.account_cache(0).unwrap().heights.get(&txid), Some(&Some(1)));
         assert_eq!(store.store.memos.get(txid_btc), Some(&"memo".to_string()));
+        assert!(store.is_utxo_frozen(&BEOutPoint::new(txid, 1)));
+        assert!(!store.is_utxo_frozen(&BEOutPoint::new(txid, 0)));
+    }
+
+    #[test]
+    fn test_prune_frozen_utxos() {
+        let id = NetworkId::Bitcoin(Network::Testnet);
+        let mut dir = TempDir::new().unwrap().into_path();
+        dir.push(Kind::Store.to_string());
+        let xpub = ExtendedPubKey::from_str("tpubD97UxEEcrMpkE8yG3NQveraWveHzTAJx3KwPsUycx9ABfxRjMtiwfm6BtrY5yhF9yF2eyMg2hyDtGDYXx6gVLBox1m2Mq4u8zB2NXFhUZmm").unwrap();
+        let txid = BETxid::from_hex(
+            "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            id,
+        )
+        .unwrap();
+        let spending_tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::new(*txid.ref_bitcoin().unwrap(), 1),
+                ..Default::default()
+            }],
+            output: vec![],
+        };
+        let spending_txid = BETxid::Bitcoin(spending_tx.txid());
+
+        {
+            let mut store = StoreMeta::new(&dir, &xpub, id).unwrap();
+            store.make_account(0, xpub, true).unwrap();
+            for vout in 0..3 {
+                store.set_utxo_frozen(&BEOutPoint::new(txid, vout), true).unwrap();
+            }
+            let acc_store = store.account_cache_mut(0).unwrap();
+            acc_store
+                .all_txs
+                .insert(spending_txid, BETransaction::Bitcoin(spending_tx.clone()).into());
+
+            // a transaction replaced or dropped from the mempool doesn't spend
+            store.prune_frozen_utxos().unwrap();
+            assert!(store.is_utxo_frozen(&BEOutPoint::new(txid, 1)));
+
+            store.account_cache_mut(0).unwrap().heights.insert(spending_txid, None);
+            store.prune_frozen_utxos().unwrap();
+        }
+
+        let store = StoreMeta::new(&dir, &xpub, id).unwrap();
+        assert!(store.is_utxo_frozen(&BEOutPoint::new(txid, 0)));
+        assert!(!store.is_utxo_frozen(&BEOutPoint::new(txid, 1)));
+        assert!(store.is_utxo_frozen(&BEOutPoint::new(txid, 2)));
+    }
+
+    #[test]
+    fn test_labels_roundtrip() {
+        let id = NetworkId::Bitcoin(Network::Testnet);
+        let mut dir = TempDir::new().unwrap().into_path();
+        dir.push(Kind::Store.to_string());
+        let xpub = ExtendedPubKey::from_str("tpubD97UxEEcrMpkE8yG3NQveraWveHzTAJx3KwPsUycx9ABfxRjMtiwfm6BtrY5yhF9yF2eyMg2hyDtGDYXx6gVLBox1m2Mq4u8zB2NXFhUZmm").unwrap();
+        let txid = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+        let jsonl = format!(
+            r#"{{"type":"tx","ref":"{txid}","label":"memo"}}
+{{"type":"addr","ref":"tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx","label":"address"}}
+{{"type":"output","ref":"{txid}:0","label":"output"}}
+{{"type":"output","ref":"{txid}:1","spendable":false}}
+{{"type":"xpub","ref":"{xpub}","label":"account"}}
+{{"type":"pubkey","ref":"0283409659355b6d1cc3c32decd5d561abaac86c37a353b52895a5e6c196d6f448","label":"pubkey"}}
+"#
+        );
+        let labels = Bip329Label::from_jsonl(&jsonl).unwrap();
+
+        {
+            let mut store = StoreMeta::new(&dir, &xpub, id).unwrap();
+            store.make_account(0, xpub, true).unwrap();
+
+            // an invalid record prevents the import of the valid ones before it
+            let mut invalid = labels.clone();
+            invalid[2].reference = "invalid".into();
+            assert!(store.import_labels(&invalid).is_err());
+            assert!(store.export_labels().is_empty());
+
+            assert_eq!(store.import_labels(&labels).unwrap(), 5);
+        }
+
+        let store = StoreMeta::new(&dir, &xpub, id).unwrap();
+        let betxid = BETxid::from_hex(txid, id).unwrap();
+        assert_eq!(store.get_memo(&betxid), Some(&"memo".to_string()));
+        assert_eq!(store.get_account_name(0), Some(&"account".to_string()));
+        assert!(store.is_utxo_frozen(&BEOutPoint::new(betxid, 1)));
+        let address = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        assert_eq!(store.get_address_label(address), Some(&"address".to_string()));
+        assert_eq!(
+            store.get_output_label(&BEOutPoint::new(betxid, 0)),
+            Some(&"output".to_string())
+        );
+        assert_eq!(store.get_output_label(&BEOutPoint::new(betxid, 1)), None);
+
+        let exported = store.export_labels();
+        let expected: Vec<_> = labels.into_iter().take(5).collect();
+        assert_eq!(exported, expected);
     }
 
     #[test]
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::unblinded;
+use elements::confidential::{AssetBlindingFactor, Nonce, ValueBlindingFactor};
+use elements::encode::{deserialize, serialize};
+use elements::pset::raw::ProprietaryKey;
+use elements::pset::{self, PartiallySignedTransaction, PsbtSighashType};
+use elements::secp256k1_zkp::{Generator, PedersenCommitment, RangeProof, SurjectionProof, Tweak};
+use elements::{
+    AssetId, BlindAssetProofs, BlindValueProofs, EcdsaSigHashType, TxOut, TxOutSecrets,
+};
+
+/// The prefix of the proprietary PSET fields added by gdk
+const PSET_PREFIX: &[u8] = b"gdk";
+
+/// The input field with the unblinded values of the spent output
+const PSET_IN_TXOUT_SECRETS: u8 = 0x00;
+
+/// The output field with the asset blinding factor of the output requested by a proposal
+const PSET_OUT_ASSET_BF: u8 = 0x01;
+
+/// The sighash of the input of a swap proposal, committing only to the output with the same
+/// index so that the counterparty can add its own inputs and outputs
+pub fn proposal_sighash() -> PsbtSighashType {
+    EcdsaSigHashType::SinglePlusAnyoneCanPay.into()
+}
+
+fn secrets_key() -> ProprietaryKey {
+    ProprietaryKey {
+        prefix: PSET_PREFIX.to_vec(),
+        subtype: PSET_IN_TXOUT_SECRETS,
+        key: vec![],
+    }
+}
+
+fn asset_bf_key() -> ProprietaryKey {
+    ProprietaryKey {
+        prefix: PSET_PREFIX.to_vec(),
+        subtype: PSET_OUT_ASSET_BF,
+        key: vec![],
+    }
+}
+
+/// Reveal in `input` the unblinded values of the output it spends
+///
+/// The counterparty needs them to know what is offered and to prove the assets of its outputs.
+pub fn set_input_secrets(input: &mut pset::Input, secrets: &TxOutSecrets) {
+    let mut value = serialize(&secrets.asset);
+    value.extend(secrets.asset_bf.into_inner().as_ref());
+    value.extend(&secrets.value.to_le_bytes());
+    value.extend(secrets.value_bf.into_inner().as_ref());
+    input.proprietary.insert(secrets_key(), value);
+}
+
+/// The unblinded values of the output spent by `input`, checked against its `witness_utxo`
+pub fn input_secrets(input: &pset::Input) -> Result<TxOutSecrets, Error> {
+    let value = input
+        .proprietary
+        .get(&secrets_key())
+        .ok_or_else(|| Error::InvalidSwap("missing the unblinded values of the input".into()))?;
+    if value.len() != 104 {
+        return Err(Error::InvalidSwap("invalid unblinded values of the input".into()));
+    }
+    let asset: AssetId = deserialize(&value[..32])?;
+    let asset_bf = AssetBlindingFactor::from_slice(&value[32..64])?;
+    let mut satoshi = [0u8; 8];
+    satoshi.copy_from_slice(&value[64..72]);
+    let satoshi = u64::from_le_bytes(satoshi);
+    let value_bf = ValueBlindingFactor::from_slice(&value[72..])?;
+    let secrets = TxOutSecrets::new(asset, asset_bf, satoshi, value_bf);
+
+    let utxo = input
+        .witness_utxo
+        .as_ref()
+        .ok_or_else(|| Error::InvalidSwap("missing the output spent by the input".into()))?;
+    if !unblinded::secrets_match(utxo, &secrets) {
+        return Err(Error::InvalidSwap("the unblinded values do not match the input".into()));
+    }
+    Ok(secrets)
+}
+
+/// Blind the output requested by the proposal `pset`, which spends the utxo with `secrets`
+///
+/// The output can't have a surjection proof yet, since the asset it receives is not among the
+/// inputs, so its asset blinding factor is revealed to let the counterparty prove it.
+pub fn blind_proposal(
+    pset: &mut PartiallySignedTransaction,
+    secrets: &TxOutSecrets,
+) -> Result<(), Error> {
+    let mut rng = rand::thread_rng();
+    let output = &mut pset.outputs_mut()[0];
+    let err = || Error::InvalidSwap("the requested output must be explicit".into());
+    let asset = output.asset.ok_or_else(err)?;
+    let satoshi = output.amount.ok_or_else(err)?;
+    let blinding_key = output.blinding_key.ok_or_else(err)?;
+
+    let abf = AssetBlindingFactor::new(&mut rng);
+    let vbf = ValueBlindingFactor::new(&mut rng);
+    let asset_comm = Generator::new_blinded(&crate::EC, asset.into_tag(), abf.into_inner());
+    let amount_comm = PedersenCommitment::new(&crate::EC, satoshi, vbf.into_inner(), asset_comm);
+    let (nonce, shared_secret) = Nonce::new_confidential(&mut rng, &crate::EC, &blinding_key.inner);
+    let mut message = [0u8; 64];
+    message[..32].copy_from_slice(asset.into_tag().as_ref());
+    message[32..].copy_from_slice(abf.into_inner().as_ref());
+    let rangeproof = RangeProof::new(
+        &crate::EC,
+        TxOut::RANGEPROOF_MIN_VALUE,
+        amount_comm,
+        satoshi,
+        vbf.into_inner(),
+        &message,
+        output.script_pubkey.as_bytes(),
+        shared_secret,
+        TxOut::RANGEPROOF_EXP_SHIFT,
+        TxOut::RANGEPROOF_MIN_PRIV_BITS,
+        asset_comm,
+    )?;
+    output.asset_comm = Some(asset_comm);
+    output.amount_comm = Some(amount_comm);
+    output.ecdh_pubkey = nonce.commitment().map(bitcoin::PublicKey::new);
+    output.value_rangeproof = Some(Box::new(rangeproof));
+    output.blind_asset_proof =
+        Some(Box::new(SurjectionProof::blind_asset_proof(&mut rng, &crate::EC, asset, abf)?));
+    output.blind_value_proof = Some(Box::new(RangeProof::blind_value_proof(
+        &mut rng,
+        &crate::EC,
+        satoshi,
+        amount_comm,
+        asset_comm,
+        vbf,
+    )?));
+    output.proprietary.insert(asset_bf_key(), abf.into_inner().as_ref().to_vec());
+
+    // as in blind_non_last, the scalar balancing the value blinding factors of the proposal
+    let mut scalar = ValueBlindingFactor::last(
+        &crate::EC,
+        satoshi,
+        abf,
+        &[(secrets.value, secrets.asset_bf, secrets.value_bf)],
+        &[],
+    );
+    scalar += -vbf;
+    pset.global.scalars.push(scalar.into_inner());
+    Ok(())
+}
+
+/// The asset, amount and asset blinding factor of the output requested by a proposal, checked
+/// against its commitments
+fn requested_output(output: &pset::Output) -> Result<(AssetId, u64, AssetBlindingFactor), Error> {
+    let err = || Error::InvalidSwap("the requested output is not blinded".into());
+    let asset = output.asset.ok_or_else(err)?;
+    let satoshi = output.amount.ok_or_else(err)?;
+    let asset_comm = output.asset_comm.ok_or_else(err)?;
+    let amount_comm = output.amount_comm.ok_or_else(err)?;
+    let asset_bf = output.proprietary.get(&asset_bf_key()).ok_or_else(err)?;
+    let asset_bf = AssetBlindingFactor::from_slice(asset_bf)?;
+    let value_proof = output.blind_value_proof.as_ref().ok_or_else(err)?;
+    if Generator::new_blinded(&crate::EC, asset.into_tag(), asset_bf.into_inner()) != asset_comm
+        || !value_proof.blind_value_proof_verify(&crate::EC, satoshi, asset_comm, amount_comm)
+    {
+        return Err(Error::InvalidSwap("the requested output does not match its proofs".into()));
+    }
+    Ok((asset, satoshi, asset_bf))
+}
+
+/// Check that `pset` is a swap proposal: a signed input offering a utxo and the blinded output
+/// requested in exchange, returning what is offered and what is requested
+pub fn check_proposal(
+    pset: &PartiallySignedTransaction,
+) -> Result<(TxOutSecrets, (AssetId, u64)), Error> {
+    if pset.inputs().len() != 1 || pset.outputs().len() != 1 {
+        return Err(Error::InvalidSwap("a proposal must have one input and one output".into()));
+    }
+    let input = &pset.inputs()[0];
+    if input.sighash_type != Some(proposal_sighash()) || input.final_script_witness.is_none() {
+        return Err(Error::InvalidSwap("the input is not signed with SINGLE|ANYONECANPAY".into()));
+    }
+    if pset.global.scalars.is_empty() {
+        return Err(Error::InvalidSwap("missing the scalar balancing the proposal".into()));
+    }
+    let offered = input_secrets(input)?;
+    let (asset, satoshi, _) = requested_output(&pset.outputs()[0])?;
+    if offered.asset == asset {
+        return Err(Error::InvalidSwap("the proposal swaps an asset with itself".into()));
+    }
+    Ok((offered, (asset, satoshi)))
+}
+
+/// Add the surjection proof of the output requested by the proposal, once all the inputs of
+/// `pset` are known
+pub fn prove_requested_output(
+    pset: &mut PartiallySignedTransaction,
+    inp_txout_sec: &HashMap<usize, TxOutSecrets>,
+) -> Result<(), Error> {
+    let (asset, _, asset_bf) = requested_output(&pset.outputs()[0])?;
+    let inputs = pset
+        .surjection_inputs(inp_txout_sec)?
+        .iter()
+        .map(|i| i.surjection_target(&crate::EC))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::InvalidSwap(e.to_string()))?;
+    let proof = SurjectionProof::new(
+        &crate::EC,
+        &mut rand::thread_rng(),
+        asset.into_tag(),
+        asset_bf.into_inner(),
+        &inputs,
+    )?;
+    pset.outputs_mut()[0].asset_surjection_proof = Some(Box::new(proof));
+    Ok(())
+}
+
+/// The scalar removing from the value blinding factors the contribution of the input with
+/// `secrets`, which is already accounted for in the scalar of the proposal
+pub fn input_offset(secrets: &TxOutSecrets) -> Tweak {
+    let contribution = ValueBlindingFactor::last(
+        &crate::EC,
+        0,
+        AssetBlindingFactor::zero(),
+        &[(secrets.value, secrets.asset_bf, secrets.value_bf)],
+        &[],
+    );
+    (-contribution).into_inner()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use elements::confidential::{Asset, Value};
+    use elements::{OutPoint, Script};
+    use std::str::FromStr;
+
+    fn confidential_utxo(secrets: &TxOutSecrets) -> TxOut {
+        let asset = Asset::new_confidential(&crate::EC, secrets.asset, secrets.asset_bf);
+        let generator = Generator::new_blinded(
+            &crate::EC,
+            secrets.asset.into_tag(),
+            secrets.asset_bf.into_inner(),
+        );
+        TxOut {
+            asset,
+            value: Value::new_confidential(&crate::EC, secrets.value, generator, secrets.value_bf),
+            script_pubkey: Script::from_str("0014d0c4a3ef09e997b6e99e397e518fe3e41a118ca1")
+                .unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn input_secrets_roundtrip() {
+        let mut rng = rand::thread_rng();
+        let secrets = TxOutSecrets::new(
+            AssetId::from_slice(&[1u8; 32]).unwrap(),
+            AssetBlindingFactor::new(&mut rng),
+            10_000,
+            ValueBlindingFactor::new(&mut rng),
+        );
+        let mut input = pset::Input::from_prevout(OutPoint::default());
+        set_input_secrets(&mut input, &secrets);
+        // without the spent output the values cannot be checked
+        assert!(input_secrets(&input).is_err());
+
+        input.witness_utxo = Some(confidential_utxo(&secrets));
+        assert_eq!(input_secrets(&input).unwrap(), secrets);
+
+        let mut other = secrets;
+        other.value = 20_000;
+        set_input_secrets(&mut input, &other);
+        assert!(input_secrets(&input).is_err());
+    }
+
+    #[test]
+    fn swap_blinding() {
+        let mut rng = rand::thread_rng();
+        let asset_a = AssetId::from_slice(&[1u8; 32]).unwrap();
+        let asset_b = AssetId::from_slice(&[2u8; 32]).unwrap();
+        let blinding_key =
+            bitcoin::PublicKey::new(elements::secp256k1_zkp::PublicKey::from_secret_key(
+                &crate::EC,
+                &elements::secp256k1_zkp::SecretKey::from_slice(&[3u8; 32]).unwrap(),
+            ));
+        let script = Script::from_str("0014d0c4a3ef09e997b6e99e397e518fe3e41a118ca1").unwrap();
+        let new_secrets = |asset, value| {
+            TxOutSecrets::new(
+                asset,
+                AssetBlindingFactor::new(&mut rand::thread_rng()),
+                value,
+                ValueBlindingFactor::new(&mut rand::thread_rng()),
+            )
+        };
+
+        // the maker offers 1000 of asset a for 500 of asset b
+        let maker_secrets = new_secrets(asset_a, 1000);
+        let mut proposal = PartiallySignedTransaction::new_v2();
+        let mut input = pset::Input::from_prevout(OutPoint::default());
+        input.witness_utxo = Some(confidential_utxo(&maker_secrets));
+        input.sighash_type = Some(proposal_sighash());
+        input.final_script_witness = Some(vec![vec![]]);
+        set_input_secrets(&mut input, &maker_secrets);
+        proposal.add_input(input);
+        let mut output =
+            pset::Output::new_explicit(script.clone(), 500, asset_b, Some(blinding_key));
+        output.blinder_index = Some(0);
+        proposal.add_output(output);
+        blind_proposal(&mut proposal, &maker_secrets).unwrap();
+
+        let (offered, requested) = check_proposal(&proposal).unwrap();
+        assert_eq!(offered, maker_secrets);
+        assert_eq!(requested, (asset_b, 500));
+        let signed_output = proposal.outputs()[0].to_txout();
+
+        // the taker pays 500 of asset b and receives 1000 of asset a
+        let taker_secrets = new_secrets(asset_b, 500);
+        let mut pset = proposal;
+        let mut input = pset::Input::from_prevout(OutPoint::new(OutPoint::default().txid, 1));
+        input.witness_utxo = Some(confidential_utxo(&taker_secrets));
+        pset.add_input(input);
+        let mut output = pset::Output::new_explicit(script, 1000, asset_a, Some(blinding_key));
+        output.blinder_index = Some(1);
+        pset.add_output(output);
+        pset.outputs_mut()[0].blinder_index = None;
+        pset.global.scalars.push(input_offset(&offered));
+        let mut inp_txout_sec = HashMap::new();
+        inp_txout_sec.insert(0, offered);
+        inp_txout_sec.insert(1, taker_secrets);
+        pset.blind_last(&mut rng, &crate::EC, &inp_txout_sec).unwrap();
+        pset.outputs_mut()[0].blinder_index = Some(0);
+        prove_requested_output(&mut pset, &inp_txout_sec).unwrap();
+
+        let tx = pset.extract_tx().unwrap();
+        // the output committed by the maker signature is unchanged
+        assert_eq!(tx.output[0].asset, signed_output.asset);
+        assert_eq!(tx.output[0].value, signed_output.value);
+        assert_eq!(tx.output[0].nonce, signed_output.nonce);
+        let spent_utxos: Vec<_> =
+            pset.inputs().iter().map(|i| i.witness_utxo.clone().unwrap()).collect();
+        tx.verify_tx_amt_proofs(&crate::EC, &spent_utxos).unwrap();
+    }
+}
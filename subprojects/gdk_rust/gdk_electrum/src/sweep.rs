@@ -0,0 +1,177 @@
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::{PrivateKey, PublicKey, Script};
+use electrum_client::ListUnspentRes;
+
+use gdk_common::be::BEScript;
+use gdk_common::model::UnspentOutput;
+use gdk_common::scripts::{p2pkh_script, ScriptType};
+use gdk_common::wally::bip38_to_private_key;
+
+use crate::error::Error;
+
+/// Decode a private key in WIF, decrypting it with `passphrase` if it's BIP38 encrypted
+pub fn decode_private_key(
+    key: &str,
+    passphrase: Option<&str>,
+    network: bitcoin::Network,
+) -> Result<PrivateKey, Error> {
+    if key.len() == 58 && key.starts_with("6P") {
+        let passphrase = passphrase.ok_or_else(|| {
+            Error::InvalidSweepRequest("a passphrase is needed for BIP38 keys".into())
+        })?;
+        let (inner, compressed) = bip38_to_private_key(key, passphrase, network)
+            .ok_or_else(|| Error::InvalidSweepRequest("invalid BIP38 key or passphrase".into()))?;
+        return Ok(PrivateKey {
+            compressed,
+            network,
+            inner,
+        });
+    }
+    let private_key = PrivateKey::from_wif(key)
+        .map_err(|_| Error::InvalidSweepRequest("invalid private key".into()))?;
+    // regtest keys are encoded as testnet ones
+    if (private_key.network == bitcoin::Network::Bitcoin) != (network == bitcoin::Network::Bitcoin)
+    {
+        return Err(Error::InvalidSweepRequest("private key for another network".into()));
+    }
+    Ok(private_key)
+}
+
+/// The scripts locking the funds of `key`, segwit scripts only for compressed keys
+pub fn sweep_scripts(key: &PrivateKey) -> Vec<(ScriptType, Script)> {
+    let public_key = key.public_key(&crate::EC);
+    let p2pkh = (ScriptType::P2pkh, Script::new_p2pkh(&public_key.pubkey_hash()));
+    match public_key.wpubkey_hash() {
+        Some(hash) => {
+            let p2wpkh = Script::new_v0_p2wpkh(&hash);
+            let p2shwpkh = Script::new_p2sh(&p2wpkh.script_hash());
+            vec![p2pkh, (ScriptType::P2wpkh, p2wpkh), (ScriptType::P2shP2wpkh, p2shwpkh)]
+        }
+        None => vec![p2pkh],
+    }
+}
+
+/// The utxo of `key` locked in `script_pubkey`, as listed by the server
+pub fn sweep_utxo(
+    key: &PrivateKey,
+    script_type: ScriptType,
+    script_pubkey: &Script,
+    unspent: &ListUnspentRes,
+    subaccount: u32,
+) -> UnspentOutput {
+    let public_key = key.public_key(&crate::EC);
+    UnspentOutput {
+        address_type: script_type.to_string(),
+        block_height: unspent.height as u32,
+        pt_idx: unspent.tx_pos as u32,
+        satoshi: unspent.value,
+        subaccount,
+        txhash: unspent.tx_hash.to_string(),
+        scriptpubkey: BEScript::Bitcoin(script_pubkey.clone()),
+        script_code: p2pkh_script(&public_key).to_hex(),
+        public_key: public_key.to_string(),
+        ..Default::default()
+    }
+}
+
+/// The key among `keys` spending `utxo` with the script type and the output it spends
+pub fn sweep_key<'a>(
+    keys: &'a [PrivateKey],
+    utxo: &UnspentOutput,
+) -> Option<(&'a PrivateKey, ScriptType, bitcoin::TxOut)> {
+    keys.iter().filter(|k| k.public_key(&crate::EC).to_string() == utxo.public_key).find_map(
+        |key| {
+            sweep_scripts(key).into_iter().find(|(t, _)| t.to_string() == utxo.address_type).map(
+                |(script_type, script_pubkey)| {
+                    let txout = bitcoin::TxOut {
+                        value: utxo.satoshi,
+                        script_pubkey,
+                    };
+                    (key, script_type, txout)
+                },
+            )
+        },
+    )
+}
+
+/// The fee of the sweep transaction `tx` once signed, `utxos` are the spent outputs
+pub fn estimated_fee(tx: &bitcoin::Transaction, utxos: &[UnspentOutput], fee_rate: f64) -> u64 {
+    let mut tx = tx.clone();
+    let mut uncompressed = 0;
+    for (input, utxo) in tx.input.iter_mut().zip(utxos.iter()) {
+        let script_type = ScriptType::types()
+            .iter()
+            .find(|t| t.to_string() == utxo.address_type)
+            .unwrap_or(&ScriptType::P2pkh);
+        input.witness = script_type.mock_witness();
+        input.script_sig = script_type.mock_script_sig().into();
+        if let Ok(public_key) = utxo.public_key.parse::<PublicKey>() {
+            if !public_key.compressed {
+                uncompressed += 1;
+            }
+        }
+    }
+    // uncompressed keys are 32 bytes longer than the mocked ones, and are in the script sig
+    let weight = tx.weight() + uncompressed * 32 * 4;
+    (weight as f64 / 4.0 * fee_rate * 1.02) as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+
+    const WIF: &str = "cVt4o7BGAig1UXywgGSmARhxMdzP5qvQsxKkSsc1XEkw3tDTQFpy";
+
+    #[test]
+    fn decode_keys() {
+        let key = decode_private_key(WIF, None, bitcoin::Network::Regtest).unwrap();
+        assert!(key.compressed);
+        assert!(decode_private_key(WIF, None, bitcoin::Network::Bitcoin).is_err());
+        assert!(decode_private_key("invalid", None, bitcoin::Network::Regtest).is_err());
+
+        // BIP38 keys need the passphrase
+        let bip38 = "6PYNKZ1EAgYgmQfmNVamxyXVWHzK5s6DGhwP4J5o44cvXdoY7sRzhtpUeo";
+        assert!(decode_private_key(bip38, None, bitcoin::Network::Bitcoin).is_err());
+        let key = decode_private_key(bip38, Some("TestingOneTwoThree"), bitcoin::Network::Bitcoin)
+            .unwrap();
+        assert_eq!(key.to_wif(), "L44B5gGEpqEDRS9vVPz7QT35jcBG2r3CZwSwQ4fCewXAhAhqGVpP");
+    }
+
+    #[test]
+    fn scripts_and_keys() {
+        let key = decode_private_key(WIF, None, bitcoin::Network::Regtest).unwrap();
+        let scripts = sweep_scripts(&key);
+        assert_eq!(scripts.len(), 3);
+        let public_key = key.public_key(&crate::EC);
+        for (script_type, script_pubkey) in scripts.iter() {
+            let address = match script_type {
+                ScriptType::P2pkh => bitcoin::Address::p2pkh(&public_key, key.network),
+                ScriptType::P2wpkh => bitcoin::Address::p2wpkh(&public_key, key.network).unwrap(),
+                _ => bitcoin::Address::p2shwpkh(&public_key, key.network).unwrap(),
+            };
+            assert_eq!(&address.script_pubkey(), script_pubkey);
+
+            let unspent = ListUnspentRes {
+                height: 100,
+                tx_hash: bitcoin::Txid::from_hex(&"11".repeat(32)).unwrap(),
+                tx_pos: 1,
+                value: 10_000,
+            };
+            let utxo = sweep_utxo(&key, *script_type, script_pubkey, &unspent, 0);
+            let keys = [key];
+            let (found, found_type, txout) = sweep_key(&keys, &utxo).unwrap();
+            assert_eq!(found, &key);
+            assert_eq!(found_type, *script_type);
+            assert_eq!(&txout.script_pubkey, script_pubkey);
+            assert_eq!(txout.value, 10_000);
+        }
+
+        // uncompressed keys are used only with P2PKH
+        let uncompressed = PrivateKey {
+            compressed: false,
+            ..key
+        };
+        assert_eq!(sweep_scripts(&uncompressed).len(), 1);
+    }
+}
@@ -0,0 +1,140 @@
+use elements::confidential::{Asset, AssetBlindingFactor, Value, ValueBlindingFactor};
+use elements::hashes::hex::ToHex;
+use elements::secp256k1_zkp::Generator;
+use elements::{AssetId, TxOut, TxOutSecrets};
+use std::str::FromStr;
+
+use crate::error::Error;
+
+/// The key of the explorer URL fragment with the unblinded values of some outputs
+const BLINDED_FRAGMENT_KEY: &str = "blinded=";
+
+/// Whether `secrets` open the asset and value commitments of `txout`
+pub fn secrets_match(txout: &TxOut, secrets: &TxOutSecrets) -> bool {
+    let asset_matches = match txout.asset {
+        Asset::Explicit(asset) => asset == secrets.asset,
+        Asset::Confidential(_) => {
+            txout.asset == Asset::new_confidential(&crate::EC, secrets.asset, secrets.asset_bf)
+        }
+        Asset::Null => false,
+    };
+    let value_matches = match txout.value {
+        Value::Explicit(value) => value == secrets.value,
+        Value::Confidential(_) => {
+            let generator = Generator::new_blinded(
+                &crate::EC,
+                secrets.asset.into_tag(),
+                secrets.asset_bf.into_inner(),
+            );
+            txout.value
+                == Value::new_confidential(&crate::EC, secrets.value, generator, secrets.value_bf)
+        }
+        Value::Null => false,
+    };
+    asset_matches && value_matches
+}
+
+/// The `#blinded=` fragment to append to the URL of a transaction on a Liquid explorer to show
+/// the unblinded values of its outputs
+///
+/// Each output is `value,asset,value_blinder,asset_blinder`, with ids and blinders hex encoded
+/// in the same byte order as Elements Core.
+pub fn blinded_url_fragment(secrets: &[TxOutSecrets]) -> String {
+    let outputs: Vec<String> = secrets
+        .iter()
+        .map(|s| format!("{},{},{},{}", s.value, s.asset, s.value_bf.to_hex(), s.asset_bf.to_hex()))
+        .collect();
+    format!("#{}{}", BLINDED_FRAGMENT_KEY, outputs.join(","))
+}
+
+/// Parse the unblinded values of a `#blinded=` fragment, alone or as part of a URL
+pub fn parse_blinded_url_fragment(s: &str) -> Result<Vec<TxOutSecrets>, Error> {
+    let err = |e: &str| Error::InvalidBlindingData(e.to_string());
+    let fragment = match s.split_once('#') {
+        Some((_, fragment)) => fragment,
+        None => s,
+    };
+    let fragment = fragment.trim().strip_prefix(BLINDED_FRAGMENT_KEY).unwrap_or(fragment);
+    if fragment.is_empty() {
+        return Err(err("no outputs"));
+    }
+    let fields: Vec<&str> = fragment.split(',').collect();
+    fields
+        .chunks(4)
+        .map(|output| {
+            if output.len() != 4 {
+                return Err(err("expected value, asset, value blinder and asset blinder"));
+            }
+            Ok(TxOutSecrets::new(
+                AssetId::from_str(output[1]).map_err(|_| err("invalid asset"))?,
+                AssetBlindingFactor::from_str(output[3])
+                    .map_err(|_| err("invalid asset blinder"))?,
+                output[0].parse().map_err(|_| err("invalid value"))?,
+                ValueBlindingFactor::from_str(output[2])
+                    .map_err(|_| err("invalid value blinder"))?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use elements::Script;
+
+    fn secrets(value: u64) -> TxOutSecrets {
+        let mut rng = rand::thread_rng();
+        TxOutSecrets::new(
+            AssetId::from_slice(&[1u8; 32]).unwrap(),
+            AssetBlindingFactor::new(&mut rng),
+            value,
+            ValueBlindingFactor::new(&mut rng),
+        )
+    }
+
+    fn blinded_txout(secrets: &TxOutSecrets) -> TxOut {
+        let asset = Asset::new_confidential(&crate::EC, secrets.asset, secrets.asset_bf);
+        let generator = asset.into_asset_gen(&crate::EC).unwrap();
+        TxOut {
+            asset,
+            value: Value::new_confidential(&crate::EC, secrets.value, generator, secrets.value_bf),
+            script_pubkey: Script::new(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn blinded_fragment_roundtrip() {
+        let outputs = vec![secrets(1_000), secrets(2_000)];
+        let fragment = blinded_url_fragment(&outputs);
+        assert!(fragment.starts_with("#blinded=1000,0101"));
+        assert_eq!(parse_blinded_url_fragment(&fragment).unwrap(), outputs);
+        let url = format!("https://blockstream.info/liquid/tx/00{}", fragment);
+        assert_eq!(parse_blinded_url_fragment(&url).unwrap(), outputs);
+        assert_eq!(parse_blinded_url_fragment(&fragment[9..]).unwrap(), outputs);
+
+        assert!(parse_blinded_url_fragment("#blinded=").is_err());
+        assert!(parse_blinded_url_fragment(&fragment[..fragment.len() - 1]).is_err());
+        assert!(parse_blinded_url_fragment("#blinded=1,2,3").is_err());
+    }
+
+    #[test]
+    fn secrets_match_commitments() {
+        let s = secrets(1_000);
+        let txout = blinded_txout(&s);
+        assert!(secrets_match(&txout, &s));
+        let mut other = s;
+        other.value = 1_001;
+        assert!(!secrets_match(&txout, &other));
+        assert!(!secrets_match(&blinded_txout(&secrets(1_000)), &s));
+
+        let explicit = TxOut::new_fee(1_000, s.asset);
+        let explicit_secrets = TxOutSecrets::new(
+            s.asset,
+            AssetBlindingFactor::zero(),
+            1_000,
+            ValueBlindingFactor::zero(),
+        );
+        assert!(secrets_match(&explicit, &explicit_secrets));
+    }
+}
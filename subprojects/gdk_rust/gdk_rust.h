@@ -33,6 +33,18 @@ int GDKRUST_call(const char *method, const char *input, char** output);
  */
 int GDKRUST_set_notification_handler(void* session, GDKRUST_notification_handler handler, void *self_context);
 
+/**
+ * A signer handler, answering a json signature request.
+ * The response must be allocated with malloc, it is freed by the library.
+ * Returns GA_OK on success, otherwise the response may contain an error message.
+ */
+typedef int (*GDKRUST_signer_handler)(void *self_context, const char *request, char **response);
+
+/**
+ * Set a handler to be called to sign transactions with keys held outside the session.
+ */
+int GDKRUST_set_signer_handler(void* session, GDKRUST_signer_handler handler, void *self_context);
+
 /**
  * Free a string returned by the api.
  *
@@ -21,7 +21,11 @@ use gdk_common::model::{InitParam, SPVDownloadHeadersParams, SPVVerifyTxParams};
 use crate::error::Error;
 use gdk_common::exchange_rates::{ExchangeRatesCache, ExchangeRatesCacher};
 use gdk_common::session::{JsonError, Session};
+use gdk_electrum::message::{self, VerifyMessageParam};
+use gdk_electrum::psbt::{self, CombineParam, ExtractTxParam, FinalizeParam};
 use gdk_electrum::pset::{self, ExtractParam, FromTxParam, MergeTxParam};
+use gdk_electrum::rpc::RpcSession;
+use gdk_electrum::signer::JsonSigner;
 use gdk_electrum::{headers, ElectrumSession, NativeNotif};
 use log::{LevelFilter, Metadata, Record};
 use serde::Serialize;
@@ -35,7 +39,7 @@ pub struct GdkSession {
 }
 
 pub enum GdkBackend {
-    // Rpc(RpcSession),
+    Rpc(RpcSession),
     Electrum(ElectrumSession),
     Greenlight(GreenlightSession),
 }
@@ -137,7 +141,6 @@ fn init_logging(level: LevelFilter) {
 }
 
 fn create_session(network: &Value) -> Result<GdkSession, Value> {
-    info!("create_session {:?}", network);
     if !network.is_object() || !network.as_object().unwrap().contains_key("server_type") {
         error!("Expected network to be an object with a server_type key");
         return Err(GA_ERROR.into());
@@ -149,10 +152,11 @@ fn create_session(network: &Value) -> Result<GdkSession, Value> {
         return Err(GA_ERROR.into());
     }
 
-    let parsed_network = parsed_network.unwrap();
+    let parsed_network: gdk_common::NetworkParameters = parsed_network.unwrap();
+    let network_str = format!("{:?}", parsed_network);
 
     let backend = match network["server_type"].as_str() {
-        // Some("rpc") => GDKRUST_session::Rpc( GDKRPC_session::create_session(parsed_network.unwrap()).unwrap() ),
+        Some("rpc") => GdkBackend::Rpc(RpcSession::new(parsed_network)?),
         Some("greenlight") => GdkBackend::Greenlight(GreenlightSession::default()),
         Some("electrum") => {
             let session = ElectrumSession::new(parsed_network)?;
@@ -160,6 +164,8 @@ fn create_session(network: &Value) -> Result<GdkSession, Value> {
         }
         _ => return Err(json!("server_type invalid")),
     };
+    // logged once validated, with the rpc credentials redacted
+    info!("create_session {}", network_str);
     let gdk_session = GdkSession {
         backend,
     };
@@ -208,6 +214,7 @@ fn call_session(sess: &mut GdkSession, method: &str, input: &str) -> Result<Valu
         let params = serde_json::from_value(input)?;
 
         let ticker = match sess.backend {
+            GdkBackend::Rpc(ref mut s) => exchange_rates::fetch_cached(s, &params),
             GdkBackend::Electrum(ref mut s) => exchange_rates::fetch_cached(s, &params),
             GdkBackend::Greenlight(ref mut s) => exchange_rates::fetch_cached(s, &params),
         }?;
@@ -231,6 +238,8 @@ fn call_session(sess: &mut GdkSession, method: &str, input: &str) -> Result<Valu
         || input_str.contains("pin")
         || input_str.contains("mnemonic")
         || input_str.contains("xprv")
+        || input_str.contains("private_keys")
+        || input_str.contains("passphrase")
     {
         "redacted".to_string()
     } else {
@@ -240,15 +249,21 @@ fn call_session(sess: &mut GdkSession, method: &str, input: &str) -> Result<Valu
     info!("GDKRUST_call_session handle_call {} input {:?}", method, input_redacted);
 
     let res = match sess.backend {
+        GdkBackend::Rpc(ref mut s) => s.handle_call(&method, input),
         GdkBackend::Electrum(ref mut s) => s.handle_call(&method, input),
         GdkBackend::Greenlight(ref mut s) => s.handle_call(&method, input),
     };
 
     let methods_to_redact_out = vec!["credentials_from_pin_data"];
-    let mut output_redacted = if methods_to_redact_out.contains(&method) {
+    let output_str = format!("{:?}", res);
+    // created transactions contain the request, with the private keys to sweep
+    let mut output_redacted = if methods_to_redact_out.contains(&method)
+        || output_str.contains("private_keys")
+        || output_str.contains("passphrase")
+    {
         "redacted".to_string()
     } else {
-        format!("{:?}", res)
+        output_str
     };
     output_redacted.truncate(200);
     info!("GDKRUST_call_session {} output {:?}", method, output_redacted);
@@ -269,6 +284,7 @@ pub extern "C" fn GDKRUST_set_notification_handler(
     let backend = &mut sess.backend;
 
     match backend {
+        GdkBackend::Rpc(ref mut s) => s.notify.set_native((handler, self_context)),
         GdkBackend::Electrum(ref mut s) => s.notify.set_native((handler, self_context)),
         GdkBackend::Greenlight(ref mut _s) => (), // TODO,
     };
@@ -278,6 +294,63 @@ pub extern "C" fn GDKRUST_set_notification_handler(
     GA_OK
 }
 
+/// A signer handler provided by the host application with its context
+struct NativeSigner {
+    handler: extern "C" fn(*const libc::c_void, *const c_char, *mut *mut c_char) -> i32,
+    self_context: *const libc::c_void,
+}
+
+// The host application is responsible for the handler being callable from any thread
+unsafe impl Send for NativeSigner {}
+unsafe impl Sync for NativeSigner {}
+
+impl NativeSigner {
+    fn call(&self, request: Value) -> Result<Value, gdk_electrum::error::Error> {
+        let request = CString::new(request.to_string()).expect("json without nul bytes");
+        let mut response: *mut c_char = std::ptr::null_mut();
+        let ret = (self.handler)(self.self_context, request.as_ptr(), &mut response);
+        let response_str = if response.is_null() {
+            String::new()
+        } else {
+            let response_str = read_str(response);
+            // the response is allocated by the host application with malloc
+            unsafe { libc::free(response as *mut libc::c_void) };
+            response_str
+        };
+        if ret != GA_OK {
+            return Err(gdk_electrum::error::Error::InvalidSignerResponse(response_str));
+        }
+        Ok(serde_json::from_str(&response_str)?)
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn GDKRUST_set_signer_handler(
+    ptr: *mut libc::c_void,
+    handler: extern "C" fn(*const libc::c_void, *const c_char, *mut *mut c_char) -> i32,
+    self_context: *const libc::c_void,
+) -> i32 {
+    if ptr.is_null() {
+        return GA_ERROR;
+    }
+    let sess: &mut GdkSession = unsafe { &mut *(ptr as *mut GdkSession) };
+    let native = NativeSigner {
+        handler,
+        self_context,
+    };
+    let signer = JsonSigner::new(move |request| native.call(request));
+
+    match sess.backend {
+        GdkBackend::Rpc(ref mut s) => s.set_signer(Arc::new(signer)),
+        GdkBackend::Electrum(ref mut s) => s.set_signer(Arc::new(signer)),
+        GdkBackend::Greenlight(_) => return GA_ERROR,
+    };
+
+    info!("set signer handler");
+
+    GA_OK
+}
+
 #[no_mangle]
 pub extern "C" fn GDKRUST_destroy_string(ptr: *mut c_char) {
     unsafe {
@@ -354,6 +427,22 @@ fn handle_call(method: &str, input: &str) -> Result<String, Error> {
             let param: MergeTxParam = serde_json::from_str(input)?;
             to_string(&pset::merge_tx(&param)?)
         }
+        "psbt_combine" => {
+            let param: CombineParam = serde_json::from_str(input)?;
+            to_string(&psbt::combine(&param)?)
+        }
+        "psbt_finalize" => {
+            let param: FinalizeParam = serde_json::from_str(input)?;
+            to_string(&psbt::finalize(&param)?)
+        }
+        "psbt_extract_tx" => {
+            let param: ExtractTxParam = serde_json::from_str(input)?;
+            to_string(&psbt::extract_tx(&param)?)
+        }
+        "verify_message" => {
+            let param: VerifyMessageParam = serde_json::from_str(input)?;
+            to_string(&message::verify(&param)?)
+        }
         "spv_verify_tx" => {
             let param: SPVVerifyTxParams = serde_json::from_str(input)?;
             to_string(&headers::spv_verify_tx(&param)?.as_i32())
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
-use std::net::TcpListener;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -13,13 +15,15 @@ use tempfile::TempDir;
 
 use gdk_common::be::BETransaction;
 use gdk_common::model::*;
+use gdk_common::network::RpcCredentials;
 use gdk_common::scripts::ScriptType;
 use gdk_common::session::Session;
 use gdk_common::{NetworkId, NetworkParameters, State};
 use gdk_electrum::error::Error;
 use gdk_electrum::headers::bitcoin::HeadersChain;
 use gdk_electrum::interface::ElectrumUrl;
-use gdk_electrum::{headers, spv, ElectrumSession};
+use gdk_electrum::rpc::RpcSession;
+use gdk_electrum::{headers, message, psbt, spv, ElectrumSession};
 use gdk_test::utils;
 use gdk_test::{ElectrumSessionExt, TestSession};
 
@@ -517,6 +521,7 @@ fn coin_selection(is_liquid: bool) {
         address: node_address.to_string(),
         satoshi: sat8,
         asset_id: test_session.asset_id(),
+        ..Default::default()
     });
     create_opt.utxos = CreateTxUtxos::default();
     create_opt.utxo_strategy = UtxoStrategy::Manual;
@@ -542,6 +547,7 @@ fn coin_selection(is_liquid: bool) {
             address: node_address.to_string(),
             satoshi: sat2_a,
             asset_id: Some(asset_a.clone()),
+            ..Default::default()
         });
         utxos.0.remove_entry(&btc_key);
         create_opt.utxos = utils::convertutxos(&utxos);
@@ -686,6 +692,26 @@ fn subaccounts(is_liquid: bool) {
     assert_eq!(acc2.settings.hidden, true);
     // update_subaccount should not affect unspecified fields
     assert_eq!(acc2.settings.name, "Account 2");
+    assert_eq!(acc2.settings.gap_limit, None);
+
+    // Set the subaccount gap limit
+    test_session
+        .session
+        .update_subaccount(UpdateAccountOpt {
+            subaccount: 2,
+            gap_limit: Some(50),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(test_session.session.get_subaccount(2).unwrap().settings.gap_limit, Some(50));
+    assert!(test_session
+        .session
+        .update_subaccount(UpdateAccountOpt {
+            subaccount: 2,
+            gap_limit: Some(0),
+            ..Default::default()
+        })
+        .is_err());
 
     // Rename subaccount (deprecated in favor of update_subaccount)
     test_session
@@ -1001,6 +1027,7 @@ fn spend_unsynced(is_liquid: bool) {
         address: address2.to_string(),
         satoshi: sat2,
         asset_id: test_session.asset_id(),
+        ..Default::default()
     });
     create_opt.utxos = utils::convertutxos(&utxos);
     let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
@@ -1027,6 +1054,7 @@ fn spend_unsynced(is_liquid: bool) {
         address: address2.to_string(),
         satoshi: sat2,
         asset_id: test_session.asset_id(),
+        ..Default::default()
     });
     create_opt.utxos = utils::convertutxos(&utxos);
     let res = test_session.session.create_transaction(&mut create_opt);
@@ -1168,6 +1196,7 @@ fn sighash(is_liquid: bool) {
             address: dest_address,
             satoshi: 5000,
             asset_id: test_session.asset_id(),
+            ..Default::default()
         });
         create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
         let mut txc = test_session.session.create_transaction(&mut create_opt).unwrap();
@@ -1206,6 +1235,56 @@ fn sighash(is_liquid: bool) {
     }
 }
 
+#[test]
+fn psbt_bitcoin() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let opt = CreateAccountOpt {
+        name: "p2tr".into(),
+        subaccount: 3,
+        ..Default::default()
+    };
+    test_session.session.create_subaccount(opt).unwrap();
+
+    for subaccount in [0, 3] {
+        let sat = 10000;
+        let address = test_session.get_receive_address(subaccount).address;
+        let txid = test_session.node_sendtoaddress(&address, sat, None);
+        test_session.wait_tx(vec![subaccount], &txid, Some(sat), Some(TransactionType::Incoming));
+
+        let mut create_opt = CreateTransaction::default();
+        create_opt.subaccount = subaccount;
+        create_opt.addressees.push(AddressAmount {
+            address: test_session.node_getnewaddress(None),
+            satoshi: 5000,
+            asset_id: None,
+            ..Default::default()
+        });
+        create_opt.utxos = utils::convertutxos(&test_session.utxos(subaccount));
+        let txc = test_session.session.create_transaction(&mut create_opt).unwrap();
+
+        // create, sign, finalize and extract the transaction as an external wallet would
+        let unsigned = test_session.session.create_psbt(&txc).unwrap().psbt;
+        let signed = test_session
+            .session
+            .sign_psbt(&SignPsbtParams {
+                psbt: unsigned,
+                subaccount,
+            })
+            .unwrap();
+        assert_eq!(signed.signed_inputs, txc.used_utxos.len());
+        let param = serde_json::from_value(serde_json::json!({ "psbt": signed.psbt })).unwrap();
+        let finalized = serde_json::to_value(psbt::finalize(&param).unwrap()).unwrap();
+        let param =
+            serde_json::from_value(serde_json::json!({ "psbt": finalized["psbt"] })).unwrap();
+        let extracted = serde_json::to_value(psbt::extract_tx(&param).unwrap()).unwrap();
+        let hex = extracted["transaction"].as_str().unwrap();
+
+        let txid = test_session.session.broadcast_transaction(hex).unwrap();
+        let sent = 5000 + txc.fee;
+        test_session.wait_tx(vec![subaccount], &txid, Some(sent), Some(TransactionType::Outgoing));
+    }
+}
+
 #[test]
 fn skip_signing_bitcoin() {
     skip_signing(false);
@@ -1234,6 +1313,7 @@ fn skip_signing(is_liquid: bool) {
         address: dest_address,
         satoshi: 15000,
         asset_id: test_session.asset_id(),
+        ..Default::default()
     });
     create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
     let mut txc = test_session.session.create_transaction(&mut create_opt).unwrap();
@@ -1275,6 +1355,312 @@ fn skip_signing(is_liquid: bool) {
     assert!(txs1_vin_decoded[1] == txs2_vin_decoded[1]);
 }
 
+#[test]
+fn issuance_liquid() {
+    let mut test_session = TestSession::new(true, |_| ());
+    test_session.fund(100_000_000, None);
+    test_session.wait_account_n_txs(0, 1);
+
+    let contract = serde_json::json!({
+        "entity": {"domain": "example.com"},
+        "issuer_pubkey": "0337cceec0beea0232ebe14cba0197a9fbd45fcf2ec946749de920e71434c2b904",
+        "name": "Test Asset",
+        "precision": 8,
+        "ticker": "TEST",
+        "version": 0,
+    });
+    let opt = CreateIssuanceOpt {
+        subaccount: 0,
+        fee_rate: Some(100),
+        utxos: HashMap::new(),
+        asset_amount: 1_000,
+        token_amount: 1,
+        asset_address: None,
+        token_address: None,
+        contract: Some(contract),
+        confidential: true,
+    };
+    let issuance = test_session.session.create_issuance(&opt).unwrap();
+    assert!(issuance.contract_hash.is_some());
+    let token_id = issuance.token_id.clone().unwrap();
+    let signed_tx = test_session.session.sign_transaction(&issuance.transaction).unwrap();
+    test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_account_n_txs(0, 2);
+    let asset_id = issuance.asset_id.clone();
+    assert_eq!(test_session.balance_account(0, Some(asset_id.clone()), None), 1_000);
+    assert_eq!(test_session.balance_account(0, Some(token_id.clone()), None), 1);
+
+    // reissue spending the token, which returns to the wallet
+    let opt = CreateReissuanceOpt {
+        subaccount: 0,
+        fee_rate: Some(100),
+        utxos: HashMap::new(),
+        asset_id: asset_id.clone(),
+        asset_amount: 500,
+        asset_address: None,
+        confidential: true,
+    };
+    let reissuance = test_session.session.create_reissuance(&opt).unwrap();
+    assert_eq!(reissuance.asset_id, asset_id);
+    assert_eq!(reissuance.asset_entropy, issuance.asset_entropy);
+    let signed_tx = test_session.session.sign_transaction(&reissuance.transaction).unwrap();
+    test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_account_n_txs(0, 3);
+    assert_eq!(test_session.balance_account(0, Some(asset_id.clone()), None), 1_500);
+    assert_eq!(test_session.balance_account(0, Some(token_id), None), 1);
+
+    let opt = CreateBurnOpt {
+        subaccount: 0,
+        fee_rate: Some(100),
+        utxos: HashMap::new(),
+        asset_id: asset_id.clone(),
+        satoshi: 200,
+    };
+    let burn = test_session.session.create_burn(&opt).unwrap();
+    let signed_tx = test_session.session.sign_transaction(&burn).unwrap();
+    test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_account_n_txs(0, 4);
+    assert_eq!(test_session.balance_account(0, Some(asset_id), None), 1_300);
+    test_session.stop();
+
+    // issuances are liquid only
+    let mut test_session = TestSession::new(false, |_| ());
+    let opt = CreateBurnOpt {
+        subaccount: 0,
+        fee_rate: None,
+        utxos: HashMap::new(),
+        asset_id: "144c654344aa716d6f3abcc1ca90e5641e4e2a7f633bc09fe3baf64585819a49".into(),
+        satoshi: 200,
+    };
+    assert!(matches!(
+        test_session.session.create_burn(&opt),
+        Err(Error::InvalidIssuanceRequest(_))
+    ));
+    test_session.stop();
+}
+
+#[test]
+fn peg_liquid() {
+    let mut test_session = TestSession::new(true, |_| ());
+    test_session.fund(100_000_000, None);
+    test_session.wait_account_n_txs(0, 1);
+
+    let opt = GetPeginAddressOpt {
+        subaccount: 0,
+    };
+    let pegin = test_session.session.get_pegin_address(&opt).unwrap();
+    let mainchain_address = bitcoin::Address::from_str(&pegin.mainchain_address).unwrap();
+    assert_eq!(mainchain_address.network, bitcoin::Network::Regtest);
+    let receive = test_session.get_receive_address(0);
+    assert_ne!(receive.address, pegin.address);
+
+    // peg out to a mainchain address
+    let mainchain_address =
+        bitcoin::Address::p2wsh(&bitcoin::Script::from(vec![0x51]), bitcoin::Network::Regtest);
+    let mainchain_address = &mainchain_address.to_string();
+    let mut create_opt = test_session.create_opt(
+        mainchain_address,
+        10_000,
+        test_session.asset_id(),
+        None,
+        0,
+        test_session.utxos(0),
+    );
+    create_opt.addressees[0].is_pegout = true;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_account_n_txs(0, 2);
+    let balance = test_session.balance_account(0, None, None);
+    assert_eq!(balance, 100_000_000 - 10_000 - signed_tx.fee);
+
+    // only the policy asset can be pegged out, and only to mainchain addresses
+    let mut create_opt = test_session.create_opt(
+        mainchain_address,
+        10_000,
+        Some("144c654344aa716d6f3abcc1ca90e5641e4e2a7f633bc09fe3baf64585819a49".into()),
+        None,
+        0,
+        test_session.utxos(0),
+    );
+    create_opt.addressees[0].is_pegout = true;
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::InvalidPegRequest(_))
+    ));
+    let mut create_opt = test_session.create_opt(
+        &receive.address,
+        10_000,
+        test_session.asset_id(),
+        None,
+        0,
+        test_session.utxos(0),
+    );
+    create_opt.addressees[0].is_pegout = true;
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::InvalidAddress)
+    ));
+    test_session.stop();
+}
+
+#[test]
+fn explicit_outputs_liquid() {
+    let mut test_session = TestSession::new(true, |_| ());
+    test_session.fund(100_000_000, None);
+    test_session.wait_account_n_txs(0, 1);
+
+    // pay an unconfidential address and leave the output to a confidential address explicit
+    let node_address = test_session.node_getnewaddress(None);
+    let unconf_address = utils::to_unconfidential(&node_address);
+    let mut create_opt = test_session.create_opt(
+        &unconf_address,
+        10_000,
+        test_session.asset_id(),
+        Some(100),
+        0,
+        test_session.utxos(0),
+    );
+    create_opt.addressees[0].is_explicit = true;
+    let mut addressee = create_opt.addressees[0].clone();
+    addressee.address = node_address;
+    create_opt.addressees.push(addressee);
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    test_session.check_fee_rate(100, &signed_tx, 0.05);
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, None);
+    let balance = test_session.balance_account(0, None, None);
+    assert_eq!(balance, 100_000_000 - 20_000 - signed_tx.fee);
+
+    let network_id = test_session.network_parameters().id();
+    let tx = match BETransaction::from_hex(&signed_tx.hex, network_id).unwrap() {
+        BETransaction::Elements(tx) => tx,
+        BETransaction::Bitcoin(_) => unreachable!(),
+    };
+    let explicit: Vec<_> = tx.output.iter().filter(|o| !o.is_fee() && o.nonce.is_null()).collect();
+    assert_eq!(explicit.len(), 2);
+    assert!(explicit.iter().all(|o| o.value.explicit() == Some(10_000) && o.witness.is_empty()));
+    // the change is confidential
+    assert!(tx.output.iter().any(|o| o.value.is_confidential()));
+
+    // without the opt-in unconfidential addresses are still rejected
+    let mut create_opt = test_session.create_opt(
+        &unconf_address,
+        10_000,
+        test_session.asset_id(),
+        None,
+        0,
+        test_session.utxos(0),
+    );
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::NonConfidentialAddress)
+    ));
+
+    // confidential inputs cannot be spent with explicit outputs only
+    create_opt.addressees[0].is_explicit = true;
+    create_opt.addressees[0].satoshi = 0;
+    create_opt.send_all = true;
+    assert!(test_session.session.create_transaction(&mut create_opt).is_err());
+    test_session.stop();
+}
+
+#[test]
+fn swap_liquid() {
+    let mut test_session = TestSession::new(true, |_| ());
+    let assets = test_session.fund(100_000_000, Some(1));
+    let asset_id = assets[0].clone();
+    test_session.wait_account_n_txs(0, 2);
+    test_session
+        .session
+        .create_subaccount(CreateAccountOpt {
+            subaccount: 1,
+            name: "Taker".into(),
+            ..Default::default()
+        })
+        .unwrap();
+    let address = test_session.get_receive_address(1);
+    let (txid, _) = test_session.send_tx_from(0, &address.address, 1_000_000, None);
+    test_session.wait_tx(vec![0, 1], &txid, None, None);
+    let policy_balance_0 = test_session.balance_account(0, None, None);
+
+    // subaccount 0 offers all its units of the asset for some L-BTC
+    let utxo = &test_session.utxos(0).0[&asset_id][0];
+    let utxo = CreateTxUtxo {
+        txid: utxo.txhash.clone(),
+        vout: utxo.pt_idx,
+    };
+    let mut opt = CreateSwapProposalOpt {
+        subaccount: 0,
+        utxo,
+        receive_asset_id: test_session.asset_id().unwrap(),
+        receive_satoshi: 50_000,
+    };
+    let proposal = test_session.session.create_swap_proposal(&opt).unwrap();
+    assert_eq!(proposal.send_asset_id, asset_id);
+    assert_eq!(proposal.send_satoshi, 100_000_000);
+    assert!(proposal.transaction.is_none());
+
+    // subaccount 1 takes it
+    let opt_complete = CompleteSwapOpt {
+        subaccount: 1,
+        psbt_hex: proposal.psbt_hex.clone(),
+        fee_rate: Some(100),
+        utxos: HashMap::new(),
+    };
+    let swap = test_session.session.complete_swap(&opt_complete).unwrap();
+    assert_eq!(swap.receive_asset_id, asset_id);
+    assert_eq!(swap.send_satoshi, 50_000);
+    let txid = test_session.session.broadcast_transaction(&swap.transaction.unwrap()).unwrap();
+    test_session.wait_tx(vec![0, 1], &txid, None, None);
+    assert_eq!(test_session.balance_account(0, Some(asset_id.clone()), None), 0);
+    assert_eq!(test_session.balance_account(0, None, None), policy_balance_0 + 50_000);
+    assert_eq!(test_session.balance_account(1, Some(asset_id.clone()), None), 100_000_000);
+    assert_eq!(test_session.balance_account(1, None, None), 1_000_000 - 50_000 - swap.fee);
+
+    // the utxo is spent and an asset cannot be swapped with itself
+    assert!(matches!(test_session.session.create_swap_proposal(&opt), Err(Error::InvalidSwap(_))));
+    let utxo = &test_session.utxos(1).0[&asset_id][0];
+    opt.subaccount = 1;
+    opt.utxo = CreateTxUtxo {
+        txid: utxo.txhash.clone(),
+        vout: utxo.pt_idx,
+    };
+    opt.receive_asset_id = asset_id;
+    assert!(matches!(test_session.session.create_swap_proposal(&opt), Err(Error::InvalidSwap(_))));
+    test_session.stop();
+}
+
+#[test]
+fn unblinded_data_liquid() {
+    let test_session = TestSession::new(true, |_| ());
+    let address = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&address.address, 10_000, None);
+    test_session.wait_tx(vec![0], &txid, None, None);
+
+    let opt = GetUnblindedDataOpt {
+        txid: txid.clone(),
+        blinded: None,
+    };
+    let data = test_session.session.get_unblinded_data(&opt).unwrap();
+    assert_eq!(data.outputs.len(), 1);
+    assert!(data.outputs[0].is_relevant);
+    assert_eq!(data.outputs[0].satoshi, 10_000);
+    assert_eq!(Some(data.outputs[0].asset_id.clone()), test_session.asset_id());
+    assert!(data.url_fragment.starts_with("#blinded=10000,"));
+
+    // the fragment is verified against the transaction
+    let opt = GetUnblindedDataOpt {
+        txid,
+        blinded: Some(data.url_fragment.replace("#blinded=10000,", "#blinded=10001,")),
+    };
+    assert!(matches!(
+        test_session.session.get_unblinded_data(&opt),
+        Err(Error::InvalidBlindingData(_))
+    ));
+}
+
 #[test]
 fn not_unblindable_liquid() {
     let test_session = TestSession::new(true, |_| ());
@@ -1291,6 +1677,392 @@ fn not_unblindable_liquid() {
     assert!(test_session.utxos(0).0.is_empty());
 }
 
+#[test]
+fn sweep_bitcoin() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[7u8; 32]).unwrap();
+    let key = bitcoin::PrivateKey::new(secret_key, bitcoin::Network::Regtest);
+    let public_key = key.public_key(&bitcoin::secp256k1::Secp256k1::new());
+    let p2pkh = bitcoin::Address::p2pkh(&public_key, bitcoin::Network::Regtest);
+    let p2wpkh = bitcoin::Address::p2wpkh(&public_key, bitcoin::Network::Regtest).unwrap();
+    let p2shwpkh = bitcoin::Address::p2shwpkh(&public_key, bitcoin::Network::Regtest).unwrap();
+    let mut txids = vec![];
+    for address in [p2pkh, p2wpkh, p2shwpkh].iter() {
+        txids.push(test_session.node_sendtoaddress(&address.to_string(), 100_000, None));
+    }
+    test_session.mine_block();
+
+    let mut create_opt = CreateTransaction::default();
+    create_opt.private_keys = vec![key.to_wif()];
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert!(tx.is_sweep);
+    assert_eq!(tx.used_utxos.len(), 3);
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    assert!(signed_tx.is_sweep);
+    test_session.check_fee_rate(1000, &signed_tx, 0.05);
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, None);
+    assert_eq!(test_session.balance_account(0, None, None), 300_000 - signed_tx.fee);
+
+    // nothing left to sweep
+    let mut create_opt = CreateTransaction::default();
+    create_opt.private_keys = vec![key.to_wif()];
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::InvalidSweepRequest(_))
+    ));
+    test_session.stop();
+}
+
+#[test]
+fn sign_message_bitcoin() {
+    let mut test_session = TestSession::new(false, |_| ());
+    for subaccount in [1, 2, 3].iter() {
+        test_session
+            .session
+            .create_subaccount(CreateAccountOpt {
+                subaccount: *subaccount,
+                name: format!("Account {}", subaccount),
+                ..Default::default()
+            })
+            .unwrap();
+    }
+    let verify = |address: &str, message: &str, signature: &str| -> bool {
+        let param = serde_json::from_value(serde_json::json!({
+            "address": address,
+            "message": message,
+            "signature": signature,
+        }))
+        .unwrap();
+        let result = message::verify(&param).unwrap();
+        serde_json::to_value(&result).unwrap()["is_valid"].as_bool().unwrap()
+    };
+
+    // p2sh-p2wpkh, p2wpkh, p2pkh and p2tr subaccounts
+    for subaccount in 0..4 {
+        let address = test_session.get_receive_address(subaccount).address;
+        for bip322 in [false, true].iter() {
+            let opt = SignMessageOpt {
+                address: address.clone(),
+                message: "message".into(),
+                bip322: *bip322,
+            };
+            let result = test_session.session.sign_message(&opt);
+            if *bip322 && (subaccount == 0 || subaccount == 2) {
+                // BIP322 simple signatures are for native segwit addresses only
+                assert!(matches!(result, Err(Error::InvalidMessageRequest(_))));
+                continue;
+            }
+            let signature = result.unwrap().signature;
+            assert!(verify(&address, "message", &signature));
+            assert!(!verify(&address, "another message", &signature));
+        }
+    }
+
+    // addresses not of the wallet cannot be signed with
+    let opt = SignMessageOpt {
+        address: test_session.node_getnewaddress(None),
+        message: "message".into(),
+        bip322: false,
+    };
+    assert!(matches!(test_session.session.sign_message(&opt), Err(Error::ScriptPubkeyNotFound)));
+    test_session.stop();
+}
+
+#[test]
+fn esplora_backend() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let sat = 98_000;
+    let address = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&address.address, sat, None);
+    test_session.wait_tx(vec![0], &txid, Some(sat), Some(TransactionType::Incoming));
+    test_session.node_generate(1);
+
+    // Same wallet, but the chain data comes from the Esplora API of the same electrs
+    let temp_dir = TempDir::new().unwrap();
+    let mut new_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", temp_dir.path().display());
+        let esplora_url = test_session.electrs.esplora_url.clone().unwrap();
+        network.esplora_url = Some(format!("http://{}", esplora_url));
+        ElectrumSession::new(network).unwrap()
+    };
+    new_session.auth_handler_login(&test_session.credentials);
+    new_session.wait_account_n_txs(0, 1);
+
+    let opt = GetBalanceOpt {
+        subaccount: 0,
+        num_confs: 0,
+        confidential_utxos_only: None,
+    };
+    let balance = new_session.get_balance(&opt).unwrap();
+    assert_eq!(*balance.get("btc").unwrap() as u64, sat);
+    assert_eq!(new_session.get_fee_estimates().unwrap().len(), 25);
+
+    // Broadcast through Esplora
+    let mut create_opt = CreateTransaction::default();
+    create_opt.addressees.push(AddressAmount {
+        address: test_session.node_getnewaddress(None),
+        satoshi: 10_000,
+        asset_id: None,
+        ..Default::default()
+    });
+    create_opt.utxos = utils::convertutxos(&test_session.utxos(0));
+    let tx = new_session.create_transaction(&mut create_opt).unwrap();
+    let tx = test_session.session.sign_transaction(&tx).unwrap();
+    let tx = new_session.send_transaction(&tx).unwrap();
+    test_session.wait_tx(vec![0], &tx.txid, None, Some(TransactionType::Outgoing));
+    test_session.stop();
+}
+
+#[test]
+fn rpc_backend() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let sat = 97_000;
+    let address = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&address.address, sat, None);
+    test_session.wait_tx(vec![0], &txid, Some(sat), Some(TransactionType::Incoming));
+    test_session.node_generate(1);
+
+    // Same wallet, but the chain data comes from a watch-only wallet in the same bitcoind
+    let temp_dir = TempDir::new().unwrap();
+    let mut new_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", temp_dir.path().display());
+        let cookie = std::fs::read_to_string(&test_session.node.params.cookie_file).unwrap();
+        let (user, password) = cookie.split_once(':').unwrap();
+        network.rpc_url = Some(format!("http://{}", test_session.node.params.rpc_socket));
+        network.rpc_credentials = Some(RpcCredentials {
+            user: user.into(),
+            password: password.into(),
+        });
+        network.rpc_wallet = Some("watchonly".into());
+        RpcSession::new(network).unwrap()
+    };
+    new_session.auth_handler_login(&test_session.credentials);
+    new_session.wait_account_n_txs(0, 1);
+
+    let opt = GetBalanceOpt {
+        subaccount: 0,
+        num_confs: 0,
+        confidential_utxos_only: None,
+    };
+    let balance = new_session.get_balance(&opt).unwrap();
+    assert_eq!(*balance.get("btc").unwrap() as u64, sat);
+
+    // A later payment is seen by the following syncs
+    let sat2 = 13_000;
+    let address = new_session
+        .get_receive_address(&GetAddressOpt {
+            subaccount: 0,
+            address_type: None,
+            is_internal: None,
+        })
+        .unwrap();
+    test_session.node_sendtoaddress(&address.address, sat2, None);
+    new_session.wait_account_n_txs(0, 2);
+    let balance = new_session.get_balance(&opt).unwrap();
+    assert_eq!(*balance.get("btc").unwrap() as u64, sat + sat2);
+
+    // Broadcast through bitcoind
+    let mut create_opt = CreateTransaction::default();
+    create_opt.addressees.push(AddressAmount {
+        address: test_session.node_getnewaddress(None),
+        satoshi: 10_000,
+        asset_id: None,
+        ..Default::default()
+    });
+    create_opt.utxos = utils::convertutxos(&test_session.utxos(0));
+    let tx = new_session.create_transaction(&mut create_opt).unwrap();
+    let tx = test_session.session.sign_transaction(&tx).unwrap();
+    let tx = new_session.send_transaction(&tx).unwrap();
+    test_session.wait_tx(vec![0], &tx.txid, None, Some(TransactionType::Outgoing));
+    test_session.stop();
+}
+
+#[test]
+fn electrum_failover() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let sat = 96_000;
+    let address = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&address.address, sat, None);
+    test_session.wait_tx(vec![0], &txid, Some(sat), Some(TransactionType::Incoming));
+
+    // Same wallet, but the primary server is down and the session fails over to electrs
+    let temp_dir = TempDir::new().unwrap();
+    let mut new_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", temp_dir.path().display());
+        let closed_port = TcpListener::bind(("127.0.0.1", 0)).unwrap().local_addr().unwrap();
+        network.electrum_url = Some(closed_port.to_string());
+        network.electrum_servers = Some(vec![test_session.electrs.electrum_url.clone()]);
+        ElectrumSession::new(network).unwrap()
+    };
+    new_session.auth_handler_login(&test_session.credentials);
+    new_session.wait_account_n_txs(0, 1);
+
+    let opt = GetBalanceOpt {
+        subaccount: 0,
+        num_confs: 0,
+        confidential_utxos_only: None,
+    };
+    let balance = new_session.get_balance(&opt).unwrap();
+    assert_eq!(*balance.get("btc").unwrap() as u64, sat);
+    test_session.stop();
+}
+
+/// A TCP proxy to `upstream` whose connections can be killed, returning its address and the
+/// open connections
+fn killable_proxy(upstream: &str) -> (String, Arc<Mutex<Vec<TcpStream>>>) {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let connections = Arc::new(Mutex::new(vec![]));
+    let upstream = upstream.to_string();
+    let conns = connections.clone();
+    thread::spawn(move || {
+        for client in listener.incoming().flatten() {
+            let server = TcpStream::connect(&upstream).unwrap();
+            conns
+                .lock()
+                .unwrap()
+                .extend([client.try_clone().unwrap(), server.try_clone().unwrap()]);
+            for (mut from, mut to) in
+                [(client.try_clone().unwrap(), server.try_clone().unwrap()), (server, client)]
+            {
+                thread::spawn(move || {
+                    let _ = std::io::copy(&mut from, &mut to);
+                    let _ = to.shutdown(Shutdown::Both);
+                });
+            }
+        }
+    });
+    (address, connections)
+}
+
+#[test]
+fn electrum_reconnect() {
+    let mut test_session = TestSession::new(false, |_| ());
+
+    // Same wallet, connected through a proxy to electrs
+    let (proxy, connections) = killable_proxy(&test_session.electrs.electrum_url);
+    let temp_dir = TempDir::new().unwrap();
+    let mut new_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", temp_dir.path().display());
+        network.electrum_url = Some(proxy);
+        ElectrumSession::new(network).unwrap()
+    };
+    new_session.auth_handler_login(&test_session.credentials);
+
+    let address = test_session.get_receive_address(0);
+    test_session.node_sendtoaddress(&address.address, 94_000, None);
+    new_session.wait_account_n_txs(0, 1);
+
+    // The subscriptions are lost with the connection, the syncer must subscribe again
+    for connection in connections.lock().unwrap().drain(..) {
+        let _ = connection.shutdown(Shutdown::Both);
+    }
+    let address = test_session.get_receive_address(0);
+    test_session.node_sendtoaddress(&address.address, 93_000, None);
+    new_session.wait_account_n_txs(0, 2);
+
+    new_session.disconnect().unwrap();
+    test_session.stop();
+}
+
+#[test]
+fn liquid_watch_only() {
+    let mut test_session = TestSession::new(true, |_| ());
+    let sat = 95_000;
+    let address = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&address.address, sat, None);
+    test_session.wait_tx(vec![0], &txid, None, None);
+    let account = test_session.session.get_subaccount(0).unwrap();
+    let master_blinding_key =
+        test_session.session.get_master_blinding_key().unwrap().master_blinding_key;
+
+    // Same wallet, from the account descriptor and the master blinding key
+    let temp_dir = TempDir::new().unwrap();
+    let mut network = test_session.network_parameters().clone();
+    network.state_dir = format!("{}", temp_dir.path().display());
+    let mut new_session = ElectrumSession::new(network.clone()).unwrap();
+    new_session.connect(&serde_json::to_value(&network).unwrap()).unwrap();
+    let mut credentials = WatchOnlyCredentials {
+        core_descriptors: account.core_descriptors.clone(),
+        ..Default::default()
+    };
+    assert!(new_session.login_watch_only(credentials.clone()).is_err(), "blinding key required");
+    credentials.master_blinding_key = master_blinding_key;
+    new_session.login_watch_only(credentials).unwrap();
+    new_session.wait_account_n_txs(0, 1);
+
+    let opt = GetBalanceOpt {
+        subaccount: 0,
+        num_confs: 0,
+        confidential_utxos_only: None,
+    };
+    let balance = new_session.get_balance(&opt).unwrap();
+    assert_eq!(balance.values().sum::<i64>() as u64, sat);
+    let address = new_session
+        .get_receive_address(&GetAddressOpt {
+            subaccount: 0,
+            address_type: None,
+            is_internal: None,
+        })
+        .unwrap();
+    assert!(address.blinding_key.is_some());
+
+    new_session.disconnect().unwrap();
+    test_session.stop();
+}
+
+#[test]
+fn liquid_watch_only_ct_descriptor() {
+    let mut test_session = TestSession::new(true, |_| ());
+    let sat = 95_000;
+    let address = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&address.address, sat, None);
+    test_session.wait_tx(vec![0], &txid, None, None);
+    let account = test_session.session.get_subaccount(0).unwrap();
+    let master_blinding_key =
+        test_session.session.get_master_blinding_key().unwrap().master_blinding_key.unwrap();
+
+    // Same wallet, from an ELIP-150 descriptor with the SLIP-77 key, the second half of the
+    // master blinding key
+    let (desc, _) = account.core_descriptors[0].split_once('#').unwrap();
+    let slip77 = bitcoin::hashes::hex::ToHex::to_hex(&master_blinding_key.0[32..]);
+    let temp_dir = TempDir::new().unwrap();
+    let mut network = test_session.network_parameters().clone();
+    network.state_dir = format!("{}", temp_dir.path().display());
+    let mut new_session = ElectrumSession::new(network.clone()).unwrap();
+    new_session.connect(&serde_json::to_value(&network).unwrap()).unwrap();
+    let credentials = WatchOnlyCredentials {
+        core_descriptors: vec![format!("ct(slip77({}),el{})", slip77, desc)],
+        ..Default::default()
+    };
+    new_session.login_watch_only(credentials).unwrap();
+    new_session.wait_account_n_txs(0, 1);
+
+    let opt = GetBalanceOpt {
+        subaccount: 0,
+        num_confs: 0,
+        confidential_utxos_only: None,
+    };
+    let balance = new_session.get_balance(&opt).unwrap();
+    assert_eq!(balance.values().sum::<i64>() as u64, sat);
+    let address = new_session
+        .get_receive_address(&GetAddressOpt {
+            subaccount: 0,
+            address_type: None,
+            is_internal: None,
+        })
+        .unwrap();
+    assert!(address.blinding_key.is_some());
+
+    new_session.disconnect().unwrap();
+    test_session.stop();
+}
+
 #[test]
 fn labels() {
     // Create a session and two accounts
@@ -1331,6 +2103,7 @@ fn labels() {
         address: test_session.get_receive_address(account2.account_num).address,
         satoshi: sat,
         asset_id: None,
+        ..Default::default()
     });
     create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
     create_opt.memo = Some("Foo, Bar Foo".into());
@@ -1361,6 +2134,7 @@ fn labels() {
         address: test_session.get_receive_address(account2.account_num).address,
         satoshi: sat,
         asset_id: None,
+        ..Default::default()
     });
     create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
     create_opt.memo = Some("Foo, Bar Foo".into());
@@ -1378,6 +2152,41 @@ fn labels() {
     assert_eq!(test_session.get_tx_from_list(account1.account_num, &txid).memo, "");
     assert_eq!(test_session.get_tx_from_list(account2.account_num, &txid).memo, "");
 
+    // Imported address and output labels are returned with the addresses and the utxos
+    let utxo = test_session.utxos(account1.account_num).0["btc"][0].clone();
+    let jsonl = format!(
+        r#"{{"type":"addr","ref":"{}","label":"address"}}
+{{"type":"output","ref":"{}:{}","label":"output"}}"#,
+        acc1_address.address, utxo.txhash, utxo.pt_idx
+    );
+    let opt = ImportLabelsOpt {
+        labels: jsonl,
+    };
+    assert_eq!(test_session.session.import_labels(&opt).unwrap().imported, 2);
+    let opt = GetPreviousAddressesOpt {
+        subaccount: account1.account_num,
+        last_pointer: None,
+        is_internal: false,
+        count: 10,
+    };
+    let previous_addresses = test_session.session.get_previous_addresses(&opt).unwrap();
+    for previous_address in previous_addresses.list {
+        let expected = if previous_address.address == acc1_address.address {
+            "address"
+        } else {
+            ""
+        };
+        assert_eq!(previous_address.label, expected);
+    }
+    for unspent in test_session.utxos(account1.account_num).0["btc"].iter() {
+        let expected = if (&unspent.txhash, unspent.pt_idx) == (&utxo.txhash, utxo.pt_idx) {
+            "output"
+        } else {
+            ""
+        };
+        assert_eq!(unspent.label, expected);
+    }
+
     test_session.stop();
 }
 
@@ -1406,6 +2215,7 @@ fn rbf() {
         address: dest_address,
         satoshi: 50000,
         asset_id: None,
+        ..Default::default()
     });
     create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
     create_opt.fee_rate = Some(25000);
@@ -1643,6 +2453,9 @@ fn spv_cross_validation_session() {
     let txitem = test_session1.get_tx_from_list(0, &txid);
     assert_eq!(txitem.block_height, 0);
     assert_eq!(txitem.spv_verified, "unconfirmed");
+    let reorg = test_session1.wait_reorg_ntf(&txid);
+    assert_eq!(reorg["fork_height"].as_u64(), Some(121));
+    assert_eq!(reorg["subaccounts"].as_array().unwrap(), &[0]);
     info!("reorged session1 into session2, tx is unconfirmed again");
 
     // Re-confirm the tx and then re-fork the chain, such that the tx is confirmed before the forking point
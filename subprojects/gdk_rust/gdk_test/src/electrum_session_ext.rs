@@ -71,6 +71,9 @@ impl ElectrumSessionExt for ElectrumSession {
             TestSigner::new(credentials, self.network.bip32_network(), self.network.liquid);
 
         for script_type in ScriptType::types() {
+            if script_type.is_taproot() && self.network.liquid {
+                continue;
+            }
             loop {
                 let opt = GetNextAccountOpt {
                     script_type: *script_type,
@@ -84,6 +87,7 @@ impl ElectrumSessionExt for ElectrumSession {
                 let opt = DiscoverAccountOpt {
                     script_type: *script_type,
                     xpub,
+                    gap_limit: None,
                 };
                 if self.discover_subaccount(opt).unwrap() {
                     let opt = CreateAccountOpt {
@@ -62,7 +62,7 @@ impl TestSession {
             ]);
             "liquidregtest"
         } else {
-            args.extend_from_slice(&["-regtest"]);
+            args.extend_from_slice(&["-regtest", "-txindex=1"]);
             "regtest"
         };
         let mut conf = electrsd::bitcoind::Conf::default();
@@ -91,7 +91,7 @@ impl TestSession {
         let mut conf = electrsd::Conf::default();
         conf.args = args;
         conf.view_stderr = is_debug;
-        conf.http_enabled = false;
+        conf.http_enabled = true;
         conf.network = network;
 
         let electrs = electrsd::ElectrsD::with_conf(&electrs, &node, &conf).unwrap();
@@ -308,6 +308,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi: 0,
             asset_id: asset_id.clone().or(self.asset_id()),
+            ..Default::default()
         });
         create_opt.send_all = true;
         let tx = self.session.create_transaction(&mut create_opt).unwrap();
@@ -351,6 +352,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi,
             asset_id: asset.clone().or(self.asset_id()),
+            ..Default::default()
         });
         create_opt.memo = memo;
         create_opt.utxos = utils::convertutxos(&unspent_outputs.unwrap_or_else(|| self.utxos(0)));
@@ -367,7 +369,7 @@ impl TestSession {
         let num_used_utxos = tx.used_utxos.len();
         match create_opt.utxo_strategy {
             UtxoStrategy::Manual => assert_eq!(num_used_utxos, num_utxos),
-            UtxoStrategy::Default => assert!(num_used_utxos > 0 && num_used_utxos <= num_utxos),
+            _ => assert!(num_used_utxos > 0 && num_used_utxos <= num_utxos),
         }
         let signed_tx = self.session.sign_transaction(&tx).unwrap();
         self.check_fee_rate(fee_rate, &signed_tx, MAX_FEE_PERCENT_DIFF);
@@ -431,6 +433,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi,
             asset_id: asset.clone().or(self.asset_id()),
+            ..Default::default()
         });
         create_opt.utxos = utils::convertutxos(&self.utxos(create_opt.subaccount));
         let tx = self.session.create_transaction(&mut create_opt).unwrap();
@@ -522,6 +525,7 @@ impl TestSession {
                 address: address.to_string(),
                 satoshi: amount,
                 asset_id,
+                ..Default::default()
             });
         }
         create_opt.utxos = utils::convertutxos(&self.utxos(create_opt.subaccount));
@@ -596,6 +600,7 @@ impl TestSession {
             address: node_address.clone(),
             satoshi: init_sat, // not enough to pay the fee with confidential utxos only
             asset_id: self.asset_id(),
+            ..Default::default()
         });
         create_opt.utxos = utils::convertutxos(&self.utxos(create_opt.subaccount));
         create_opt.confidential_utxos_only = true;
@@ -653,6 +658,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi,
             asset_id: self.asset_id(),
+            ..Default::default()
         });
         create_opt.utxos = utils::convertutxos(&self.utxos(create_opt.subaccount));
         let tx = self.session.create_transaction(&mut create_opt).unwrap();
@@ -688,6 +694,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi,
             asset_id,
+            ..Default::default()
         });
         create_opt
     }
@@ -1195,6 +1202,20 @@ impl TestSession {
         }
     }
 
+    /// wait for a reorg notification involving the txid, returning it
+    pub fn wait_reorg_ntf(&self, txid: &str) -> Value {
+        for _ in 0..60 {
+            let events = self.session.filter_events("reorg");
+            if let Some(event) = events.iter().find(|e| {
+                e["reorg"]["txhashes"].as_array().unwrap().iter().any(|t| t.as_str() == Some(txid))
+            }) {
+                return event["reorg"].clone();
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+        panic!("timeout waiting for reorg notification for tx {}", txid);
+    }
+
     pub fn test_signer(&self) -> TestSigner {
         TestSigner::new(
             &self.credentials,